@@ -0,0 +1,66 @@
+/* generated by intcode_ffi::header(), do not edit */
+
+#ifndef INTCODE_H
+#define INTCODE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct IntcodeMachine IntcodeMachine;
+
+#define INTCODE_STATUS_OK 0
+#define INTCODE_STATUS_OUTPUT 0
+#define INTCODE_STATUS_NEED_INPUT 1
+#define INTCODE_STATUS_HALTED 2
+#define INTCODE_STATUS_STEP_LIMIT 3
+#define INTCODE_STATUS_ERROR (-1)
+#define INTCODE_STATUS_NULL (-2)
+#define INTCODE_ERROR_NONE 0
+#define INTCODE_ERROR_INVALID_OPCODE 1
+#define INTCODE_ERROR_INVALID_MODE 2
+#define INTCODE_ERROR_IMMEDIATE_WRITE 3
+#define INTCODE_ERROR_INVALID_ADDRESS 4
+#define INTCODE_ERROR_ARITHMETIC_OVERFLOW 5
+
+/* copies the program; NULL if program is NULL and len isn't 0 */
+IntcodeMachine *intcode_create(const int64_t *program, size_t len);
+
+/* an independent copy of the machine's whole state; NULL for a NULL machine */
+IntcodeMachine *intcode_snapshot(const IntcodeMachine *machine);
+
+/* frees the machine; does nothing for a NULL machine */
+void intcode_destroy(IntcodeMachine *machine);
+
+/* queues value for the machine's next input; INTCODE_STATUS_OK, or INTCODE_STATUS_NULL for a NULL machine */
+int32_t intcode_push_input(IntcodeMachine *machine, int64_t value);
+
+/* runs until an output, a read with no input queued, a halt or an error, and returns the INTCODE_STATUS_* for which; INTCODE_STATUS_ERROR again once the machine has faulted, INTCODE_STATUS_NULL for a NULL machine */
+int32_t intcode_run(IntcodeMachine *machine);
+
+/* as intcode_run, but returns INTCODE_STATUS_STEP_LIMIT once the machine has taken step_limit steps in all */
+int32_t intcode_run_limited(IntcodeMachine *machine, size_t step_limit);
+
+/* pops the oldest unread output into *value; 1 if there was one, 0 if not, INTCODE_STATUS_NULL if machine or value is NULL */
+int32_t intcode_read_output(IntcodeMachine *machine, int64_t *value);
+
+/* copies up to capacity words of memory; returns the full memory length, 0 for a NULL machine */
+size_t intcode_read_memory(const IntcodeMachine *machine, int64_t *buffer, size_t capacity);
+
+/* the address of the next instruction to run; 0 for a NULL machine */
+size_t intcode_instr_ptr(const IntcodeMachine *machine);
+
+/* the INTCODE_ERROR_* code of the fault that stopped the machine, and its address in *instr_ptr unless that is NULL; INTCODE_ERROR_NONE if it hasn't faulted, INTCODE_STATUS_NULL for a NULL machine */
+int32_t intcode_last_error(const IntcodeMachine *machine, size_t *instr_ptr);
+
+/* the opcode, instruction or address the last fault was about; 0 if it has none or for a NULL machine */
+int64_t intcode_error_word(const IntcodeMachine *machine);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,224 @@
+use super::Word;
+use super::machine::ErrorKind;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Opcode {
+    Add,
+    Mul,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    AdjustBase,
+    Halt
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Position,
+    Immediate,
+    Relative
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub modes: [Mode; 3]
+}
+
+pub const ALL_OPCODES: [Opcode; 10] = [
+    Opcode::Add, Opcode::Mul, Opcode::Input, Opcode::Output,
+    Opcode::JumpIfTrue, Opcode::JumpIfFalse, Opcode::LessThan, Opcode::Equals,
+    Opcode::AdjustBase, Opcode::Halt
+];
+
+impl Opcode {
+    pub fn from_code(code: Word) -> Option<Opcode> {
+        match code {
+            1 => Some(Opcode::Add),
+            2 => Some(Opcode::Mul),
+            3 => Some(Opcode::Input),
+            4 => Some(Opcode::Output),
+            5 => Some(Opcode::JumpIfTrue),
+            6 => Some(Opcode::JumpIfFalse),
+            7 => Some(Opcode::LessThan),
+            8 => Some(Opcode::Equals),
+            9 => Some(Opcode::AdjustBase),
+            99 => Some(Opcode::Halt),
+            _ => None
+        }
+    }
+
+    pub fn code(self) -> Word {
+        match self {
+            Opcode::Add => 1,
+            Opcode::Mul => 2,
+            Opcode::Input => 3,
+            Opcode::Output => 4,
+            Opcode::JumpIfTrue => 5,
+            Opcode::JumpIfFalse => 6,
+            Opcode::LessThan => 7,
+            Opcode::Equals => 8,
+            Opcode::AdjustBase => 9,
+            Opcode::Halt => 99
+        }
+    }
+
+    pub fn param_count(self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Mul | Opcode::LessThan | Opcode::Equals => 3,
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => 2,
+            Opcode::Input | Opcode::Output | Opcode::AdjustBase => 1,
+            Opcode::Halt => 0
+        }
+    }
+
+    // index of the parameter that is written to, if any
+    pub fn dest_param(self) -> Option<usize> {
+        match self {
+            Opcode::Add | Opcode::Mul | Opcode::LessThan | Opcode::Equals => Some(2),
+            Opcode::Input => Some(0),
+            _ => None
+        }
+    }
+
+    pub fn is_jump(self) -> bool {
+        self == Opcode::JumpIfTrue || self == Opcode::JumpIfFalse
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Add => "add",
+            Opcode::Mul => "mul",
+            Opcode::Input => "in",
+            Opcode::Output => "out",
+            Opcode::JumpIfTrue => "jt",
+            Opcode::JumpIfFalse => "jf",
+            Opcode::LessThan => "lt",
+            Opcode::Equals => "eq",
+            Opcode::AdjustBase => "arb",
+            Opcode::Halt => "hlt"
+        }
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Opcode> {
+        ALL_OPCODES.iter().cloned().find(|op| op.mnemonic() == mnemonic)
+    }
+}
+
+impl Mode {
+    pub fn from_code(code: Word) -> Option<Mode> {
+        match code {
+            0 => Some(Mode::Position),
+            1 => Some(Mode::Immediate),
+            2 => Some(Mode::Relative),
+            _ => None
+        }
+    }
+
+    pub fn code(self) -> Word {
+        match self {
+            Mode::Position => 0,
+            Mode::Immediate => 1,
+            Mode::Relative => 2
+        }
+    }
+}
+
+impl Instruction {
+    pub fn new(opcode: Opcode, modes: [Mode; 3]) -> Instruction {
+        Instruction {
+            opcode: opcode,
+            modes: modes
+        }
+    }
+
+    pub fn decode(word: Word) -> Result<Instruction, ErrorKind> {
+        if word < 0 {
+            return Err(ErrorKind::InvalidOpcode(word));
+        }
+
+        let opcode = Opcode::from_code(word % 100)
+            .ok_or(ErrorKind::InvalidOpcode(word))?;
+
+        // mode digits beyond the parameters an opcode takes must be zero
+        let mut mode_digits = word / 100;
+        let mut modes = [Mode::Position; 3];
+
+        for (param_i, slot) in modes.iter_mut().enumerate() {
+            let mode = Mode::from_code(mode_digits % 10)
+                .ok_or(ErrorKind::InvalidMode(word))?;
+
+            if param_i >= opcode.param_count() && mode != Mode::Position {
+                return Err(ErrorKind::InvalidMode(word));
+            }
+
+            *slot = mode;
+            mode_digits /= 10;
+        }
+
+        if mode_digits != 0 {
+            return Err(ErrorKind::InvalidMode(word));
+        }
+
+        if let Some(dest) = opcode.dest_param() {
+            if modes[dest] == Mode::Immediate {
+                return Err(ErrorKind::ImmediateWrite);
+            }
+        }
+
+        Ok(Instruction::new(opcode, modes))
+    }
+
+    pub fn encode(&self) -> Word {
+        let mut word = self.opcode.code();
+
+        for (param_i, mode) in self.modes.iter().enumerate() {
+            word += mode.code() * 100 * (10 as Word).pow(param_i as u32);
+        }
+
+        word
+    }
+
+    // an instruction always holds at least its opcode, so it is never empty
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.opcode.param_count() + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        let instr = Instruction::decode(1002).unwrap();
+
+        assert_eq!(instr.opcode, Opcode::Mul);
+        assert_eq!(instr.modes, [Mode::Position, Mode::Immediate, Mode::Position]);
+
+        let instr = Instruction::decode(21101).unwrap();
+
+        assert_eq!(instr.modes, [Mode::Immediate, Mode::Immediate, Mode::Relative]);
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(Instruction::decode(42), Err(ErrorKind::InvalidOpcode(42)));
+        assert_eq!(Instruction::decode(-1), Err(ErrorKind::InvalidOpcode(-1)));
+        assert_eq!(Instruction::decode(301), Err(ErrorKind::InvalidMode(301)));
+        assert_eq!(Instruction::decode(1104), Err(ErrorKind::InvalidMode(1104)));
+        assert_eq!(Instruction::decode(11101), Err(ErrorKind::ImmediateWrite));
+        assert_eq!(Instruction::decode(103), Err(ErrorKind::ImmediateWrite));
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        for &word in [1, 2, 99, 1002, 21101, 204, 1105, 2207, 109].iter() {
+            assert_eq!(Instruction::decode(word).unwrap().encode(), word);
+        }
+    }
+}
@@ -0,0 +1,10 @@
+// decoding and executing Intcode, on core and alloc alone so it builds for
+// targets without std. loading programs from files lives with the puzzles
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+pub mod instruction;
+pub mod machine;
+
+pub type Word = i64;
@@ -0,0 +1,317 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::Word;
+use super::instruction::{ Instruction, Mode, Opcode };
+
+// writes past this address are treated as a fault rather than grown into
+pub const MEMORY_LIMIT: usize = 1 << 24;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ErrorKind {
+    InvalidOpcode(Word),
+    InvalidMode(Word),
+    ImmediateWrite,
+    InvalidAddress(Word),
+    ArithmeticOverflow
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct VmError {
+    pub kind: ErrorKind,
+    pub instr_ptr: usize
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Event {
+    Output(Word),
+    NeedInput,
+    Halted
+}
+
+#[derive(Clone, Debug)]
+pub struct Machine {
+    memory: Vec<Word>,
+    instr_ptr: usize,
+    relative_base: Word,
+    input_pool: VecDeque<Word>,
+    halted: bool,
+    steps: usize
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::InvalidOpcode(word) => write!(f, "invalid opcode {}", word),
+            ErrorKind::InvalidMode(word) => write!(f, "invalid parameter mode in {}", word),
+            ErrorKind::ImmediateWrite => write!(f, "write parameter in immediate mode"),
+            ErrorKind::InvalidAddress(addr) => write!(f, "invalid address {}", addr),
+            ErrorKind::ArithmeticOverflow => write!(f, "arithmetic overflow")
+        }
+    }
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.kind, self.instr_ptr)
+    }
+}
+
+impl Machine {
+    pub fn new(program: Vec<Word>) -> Machine {
+        Machine {
+            memory: program,
+            instr_ptr: 0,
+            relative_base: 0,
+            input_pool: VecDeque::new(),
+            halted: false,
+            steps: 0
+        }
+    }
+
+    pub fn push_input(&mut self, value: Word) {
+        self.input_pool.push_back(value);
+    }
+
+    pub fn extend_input(&mut self, values: &[Word]) {
+        self.input_pool.extend(values.iter().cloned());
+    }
+
+    pub fn pending_input(&self) -> usize {
+        self.input_pool.len()
+    }
+
+    pub fn memory(&self) -> &[Word] {
+        &self.memory
+    }
+
+    pub fn instr_ptr(&self) -> usize {
+        self.instr_ptr
+    }
+
+    pub fn relative_base(&self) -> Word {
+        self.relative_base
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn read(&self, addr: Word) -> Result<Word, ErrorKind> {
+        let addr = Machine::check_address(addr)?;
+
+        // memory beyond the program reads as zero
+        Ok(self.memory.get(addr).cloned().unwrap_or(0))
+    }
+
+    pub fn write(&mut self, addr: Word, value: Word) -> Result<(), ErrorKind> {
+        let addr = Machine::check_address(addr)?;
+
+        if addr >= self.memory.len() {
+            self.memory.resize(addr + 1, 0);
+        }
+
+        self.memory[addr] = value;
+
+        Ok(())
+    }
+
+    pub fn current_instruction(&self) -> Result<Instruction, ErrorKind> {
+        Instruction::decode(self.read(self.instr_ptr as Word)?)
+    }
+
+    // runs until the machine produces output, blocks on input or halts
+    pub fn run(&mut self) -> Result<Event, VmError> {
+        loop {
+            if let Some(event) = self.step()? {
+                return Ok(event);
+            }
+        }
+    }
+
+    // executes a single instruction, returning any event it raised
+    pub fn step(&mut self) -> Result<Option<Event>, VmError> {
+        if self.halted {
+            return Ok(Some(Event::Halted));
+        }
+
+        let i_ptr = self.instr_ptr;
+
+        self.execute().map_err(|kind| VmError { kind: kind, instr_ptr: i_ptr })
+    }
+
+    fn execute(&mut self) -> Result<Option<Event>, ErrorKind> {
+        let instr = self.current_instruction()?;
+        let mut next_ptr = self.instr_ptr + instr.len();
+        let mut event = None;
+
+        match instr.opcode {
+            Opcode::Add => {
+                let result = self.load(&instr, 0)?.checked_add(self.load(&instr, 1)?);
+                self.store(&instr, 2, result.ok_or(ErrorKind::ArithmeticOverflow)?)?;
+            },
+            Opcode::Mul => {
+                let result = self.load(&instr, 0)?.checked_mul(self.load(&instr, 1)?);
+                self.store(&instr, 2, result.ok_or(ErrorKind::ArithmeticOverflow)?)?;
+            },
+            Opcode::Input => {
+                let value = match self.input_pool.pop_front() {
+                    Some(value) => value,
+                    None => return Ok(Some(Event::NeedInput))
+                };
+
+                self.store(&instr, 0, value)?;
+            },
+            Opcode::Output => {
+                event = Some(Event::Output(self.load(&instr, 0)?));
+            },
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                let is_zero = self.load(&instr, 0)? == 0;
+
+                if is_zero == (instr.opcode == Opcode::JumpIfFalse) {
+                    next_ptr = Machine::check_address(self.load(&instr, 1)?)?;
+                }
+            },
+            Opcode::LessThan => {
+                let result = self.load(&instr, 0)? < self.load(&instr, 1)?;
+                self.store(&instr, 2, result as Word)?;
+            },
+            Opcode::Equals => {
+                let result = self.load(&instr, 0)? == self.load(&instr, 1)?;
+                self.store(&instr, 2, result as Word)?;
+            },
+            Opcode::AdjustBase => {
+                let offset = self.load(&instr, 0)?;
+                self.relative_base = self.relative_base.checked_add(offset)
+                    .ok_or(ErrorKind::ArithmeticOverflow)?;
+            },
+            Opcode::Halt => {
+                self.halted = true;
+                self.steps += 1;
+
+                return Ok(Some(Event::Halted));
+            }
+        }
+
+        self.instr_ptr = next_ptr;
+        self.steps += 1;
+
+        Ok(event)
+    }
+
+    fn param(&self, param_i: usize) -> Result<Word, ErrorKind> {
+        self.read((self.instr_ptr + 1 + param_i) as Word)
+    }
+
+    fn param_address(&self, instr: &Instruction, param_i: usize) -> Result<Word, ErrorKind> {
+        let raw = self.param(param_i)?;
+
+        match instr.modes[param_i] {
+            Mode::Position => Ok(raw),
+            Mode::Relative => self.relative_base.checked_add(raw).ok_or(ErrorKind::InvalidAddress(raw)),
+            Mode::Immediate => Err(ErrorKind::ImmediateWrite)
+        }
+    }
+
+    fn load(&self, instr: &Instruction, param_i: usize) -> Result<Word, ErrorKind> {
+        match instr.modes[param_i] {
+            Mode::Immediate => self.param(param_i),
+            _ => self.read(self.param_address(instr, param_i)?)
+        }
+    }
+
+    fn store(&mut self, instr: &Instruction, param_i: usize, value: Word) -> Result<(), ErrorKind> {
+        let addr = self.param_address(instr, param_i)?;
+
+        self.write(addr, value)
+    }
+
+    fn check_address(addr: Word) -> Result<usize, ErrorKind> {
+        if addr < 0 || addr as usize >= MEMORY_LIMIT {
+            return Err(ErrorKind::InvalidAddress(addr));
+        }
+
+        Ok(addr as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_to_halt(program: Vec<Word>, inputs: &[Word]) -> (Vec<Word>, Machine) {
+        let mut machine = Machine::new(program);
+        machine.extend_input(inputs);
+
+        let mut outputs = Vec::new();
+
+        loop {
+            match machine.run().unwrap() {
+                Event::Output(value) => outputs.push(value),
+                Event::Halted => break,
+                Event::NeedInput => panic!("ran out of input")
+            }
+        }
+
+        (outputs, machine)
+    }
+
+    #[test]
+    fn day2_example() {
+        let (_, machine) = run_to_halt(vec!(1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50), &[]);
+
+        assert_eq!(machine.memory()[0], 3500);
+    }
+
+    #[test]
+    fn day5_compare() {
+        let program = vec!(3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8);
+
+        assert_eq!(run_to_halt(program.to_vec(), &[8]).0, vec!(1));
+        assert_eq!(run_to_halt(program, &[7]).0, vec!(0));
+    }
+
+    #[test]
+    fn day9_quine() {
+        let program = vec!(109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99);
+
+        assert_eq!(run_to_halt(program.to_vec(), &[]).0, program);
+    }
+
+    #[test]
+    fn day9_large_numbers() {
+        let (outputs, _) = run_to_halt(vec!(104, 1125899906842624, 99), &[]);
+
+        assert_eq!(outputs, vec!(1125899906842624));
+    }
+
+    #[test]
+    fn blocks_on_input() {
+        let mut machine = Machine::new(vec!(3, 5, 4, 5, 99, 0));
+
+        assert_eq!(machine.run(), Ok(Event::NeedInput));
+        assert_eq!(machine.instr_ptr(), 0);
+
+        machine.push_input(42);
+
+        assert_eq!(machine.run(), Ok(Event::Output(42)));
+        assert_eq!(machine.run(), Ok(Event::Halted));
+        assert_eq!(machine.run(), Ok(Event::Halted));
+    }
+
+    #[test]
+    fn reports_errors() {
+        let mut machine = Machine::new(vec!(1, 0, 0, 0, 42));
+
+        let expected = VmError { kind: ErrorKind::InvalidOpcode(42), instr_ptr: 4 };
+        assert_eq!(machine.run(), Err(expected));
+
+        let mut machine = Machine::new(vec!(1, -3, 0, 0, 99));
+        assert_eq!(machine.run().unwrap_err().kind, ErrorKind::InvalidAddress(-3));
+    }
+}
@@ -0,0 +1,7 @@
+// prints the C header for the library:
+//
+//   cargo run --manifest-path intcode-ffi/Cargo.toml --bin intcode-header > include/intcode.h
+
+fn main() {
+    print!("{}", intcode_ffi::header());
+}
@@ -0,0 +1,549 @@
+use std::collections::VecDeque;
+use std::ptr;
+use std::slice;
+use std::vec::Vec;
+
+use intcode_core::Word;
+use intcode_core::machine::{ ErrorKind, Event, Machine, VmError };
+
+// a C interface to the reference machine, for harnesses outside Rust, built
+// as a cdylib. every machine lives behind an opaque pointer from
+// intcode_create or intcode_snapshot and must be released with
+// intcode_destroy.
+//
+// include/intcode.h is generated from this file by header(): the constants
+// and exported signatures below are read back out of the source, and the
+// `//` comment above each export becomes its comment in the header.
+
+pub const STATUS_OK: i32 = 0;
+pub const STATUS_OUTPUT: i32 = 0;
+pub const STATUS_NEED_INPUT: i32 = 1;
+pub const STATUS_HALTED: i32 = 2;
+pub const STATUS_STEP_LIMIT: i32 = 3;
+pub const STATUS_ERROR: i32 = -1;
+pub const STATUS_NULL: i32 = -2;
+
+pub const ERROR_NONE: i32 = 0;
+pub const ERROR_INVALID_OPCODE: i32 = 1;
+pub const ERROR_INVALID_MODE: i32 = 2;
+pub const ERROR_IMMEDIATE_WRITE: i32 = 3;
+pub const ERROR_INVALID_ADDRESS: i32 = 4;
+pub const ERROR_ARITHMETIC_OVERFLOW: i32 = 5;
+
+const SOURCE: &str = include_str!("lib.rs");
+
+#[derive(Clone)]
+pub struct IntcodeMachine {
+    machine: Machine,
+    outputs: VecDeque<Word>,
+    error: Option<VmError>
+}
+
+fn error_code(kind: ErrorKind) -> i32 {
+    match kind {
+        ErrorKind::InvalidOpcode(_) => ERROR_INVALID_OPCODE,
+        ErrorKind::InvalidMode(_) => ERROR_INVALID_MODE,
+        ErrorKind::ImmediateWrite => ERROR_IMMEDIATE_WRITE,
+        ErrorKind::InvalidAddress(_) => ERROR_INVALID_ADDRESS,
+        ErrorKind::ArithmeticOverflow => ERROR_ARITHMETIC_OVERFLOW
+    }
+}
+
+fn into_handle(state: IntcodeMachine) -> *mut IntcodeMachine {
+    Box::into_raw(Box::new(state))
+}
+
+// an exported function as declared in this file
+#[derive(Clone, PartialEq, Debug)]
+pub struct Export {
+    pub comment: String,
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub ret: Option<String>
+}
+
+// the C spelling of a parameter or return type; anything without a fixed
+// mapping stops the header being written rather than guessed at
+pub fn c_type(rust: &str) -> String {
+    let rust = rust.trim();
+
+    if let Some(pointee) = rust.strip_prefix("*const ") {
+        return format!("const {} *", c_type(pointee));
+    }
+    if let Some(pointee) = rust.strip_prefix("*mut ") {
+        return format!("{} *", c_type(pointee));
+    }
+
+    match rust {
+        "i32" => "int32_t",
+        "i64" => "int64_t",
+        "usize" => "size_t",
+        "IntcodeMachine" => "IntcodeMachine",
+        _ => panic!("no C type for `{}`", rust)
+    }.to_string()
+}
+
+// `int64_t *` and `value` make `int64_t *value`
+fn declare(c_type: &str, name: &str) -> String {
+    if c_type.ends_with('*') {
+        format!("{}{}", c_type, name)
+    } else {
+        format!("{} {}", c_type, name)
+    }
+}
+
+fn parse_export(line: &str, comment: String) -> Export {
+    let signature = line.trim().strip_prefix("pub unsafe extern \"C\" fn ")
+        .and_then(|rest| rest.strip_suffix(" {"))
+        .unwrap_or_else(|| panic!("exports go on one line: {}", line));
+
+    let open = signature.find('(').unwrap();
+    let close = signature.rfind(')').unwrap();
+
+    let params = signature[open + 1..close].split(',')
+        .map(str::trim)
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (name, ty) = param.split_at(param.find(':').unwrap());
+            (name.trim().to_string(), ty[1..].trim().to_string())
+        })
+        .collect();
+
+    let ret = signature[close + 1..].trim().strip_prefix("->").map(|ret| ret.trim().to_string());
+
+    Export {
+        comment: comment,
+        name: signature[..open].trim().to_string(),
+        params: params,
+        ret: ret
+    }
+}
+
+// every `extern "C"` function in this file, in order, with the plain comment
+// lines just above its doc comment
+pub fn exports() -> Vec<Export> {
+    let mut exports = Vec::new();
+    let mut comment: Vec<&str> = Vec::new();
+
+    for line in SOURCE.lines().take_while(|&line| line != "#[cfg(test)]") {
+        let line = line.trim();
+
+        if line.starts_with("pub unsafe extern \"C\" fn ") {
+            exports.push(parse_export(line, comment.join(" ")));
+        }
+
+        if let Some(text) = line.strip_prefix("// ") {
+            comment.push(text);
+        } else if !line.starts_with("///") && !line.starts_with("#[") {
+            comment.clear();
+        }
+    }
+
+    exports
+}
+
+// `pub const NAME: i32 = value;` lines, as INTCODE_NAME and value
+fn constants() -> Vec<(String, String)> {
+    SOURCE.lines()
+        .filter_map(|line| line.strip_prefix("pub const "))
+        .map(|rest| {
+            let (name, rest) = rest.split_at(rest.find(':').unwrap());
+            let value = rest.split('=').nth(1).unwrap().trim().trim_end_matches(';');
+
+            (format!("INTCODE_{}", name), value.to_string())
+        })
+        .collect()
+}
+
+pub fn prototype(export: &Export) -> String {
+    let ret = export.ret.as_ref().map_or(String::from("void"), |ret| c_type(ret));
+
+    let params: Vec<String> = export.params.iter()
+        .map(|(name, ty)| declare(&c_type(ty), name))
+        .collect();
+
+    format!("{}({});", declare(&ret, &export.name), params.join(", "))
+}
+
+// the header for the functions below
+pub fn header() -> String {
+    let mut text = String::from("/* generated by intcode_ffi::header(), do not edit */\n\n");
+
+    text.push_str("#ifndef INTCODE_H\n#define INTCODE_H\n\n");
+    text.push_str("#include <stddef.h>\n#include <stdint.h>\n\n");
+    text.push_str("#ifdef __cplusplus\nextern \"C\" {\n#endif\n\n");
+    text.push_str("typedef struct IntcodeMachine IntcodeMachine;\n\n");
+
+    for (name, value) in constants() {
+        let value = if value.starts_with('-') { format!("({})", value) } else { value };
+        text.push_str(&format!("#define {} {}\n", name, value));
+    }
+
+    for export in exports() {
+        text.push('\n');
+
+        if !export.comment.is_empty() {
+            text.push_str(&format!("/* {} */\n", export.comment));
+        }
+        text.push_str(&prototype(&export));
+        text.push('\n');
+    }
+
+    text.push_str("\n#ifdef __cplusplus\n}\n#endif\n\n#endif\n");
+    text
+}
+
+// copies the program; NULL if program is NULL and len isn't 0
+/// # Safety
+/// `program` must point to `len` readable words, or be null with `len` 0.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_create(program: *const i64, len: usize) -> *mut IntcodeMachine {
+    let program = if len == 0 {
+        Vec::new()
+    } else if program.is_null() {
+        return ptr::null_mut();
+    } else {
+        slice::from_raw_parts(program, len).to_vec()
+    };
+
+    into_handle(IntcodeMachine {
+        machine: Machine::new(program),
+        outputs: VecDeque::new(),
+        error: None
+    })
+}
+
+// an independent copy of the machine's whole state; NULL for a NULL machine
+/// # Safety
+/// `machine` must be null or a live handle from this library.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_snapshot(machine: *const IntcodeMachine) -> *mut IntcodeMachine {
+    match machine.as_ref() {
+        Some(state) => into_handle(state.clone()),
+        None => ptr::null_mut()
+    }
+}
+
+// frees the machine; does nothing for a NULL machine
+/// # Safety
+/// `machine` must be null or a live handle from this library, and isn't
+/// usable afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_destroy(machine: *mut IntcodeMachine) {
+    if !machine.is_null() {
+        drop(Box::from_raw(machine));
+    }
+}
+
+// queues value for the machine's next input; INTCODE_STATUS_OK, or
+// INTCODE_STATUS_NULL for a NULL machine
+/// # Safety
+/// `machine` must be null or a live handle from this library.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_push_input(machine: *mut IntcodeMachine, value: i64) -> i32 {
+    match machine.as_mut() {
+        Some(state) => {
+            state.machine.push_input(value);
+            STATUS_OK
+        },
+        None => STATUS_NULL
+    }
+}
+
+// runs until an output, a read with no input queued, a halt or an error, and
+// returns the INTCODE_STATUS_* for which; INTCODE_STATUS_ERROR again once the
+// machine has faulted, INTCODE_STATUS_NULL for a NULL machine
+/// # Safety
+/// `machine` must be null or a live handle from this library.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_run(machine: *mut IntcodeMachine) -> i32 {
+    intcode_run_limited(machine, usize::MAX)
+}
+
+// as intcode_run, but returns INTCODE_STATUS_STEP_LIMIT once the machine has
+// taken step_limit steps in all
+/// # Safety
+/// `machine` must be null or a live handle from this library.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_run_limited(machine: *mut IntcodeMachine, step_limit: usize) -> i32 {
+    let state = match machine.as_mut() {
+        Some(state) => state,
+        None => return STATUS_NULL
+    };
+
+    if state.error.is_some() {
+        return STATUS_ERROR;
+    }
+
+    loop {
+        if state.machine.steps() >= step_limit {
+            return STATUS_STEP_LIMIT;
+        }
+
+        match state.machine.step() {
+            Ok(None) => (),
+            Ok(Some(Event::Output(value))) => {
+                state.outputs.push_back(value);
+                return STATUS_OUTPUT;
+            },
+            Ok(Some(Event::NeedInput)) => return STATUS_NEED_INPUT,
+            Ok(Some(Event::Halted)) => return STATUS_HALTED,
+            Err(err) => {
+                state.error = Some(err);
+                return STATUS_ERROR;
+            }
+        }
+    }
+}
+
+// pops the oldest unread output into *value; 1 if there was one, 0 if not,
+// INTCODE_STATUS_NULL if machine or value is NULL
+/// # Safety
+/// `machine` must be null or a live handle from this library, and `value`
+/// null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_read_output(machine: *mut IntcodeMachine, value: *mut i64) -> i32 {
+    let state = match machine.as_mut() {
+        Some(state) if !value.is_null() => state,
+        _ => return STATUS_NULL
+    };
+
+    match state.outputs.pop_front() {
+        Some(output) => {
+            *value = output;
+            1
+        },
+        None => 0
+    }
+}
+
+// copies up to capacity words of memory; returns the full memory length, 0
+// for a NULL machine
+/// # Safety
+/// `machine` must be null or a live handle from this library, and `buffer`
+/// must have room for `capacity` words unless `capacity` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_read_memory(machine: *const IntcodeMachine, buffer: *mut i64, capacity: usize) -> usize {
+    let memory = match machine.as_ref() {
+        Some(state) => state.machine.memory(),
+        None => return 0
+    };
+
+    let count = memory.len().min(capacity);
+    if count > 0 && !buffer.is_null() {
+        ptr::copy_nonoverlapping(memory.as_ptr(), buffer, count);
+    }
+
+    memory.len()
+}
+
+// the address of the next instruction to run; 0 for a NULL machine
+/// # Safety
+/// `machine` must be null or a live handle from this library.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_instr_ptr(machine: *const IntcodeMachine) -> usize {
+    machine.as_ref().map_or(0, |state| state.machine.instr_ptr())
+}
+
+// the INTCODE_ERROR_* code of the fault that stopped the machine, and its
+// address in *instr_ptr unless that is NULL; INTCODE_ERROR_NONE if it hasn't
+// faulted, INTCODE_STATUS_NULL for a NULL machine
+/// # Safety
+/// `machine` must be null or a live handle from this library, and `instr_ptr`
+/// null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_last_error(machine: *const IntcodeMachine, instr_ptr: *mut usize) -> i32 {
+    let err = match machine.as_ref() {
+        Some(state) => state.error,
+        None => return STATUS_NULL
+    };
+
+    match err {
+        Some(err) => {
+            if !instr_ptr.is_null() {
+                *instr_ptr = err.instr_ptr;
+            }
+            error_code(err.kind)
+        },
+        None => ERROR_NONE
+    }
+}
+
+// the opcode, instruction or address the last fault was about; 0 if it has
+// none or for a NULL machine
+/// # Safety
+/// `machine` must be null or a live handle from this library.
+#[no_mangle]
+pub unsafe extern "C" fn intcode_error_word(machine: *const IntcodeMachine) -> i64 {
+    let err = match machine.as_ref().and_then(|state| state.error) {
+        Some(err) => err,
+        None => return 0
+    };
+
+    match err.kind {
+        ErrorKind::InvalidOpcode(word) | ErrorKind::InvalidMode(word) | ErrorKind::InvalidAddress(word) => word,
+        ErrorKind::ImmediateWrite | ErrorKind::ArithmeticOverflow => 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = include_str!("../../include/intcode.h");
+
+    // outputs 1 if the input is 8, 0 otherwise
+    const EQUALS_EIGHT: [Word; 11] = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+
+    fn memory(machine: *const IntcodeMachine) -> Vec<i64> {
+        unsafe {
+            let len = intcode_read_memory(machine, ptr::null_mut(), 0);
+            let mut buffer = vec!(0; len);
+
+            intcode_read_memory(machine, buffer.as_mut_ptr(), len);
+            buffer
+        }
+    }
+
+    #[test]
+    fn header_is_current() {
+        assert!(HEADER == header(), "include/intcode.h is out of date; regenerate it with the intcode-header binary");
+    }
+
+    #[test]
+    fn reads_every_export() {
+        let names: Vec<String> = exports().into_iter().map(|export| export.name).collect();
+
+        assert_eq!(names, vec!("intcode_create", "intcode_snapshot", "intcode_destroy", "intcode_push_input",
+            "intcode_run", "intcode_run_limited", "intcode_read_output", "intcode_read_memory",
+            "intcode_instr_ptr", "intcode_last_error", "intcode_error_word"));
+    }
+
+    #[test]
+    fn comments_every_export() {
+        for export in exports() {
+            assert!(!export.comment.is_empty(), "{} has no comment for the header", export.name);
+        }
+    }
+
+    #[test]
+    fn translates_signatures() {
+        let export = parse_export("pub unsafe extern \"C\" fn f(a: *const i64, b: usize, c: *mut *mut i32) -> *mut IntcodeMachine {",
+            String::from("note"));
+
+        assert_eq!(export.params[2], (String::from("c"), String::from("*mut *mut i32")));
+        assert_eq!(prototype(&export), "IntcodeMachine *f(const int64_t *a, size_t b, int32_t * *c);");
+
+        let export = parse_export("pub unsafe extern \"C\" fn g() {", String::new());
+        assert_eq!(prototype(&export), "void g();");
+    }
+
+    #[test]
+    #[should_panic(expected = "no C type for `u8`")]
+    fn refuses_unknown_types() {
+        c_type("*const u8");
+    }
+
+    #[test]
+    fn runs_through_the_interface() {
+        unsafe {
+            let machine = intcode_create(EQUALS_EIGHT.as_ptr(), EQUALS_EIGHT.len());
+            let mut value = 0;
+
+            assert_eq!(intcode_run(machine), STATUS_NEED_INPUT);
+            assert_eq!(intcode_read_output(machine, &mut value), 0);
+
+            assert_eq!(intcode_push_input(machine, 8), STATUS_OK);
+
+            assert_eq!(intcode_run(machine), STATUS_OUTPUT);
+            assert_eq!(intcode_run(machine), STATUS_HALTED);
+
+            assert_eq!(intcode_read_output(machine, &mut value), 1);
+            assert_eq!(value, 1);
+            assert_eq!(intcode_read_output(machine, &mut value), 0);
+
+            assert_eq!(intcode_instr_ptr(machine), 8);
+            assert_eq!(intcode_last_error(machine, ptr::null_mut()), ERROR_NONE);
+
+            intcode_destroy(machine);
+        }
+    }
+
+    #[test]
+    fn snapshots_are_independent() {
+        unsafe {
+            let machine = intcode_create(EQUALS_EIGHT.as_ptr(), EQUALS_EIGHT.len());
+            assert_eq!(intcode_run(machine), STATUS_NEED_INPUT);
+
+            let copy = intcode_snapshot(machine);
+
+            intcode_push_input(machine, 8);
+            intcode_push_input(copy, 3);
+            intcode_run(machine);
+            intcode_run(copy);
+
+            assert_eq!(memory(machine)[9], 1);
+            assert_eq!(memory(copy)[9], 0);
+
+            intcode_destroy(machine);
+            intcode_destroy(copy);
+        }
+    }
+
+    #[test]
+    fn reports_errors() {
+        let program = [1, 0, 0, 0, 77];
+
+        unsafe {
+            let machine = intcode_create(program.as_ptr(), program.len());
+            let mut instr_ptr = 0;
+
+            assert_eq!(intcode_run(machine), STATUS_ERROR);
+            assert_eq!(intcode_last_error(machine, &mut instr_ptr), ERROR_INVALID_OPCODE);
+            assert_eq!(instr_ptr, 4);
+            assert_eq!(intcode_error_word(machine), 77);
+
+            // a faulted machine stays faulted
+            assert_eq!(intcode_run(machine), STATUS_ERROR);
+
+            intcode_destroy(machine);
+        }
+    }
+
+    #[test]
+    fn stops_at_the_step_limit() {
+        let program = [1105, 1, 0];
+
+        unsafe {
+            let machine = intcode_create(program.as_ptr(), program.len());
+
+            assert_eq!(intcode_run_limited(machine, 50), STATUS_STEP_LIMIT);
+            assert_eq!(intcode_run_limited(machine, 50), STATUS_STEP_LIMIT);
+            assert_eq!(intcode_error_word(machine), 0);
+
+            intcode_destroy(machine);
+        }
+    }
+
+    #[test]
+    fn tolerates_null() {
+        unsafe {
+            let mut value = 0;
+
+            assert!(intcode_create(ptr::null(), 3).is_null());
+            assert!(intcode_snapshot(ptr::null()).is_null());
+            assert_eq!(intcode_run(ptr::null_mut()), STATUS_NULL);
+            assert_eq!(intcode_push_input(ptr::null_mut(), 1), STATUS_NULL);
+            assert_eq!(intcode_read_output(ptr::null_mut(), &mut value), STATUS_NULL);
+            assert_eq!(intcode_read_memory(ptr::null(), ptr::null_mut(), 0), 0);
+            assert_eq!(intcode_run_limited(ptr::null_mut(), 10), STATUS_NULL);
+            assert_eq!(intcode_error_word(ptr::null()), 0);
+
+            intcode_destroy(ptr::null_mut());
+
+            let empty = intcode_create(ptr::null(), 0);
+            assert_eq!(memory(empty), Vec::<i64>::new());
+            intcode_destroy(empty);
+        }
+    }
+}
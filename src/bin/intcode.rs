@@ -0,0 +1,13 @@
+use std::env;
+use std::io;
+use std::process;
+
+use advent2019::intcode::cli;
+
+// runs an Intcode program file; see intcode::cli for the options and exit codes
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let code = cli::main(&args, &mut io::stdin(), &mut io::stdout(), &mut io::stderr());
+    process::exit(code);
+}
@@ -1,30 +1,67 @@
-#[allow(dead_code)]
+pub mod fuel;
+pub mod manifest;
+pub mod model;
+pub mod rocket;
+pub mod solver;
+pub mod summation;
 
-use std::fs::File;
-use std::io::{BufReader, BufRead};
+use std::io::Read;
+
+use manifest::{ Format, Manifest };
 use crate::util;
 
-fn fuel_cost(mass: i32, fuel: i32) -> i32 {
-    let new_fuel = (mass / 3) as i32 - 2;
+// a labelled manifest, when there is one, also gets totals per tag
+fn solve_manifest(mut file: std::fs::File, format: Format) {
+    let mut text = String::new();
 
-    if new_fuel <= 0 {
-        return fuel;
-    }
+    let manifest = match file.read_to_string(&mut text).map_err(|err| err.to_string())
+        .and_then(|_| Manifest::parse(&text, format).map_err(|err| err.to_string()))
+    {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            println!("bad manifest: {}", err);
+            return;
+        }
+    };
+
+    let grouped = match manifest.by_tag() {
+        Ok(grouped) => grouped,
+        Err(err) => {
+            println!("bad manifest: {}", err);
+            return;
+        }
+    };
 
-    return fuel_cost(new_fuel, fuel + new_fuel);
+    println!("part 1: {}", grouped.all.direct);
+    println!("part 2: {}", grouped.all.total);
+
+    for (tag, totals) in grouped.tags.iter() {
+        println!("  {}: {} modules, {} / {}", tag, totals.modules, totals.direct, totals.total);
+    }
+    if grouped.untagged.modules > 0 {
+        let totals = grouped.untagged;
+        println!("  untagged: {} modules, {} / {}", totals.modules, totals.direct, totals.total);
+    }
 }
 
 pub fn solve() {
-    let input: File = util::get_input(1);
-    let input = BufReader::new(input);
+    for &extension in ["json", "toml"].iter() {
+        if let Some(file) = util::find_input(1, &format!(".{}", extension)) {
+            solve_manifest(file, Format::from_extension(extension).unwrap());
+            return;
+        }
+    }
 
-    let mut total_fuel: i32 = 0;
-    
-    for line in input.lines() {
-        let mass: i32 = line.unwrap().parse().unwrap();
+    let input = util::get_input_line_reader(1);
 
-        total_fuel += fuel_cost(mass, 0);
-    }
+    let totals = match summation::sum_lines(input, summation::workers()) {
+        Ok(totals) => totals,
+        Err(err) => {
+            println!("bad input: {}", err);
+            return;
+        }
+    };
 
-    println!("total fuel: {}", total_fuel);
-}
\ No newline at end of file
+    println!("part 1: {}", totals.direct);
+    println!("part 2: {}", totals.total);
+}
@@ -0,0 +1,199 @@
+use std::fmt;
+use std::io::{ BufRead, Write };
+use std::vec::Vec;
+
+use serde::Serialize;
+
+use super::model::{ Day1, FuelModel };
+
+// masses and fuel are unsigned and wide enough that no single module
+// overflows; sums over many modules are u128
+pub type Mass = u64;
+
+// fuel for a mass on its own: a third of it, rounded down, less two, and
+// never less than nothing
+pub fn direct_fuel(mass: Mass) -> Mass {
+    (mass / 3).saturating_sub(2)
+}
+
+// fuel for the mass plus the fuel that fuel needs, and so on until the
+// extra fuel would be nothing. also returns how many rounds that took
+pub fn fuel_with_fuel(mass: Mass) -> (Mass, usize) {
+    Day1.fuel_with_fuel(mass)
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct ModuleFuel {
+    pub index: usize,
+    pub mass: Mass,
+    pub direct_fuel: Mass,
+    pub fuel_with_fuel: Mass,
+    pub iterations: usize
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct FuelReport {
+    pub modules: Vec<ModuleFuel>,
+    // part 1
+    pub direct_total: u128,
+    // part 2
+    pub total: u128
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl ModuleFuel {
+    pub fn new(index: usize, mass: Mass) -> ModuleFuel {
+        ModuleFuel::with_model(index, mass, &Day1)
+    }
+
+    pub fn with_model(index: usize, mass: Mass, model: &dyn FuelModel) -> ModuleFuel {
+        let (fuel, iterations) = model.fuel_with_fuel(mass);
+
+        ModuleFuel {
+            index: index,
+            mass: mass,
+            direct_fuel: model.direct_fuel(mass),
+            fuel_with_fuel: fuel,
+            iterations: iterations
+        }
+    }
+}
+
+impl FuelReport {
+    pub fn from_masses(masses: &[Mass]) -> FuelReport {
+        FuelReport::with_model(masses, &Day1)
+    }
+
+    pub fn with_model(masses: &[Mass], model: &dyn FuelModel) -> FuelReport {
+        let modules: Vec<ModuleFuel> = masses.iter()
+            .enumerate()
+            .map(|(index, &mass)| ModuleFuel::with_model(index, mass, model))
+            .collect();
+
+        FuelReport {
+            direct_total: modules.iter().map(|module| module.direct_fuel as u128).sum(),
+            total: modules.iter().map(|module| module.fuel_with_fuel as u128).sum(),
+            modules: modules
+        }
+    }
+
+    // one mass per line; blank lines are skipped
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<FuelReport, ParseError> {
+        let mut masses = Vec::new();
+
+        for (line_i, line) in reader.lines().enumerate() {
+            let error = |message: String| ParseError { line: line_i + 1, message: message };
+
+            let line = line.map_err(|err| error(err.to_string()))?;
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            masses.push(line.parse().map_err(|_| error(format!("not a mass: {}", line)))?);
+        }
+
+        Ok(FuelReport::from_masses(&masses))
+    }
+
+    // the per-module breakdown, with a header row
+    pub fn write_csv<W: Write>(&self, writer: W) -> csv::Result<()> {
+        let mut writer = csv::Writer::from_writer(writer);
+
+        for module in self.modules.iter() {
+            writer.serialize(module)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("report is always serializable")
+    }
+
+    // modules ordered by the share of the budget they take, largest first
+    pub fn heaviest(&self) -> Vec<&ModuleFuel> {
+        let mut modules: Vec<&ModuleFuel> = self.modules.iter().collect();
+        modules.sort_by(|a, b| b.fuel_with_fuel.cmp(&a.fuel_with_fuel).then(a.index.cmp(&b.index)));
+
+        modules
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day1::model::Linear;
+
+    #[test]
+    fn examples() {
+        assert_eq!(direct_fuel(12), 2);
+        assert_eq!(direct_fuel(14), 2);
+        assert_eq!(direct_fuel(1969), 654);
+        assert_eq!(direct_fuel(100756), 33583);
+
+        assert_eq!(fuel_with_fuel(14), (2, 1));
+        assert_eq!(fuel_with_fuel(1969), (966, 5));
+        assert_eq!(fuel_with_fuel(100756), (50346, 9));
+        assert_eq!(fuel_with_fuel(2), (0, 0));
+    }
+
+    #[test]
+    fn both_totals() {
+        let report = FuelReport::from_reader("12\n\n1969\n100756\n".as_bytes()).unwrap();
+
+        assert_eq!(report.direct_total, 2 + 654 + 33583);
+        assert_eq!(report.total, 2 + 966 + 50346);
+        assert_eq!(report.modules[2], ModuleFuel {
+            index: 2,
+            mass: 100756,
+            direct_fuel: 33583,
+            fuel_with_fuel: 50346,
+            iterations: 9
+        });
+        assert_eq!(report.heaviest()[0].index, 2);
+    }
+
+    #[test]
+    fn other_models() {
+        let report = FuelReport::with_model(&[100], &Linear { divisor: 4, offset: 1 });
+
+        assert_eq!(report.direct_total, 24);
+        assert_eq!(report.total, 29);
+    }
+
+    #[test]
+    fn reports_bad_lines() {
+        let err = FuelReport::from_reader("12\nfourteen\n".as_bytes()).unwrap_err();
+
+        assert_eq!(err, ParseError { line: 2, message: String::from("not a mass: fourteen") });
+    }
+
+    #[test]
+    fn exports() {
+        let report = FuelReport::from_masses(&[12, 1969]);
+
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv).unwrap();
+
+        assert_eq!(String::from_utf8(csv).unwrap(),
+            "index,mass,direct_fuel,fuel_with_fuel,iterations\n0,12,2,2,1\n1,1969,654,966,5\n");
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["total"], 968);
+        assert_eq!(json["modules"][1]["iterations"], 5);
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::vec::Vec;
+
+use serde::{ Deserialize, Serialize };
+
+use super::fuel::{ Mass, ParseError };
+use super::summation::{ Overflow, Totals };
+
+// a labelled inventory of modules, as json:
+//
+//   { "modules": [ { "name": "nav", "mass": 1969, "quantity": 2, "tags": ["core"] } ] }
+//
+// or toml:
+//
+//   [[modules]]
+//   name = "nav"
+//   mass = 1969
+//   quantity = 2
+//   tags = ["core"]
+//
+// quantity defaults to one and tags to none. the plain one-mass-per-line
+// input reads as a manifest of untagged modules named by line number
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Plain,
+    Json,
+    Toml
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Module {
+    pub name: String,
+    pub mass: Mass,
+    #[serde(default = "one")]
+    pub quantity: u64,
+    #[serde(default)]
+    pub tags: Vec<String>
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub modules: Vec<Module>
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Grouped {
+    pub all: Totals,
+    // a module with several tags counts towards each of them
+    pub tags: BTreeMap<String, Totals>,
+    pub untagged: Totals
+}
+
+fn one() -> u64 {
+    1
+}
+
+// the one-based line a byte offset falls on
+fn line_of(text: &str, offset: usize) -> usize {
+    text.as_bytes()[..offset.min(text.len())].iter().filter(|&&byte| byte == b'\n').count() + 1
+}
+
+fn overflow(module: &Module, overflow: Overflow) -> String {
+    format!("{}: {}", module.name, overflow)
+}
+
+impl Format {
+    // plain input is summed as it streams in rather than read as a manifest,
+    // so only the structured formats are picked by extension
+    pub fn from_extension(extension: &str) -> Option<Format> {
+        match extension {
+            "json" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            _ => None
+        }
+    }
+}
+
+impl Module {
+    pub fn new(name: &str, mass: Mass) -> Module {
+        Module {
+            name: String::from(name),
+            mass: mass,
+            quantity: 1,
+            tags: Vec::new()
+        }
+    }
+}
+
+impl Manifest {
+    pub fn parse(text: &str, format: Format) -> Result<Manifest, ParseError> {
+        match format {
+            Format::Plain => Manifest::from_plain(text.as_bytes()),
+            Format::Json => serde_json::from_str(text).map_err(|err| ParseError {
+                line: err.line(),
+                message: err.to_string()
+            }),
+            Format::Toml => toml::from_str(text).map_err(|err: toml::de::Error| ParseError {
+                line: err.span().map_or(0, |span| line_of(text, span.start)),
+                message: err.message().to_string()
+            })
+        }
+    }
+
+    pub fn from_plain<R: BufRead>(reader: R) -> Result<Manifest, ParseError> {
+        let mut modules = Vec::new();
+
+        for (line_i, line) in reader.lines().enumerate() {
+            let error = |message: String| ParseError { line: line_i + 1, message: message };
+
+            let line = line.map_err(|err| error(err.to_string()))?;
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let mass = line.parse().map_err(|_| error(format!("not a mass: {}", line)))?;
+            modules.push(Module::new(&format!("line {}", line_i + 1), mass));
+        }
+
+        Ok(Manifest { modules: modules })
+    }
+
+    // errors name the module that overflowed the totals
+    pub fn totals(&self) -> Result<Totals, String> {
+        let mut totals = Totals::default();
+
+        for module in self.modules.iter() {
+            totals.add_copies(module.mass, module.quantity).map_err(|err| overflow(module, err))?;
+        }
+
+        Ok(totals)
+    }
+
+    pub fn by_tag(&self) -> Result<Grouped, String> {
+        let mut grouped = Grouped::default();
+
+        for module in self.modules.iter() {
+            let add = |totals: &mut Totals| totals.add_copies(module.mass, module.quantity).map_err(|err| overflow(module, err));
+
+            add(&mut grouped.all)?;
+
+            if module.tags.is_empty() {
+                add(&mut grouped.untagged)?;
+            }
+
+            let mut tags: Vec<&String> = module.tags.iter().collect();
+            tags.sort();
+            tags.dedup();
+
+            for tag in tags {
+                add(grouped.tags.entry(tag.clone()).or_default())?;
+            }
+        }
+
+        Ok(grouped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON: &str = r#"{
+        "modules": [
+            { "name": "nav", "mass": 12, "tags": ["core", "avionics"] },
+            { "name": "tank", "mass": 1969, "quantity": 3, "tags": ["core"] },
+            { "name": "probe", "mass": 100756 }
+        ]
+    }"#;
+
+    const TOML: &str = r#"
+        [[modules]]
+        name = "nav"
+        mass = 12
+        tags = ["core", "avionics"]
+
+        [[modules]]
+        name = "tank"
+        mass = 1969
+        quantity = 3
+        tags = ["core"]
+
+        [[modules]]
+        name = "probe"
+        mass = 100756
+    "#;
+
+    #[test]
+    fn formats_agree() {
+        let json = Manifest::parse(JSON, Format::Json).unwrap();
+        let toml = Manifest::parse(TOML, Format::Toml).unwrap();
+
+        assert_eq!(json, toml);
+        assert_eq!(json.modules[0].quantity, 1);
+        assert_eq!(json.modules[2].tags, Vec::<String>::new());
+    }
+
+    #[test]
+    fn grouped_totals() {
+        let grouped = Manifest::parse(JSON, Format::Json).unwrap().by_tag().unwrap();
+
+        assert_eq!(grouped.all, Totals { modules: 5, direct: 2 + 3 * 654 + 33583, total: 2 + 3 * 966 + 50346 });
+        assert_eq!(grouped.tags["core"], Totals { modules: 4, direct: 2 + 3 * 654, total: 2 + 3 * 966 });
+        assert_eq!(grouped.tags["avionics"], Totals { modules: 1, direct: 2, total: 2 });
+        assert_eq!(grouped.untagged, Totals { modules: 1, direct: 33583, total: 50346 });
+    }
+
+    #[test]
+    fn plain_input() {
+        let manifest = Manifest::parse("12\n\n1969\n", Format::Plain).unwrap();
+
+        assert_eq!(manifest.modules, vec!(Module::new("line 1", 12), Module::new("line 3", 1969)));
+        assert_eq!(manifest.totals().unwrap(), Totals { modules: 2, direct: 656, total: 968 });
+    }
+
+    #[test]
+    fn errors_carry_lines() {
+        let err = Manifest::parse("12\nfourteen\n", Format::Plain).unwrap_err();
+        assert_eq!(err.line, 2);
+
+        let err = Manifest::parse("{\n \"modules\": [\n { \"name\": \"x\", \"mass\": -4 }\n ]\n}", Format::Json).unwrap_err();
+        assert_eq!(err.line, 3);
+
+        let err = Manifest::parse("[[modules]]\nname = \"x\"\nmass = \"heavy\"\n", Format::Toml).unwrap_err();
+        assert_eq!(err.line, 3);
+    }
+
+    #[test]
+    fn overflowing_quantities() {
+        let mut manifest = Manifest::parse(JSON, Format::Json).unwrap();
+        manifest.modules[1].quantity = u64::MAX;
+
+        assert_eq!(manifest.totals(), Err(String::from("tank: totals overflow")));
+        assert_eq!(manifest.by_tag(), Err(String::from("tank: totals overflow")));
+    }
+}
@@ -0,0 +1,144 @@
+use super::fuel::{ self, Mass };
+
+// how much fuel a mass needs. the fuel-on-fuel total comes for free from
+// direct_fuel and tank_mass: each increment of fuel, plus whatever holds it,
+// is itself a mass that needs fuel, until an increment needs none
+pub trait FuelModel {
+    fn direct_fuel(&self, mass: Mass) -> Mass;
+
+    // dry mass of the tanks carrying this much fuel
+    fn tank_mass(&self, _fuel: Mass) -> Mass {
+        0
+    }
+
+    // total fuel and the number of increments it took, or None if the
+    // increments stop shrinking, as when heavy tanks need more fuel than
+    // they carry, and the total grows without bound. iterative, so it
+    // doesn't matter how many increments there are
+    fn try_fuel_with_fuel(&self, mass: Mass) -> Option<(Mass, usize)> {
+        let mut total: Mass = 0;
+        let mut iterations = 0;
+        let mut fuel = self.direct_fuel(mass);
+
+        while fuel > 0 {
+            total = total.checked_add(fuel)?;
+            iterations += 1;
+
+            let next = self.direct_fuel(fuel.saturating_add(self.tank_mass(fuel)));
+            if next >= fuel {
+                return None;
+            }
+            fuel = next;
+        }
+
+        Some((total, iterations))
+    }
+
+    fn fuel_with_fuel(&self, mass: Mass) -> (Mass, usize) {
+        self.try_fuel_with_fuel(mass).expect("fuel model never converges")
+    }
+}
+
+// the puzzle's rule: a third of the mass, rounded down, less two
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Day1;
+
+// mass / divisor - offset
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Linear {
+    pub divisor: Mass,
+    pub offset: Mass
+}
+
+// any formula at all, e.g. an efficiency curve fitted from test data
+pub struct Curve<F: Fn(Mass) -> Mass>(pub F);
+
+// wraps another model with tanks that hold `capacity` fuel each and weigh
+// `dry_mass` empty
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Tanked<M: FuelModel> {
+    pub inner: M,
+    pub capacity: Mass,
+    pub dry_mass: Mass
+}
+
+impl FuelModel for Day1 {
+    fn direct_fuel(&self, mass: Mass) -> Mass {
+        fuel::direct_fuel(mass)
+    }
+}
+
+impl FuelModel for Linear {
+    fn direct_fuel(&self, mass: Mass) -> Mass {
+        (mass / self.divisor).saturating_sub(self.offset)
+    }
+}
+
+impl<F: Fn(Mass) -> Mass> FuelModel for Curve<F> {
+    fn direct_fuel(&self, mass: Mass) -> Mass {
+        (self.0)(mass)
+    }
+}
+
+impl<M: FuelModel> FuelModel for Tanked<M> {
+    fn direct_fuel(&self, mass: Mass) -> Mass {
+        self.inner.direct_fuel(mass)
+    }
+
+    fn tank_mass(&self, fuel: Mass) -> Mass {
+        let tanks = fuel.div_ceil(self.capacity);
+
+        tanks.saturating_mul(self.dry_mass).saturating_add(self.inner.tank_mass(fuel))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_matches_the_puzzle() {
+        assert_eq!(Day1.direct_fuel(1969), 654);
+        assert_eq!(Day1.fuel_with_fuel(1969), (966, 5));
+        assert_eq!(Day1.fuel_with_fuel(100756), (50346, 9));
+    }
+
+    #[test]
+    fn linear() {
+        let model = Linear { divisor: 4, offset: 1 };
+
+        assert_eq!(model.direct_fuel(100), 24);
+        // 24, then 5, then 0
+        assert_eq!(model.fuel_with_fuel(100), (29, 2));
+    }
+
+    #[test]
+    fn curve() {
+        // more efficient the heavier the module
+        let model = Curve(|mass: Mass| if mass > 1000 { mass / 4 } else { mass / 3 }.saturating_sub(2));
+
+        assert_eq!(model.direct_fuel(1200), 298);
+        assert_eq!(model.fuel_with_fuel(1200).0, 298 + 97 + 30 + 8);
+    }
+
+    #[test]
+    fn tanks_add_mass() {
+        let model = Tanked { inner: Day1, capacity: 100, dry_mass: 1 };
+
+        assert_eq!(model.tank_mass(0), 0);
+        assert_eq!(model.tank_mass(1), 1);
+        assert_eq!(model.tank_mass(250), 3);
+
+        // 654 fuel needs 7 tanks, so the next increment lifts 661, not 654
+        assert_eq!(model.fuel_with_fuel(1969), (654 + 218 + 71 + 22 + 5, 5));
+    }
+
+    #[test]
+    fn heavy_tanks_diverge() {
+        let model = Tanked { inner: Day1, capacity: 100, dry_mass: 10 };
+
+        // 2 fuel in a 10 mass tank needs 2 more fuel, forever
+        assert_eq!(model.try_fuel_with_fuel(1969), None);
+        assert_eq!(Day1.try_fuel_with_fuel(1969), Some((966, 5)));
+    }
+}
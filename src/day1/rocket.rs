@@ -0,0 +1,107 @@
+use std::vec::Vec;
+
+use super::fuel::Mass;
+use super::model::FuelModel;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Stage {
+    pub name: String,
+    pub dry_mass: Mass
+}
+
+// stages listed from the bottom up, with the payload riding on the top one
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Rocket {
+    pub payload: Mass,
+    pub stages: Vec<Stage>
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct StagePlan {
+    pub name: String,
+    // everything this stage's fuel has to lift besides itself: the payload
+    // and every stage above, fuelled
+    pub lifted: Mass,
+    pub fuel: Mass,
+    // the stage with its fuel, as seen by the stage below
+    pub wet_mass: Mass
+}
+
+impl Stage {
+    pub fn new(name: &str, dry_mass: Mass) -> Stage {
+        Stage {
+            name: String::from(name),
+            dry_mass: dry_mass
+        }
+    }
+}
+
+impl Rocket {
+    // fuels each stage from the top down, since a stage's fuel has to lift
+    // the stages above it with all of theirs. the plan is bottom up, like
+    // the stages, or None if some stage can never carry enough
+    pub fn plan(&self, model: &dyn FuelModel) -> Option<Vec<StagePlan>> {
+        let mut above = self.payload;
+        let mut plans = Vec::new();
+
+        for stage in self.stages.iter().rev() {
+            let (fuel, _) = model.try_fuel_with_fuel(stage.dry_mass.checked_add(above)?)?;
+            let wet_mass = stage.dry_mass.checked_add(fuel)?;
+
+            plans.push(StagePlan {
+                name: stage.name.clone(),
+                lifted: above,
+                fuel: fuel,
+                wet_mass: wet_mass
+            });
+
+            above = above.checked_add(wet_mass)?;
+        }
+
+        plans.reverse();
+        Some(plans)
+    }
+
+    pub fn total_fuel(&self, model: &dyn FuelModel) -> Option<Mass> {
+        self.plan(model)?.iter().try_fold(0 as Mass, |total, plan| total.checked_add(plan.fuel))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day1::model::{ Day1, Tanked };
+
+    #[test]
+    fn single_stage_is_a_module() {
+        let rocket = Rocket { payload: 1000, stages: vec!(Stage::new("only", 969)) };
+
+        assert_eq!(rocket.total_fuel(&Day1), Some(966));
+    }
+
+    #[test]
+    fn lower_stages_lift_upper_ones() {
+        let rocket = Rocket {
+            payload: 100,
+            stages: vec!(Stage::new("booster", 2000), Stage::new("upper", 300))
+        };
+
+        let plan = rocket.plan(&Day1).unwrap();
+
+        // upper: 400 needs 131 + 41 + 11 + 1
+        assert_eq!(plan[1], StagePlan { name: String::from("upper"), lifted: 100, fuel: 184, wet_mass: 484 });
+
+        // booster lifts the fuelled upper stage and the payload on top of itself
+        assert_eq!(plan[0].lifted, 584);
+        assert_eq!(plan[0].fuel, Day1.fuel_with_fuel(2584).0);
+        assert_eq!(rocket.total_fuel(&Day1), Some(184 + plan[0].fuel));
+    }
+
+    #[test]
+    fn unflyable_stage() {
+        let rocket = Rocket { payload: 10, stages: vec!(Stage::new("heavy", 5000)) };
+        let model = Tanked { inner: Day1, capacity: 100, dry_mass: 10 };
+
+        assert_eq!(rocket.plan(&model), None);
+    }
+}
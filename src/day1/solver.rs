@@ -0,0 +1,142 @@
+use super::fuel::Mass;
+use super::model::FuelModel;
+
+// answers "how much can we carry" by searching on the forward fuel rule,
+// which only works because more mass never needs less fuel
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Plan {
+    // fuel for the modules that are going anyway
+    pub fixed_fuel: Mass,
+    // the heaviest extra payload the rest of the budget can lift
+    pub payload: Mass,
+    pub payload_fuel: Mass,
+    // budget left over once the payload is fuelled
+    pub spare: Mass
+}
+
+fn fuel(model: &dyn FuelModel, mass: Mass) -> Option<Mass> {
+    model.try_fuel_with_fuel(mass).map(|(fuel, _)| fuel)
+}
+
+// the largest value in 0..=Mass::MAX the predicate holds for, given it holds
+// for 0 and, once false, stays false
+fn largest(fits: impl Fn(Mass) -> bool) -> Mass {
+    let mut low = 0;
+    let mut high = 1;
+
+    // grow until something doesn't fit
+    while fits(high) {
+        low = high;
+
+        if high == Mass::MAX {
+            return high;
+        }
+        high = high.saturating_mul(2);
+    }
+
+    // low fits, high doesn't
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+
+        if fits(mid) {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    low
+}
+
+// the heaviest payload that can fly alongside the fixed modules on the
+// budget, or None if the fixed modules alone need more than that
+pub fn max_payload(budget: Mass, fixed: &[Mass], model: &dyn FuelModel) -> Option<Plan> {
+    let mut fixed_fuel: Mass = 0;
+
+    for &mass in fixed.iter() {
+        fixed_fuel = fixed_fuel.checked_add(fuel(model, mass)?)?;
+    }
+
+    let remaining = budget.checked_sub(fixed_fuel)?;
+    let payload = largest(|mass| fuel(model, mass).is_some_and(|fuel| fuel <= remaining));
+    let payload_fuel = fuel(model, payload)?;
+
+    Some(Plan {
+        fixed_fuel: fixed_fuel,
+        payload: payload,
+        payload_fuel: payload_fuel,
+        spare: remaining - payload_fuel
+    })
+}
+
+// how many more modules of this mass the budget stretches to
+pub fn max_copies(budget: Mass, fixed: &[Mass], mass: Mass, model: &dyn FuelModel) -> Option<Mass> {
+    let plan = max_payload(budget, fixed, model)?;
+    let each = fuel(model, mass)?;
+
+    if each == 0 {
+        return None;
+    }
+
+    Some((budget - plan.fixed_fuel) / each)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day1::model::{ Day1, Tanked };
+
+    #[test]
+    fn inverts_the_examples() {
+        // 966 is exactly what 1969 needs, and 1970 needs no more
+        let plan = max_payload(966, &[], &Day1).unwrap();
+
+        assert_eq!(plan.payload, 1970);
+        assert_eq!(plan.payload_fuel, 966);
+        assert_eq!(plan.spare, 0);
+        assert!(Day1.fuel_with_fuel(plan.payload + 1).0 > 966);
+    }
+
+    #[test]
+    fn small_budgets() {
+        // anything up to 8 flies for free
+        assert_eq!(max_payload(0, &[], &Day1).unwrap().payload, 8);
+        assert_eq!(max_payload(1, &[], &Day1).unwrap().payload, 11);
+    }
+
+    #[test]
+    fn fixed_modules_come_first() {
+        let plan = max_payload(50346 + 1000, &[100756], &Day1).unwrap();
+
+        assert_eq!(plan.fixed_fuel, 50346);
+        assert!(plan.payload_fuel <= 1000);
+        assert!(Day1.fuel_with_fuel(plan.payload + 1).0 > 1000);
+
+        assert_eq!(max_payload(50000, &[100756], &Day1), None);
+    }
+
+    #[test]
+    fn copies() {
+        // 1969 takes 966 each
+        assert_eq!(max_copies(10_000, &[], 1969, &Day1), Some(10));
+        assert_eq!(max_copies(10_000, &[100756], 1969, &Day1), None);
+        assert_eq!(max_copies(10_000, &[], 5, &Day1), None);
+    }
+
+    #[test]
+    fn huge_budget() {
+        let plan = max_payload(Mass::MAX, &[], &Day1).unwrap();
+
+        assert_eq!(plan.payload, Mass::MAX);
+        assert_eq!(plan.spare, Mass::MAX - plan.payload_fuel);
+    }
+
+    #[test]
+    fn diverging_model() {
+        let model = Tanked { inner: Day1, capacity: 100, dry_mass: 10 };
+
+        // nothing that needs fuel can fly, so only the free masses
+        assert_eq!(max_payload(1_000_000, &[], &model).unwrap().payload, 8);
+    }
+}
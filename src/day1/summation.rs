@@ -0,0 +1,256 @@
+use std::fmt;
+use std::io::BufRead;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::mpsc;
+use std::sync::{ Arc, Mutex };
+use std::thread;
+use std::vec::Vec;
+
+use super::fuel::{ self, Mass, ParseError };
+
+// both answers for manifests too big to keep in memory: lines are read in
+// chunks and summed on worker threads as they arrive
+
+const CHUNK_LINES: usize = 1 << 16;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Totals {
+    pub modules: u64,
+    // part 1
+    pub direct: u128,
+    // part 2
+    pub total: u128
+}
+
+// a count grew past what Totals can hold
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Overflow;
+
+// a run of lines and the line number of the first
+type Chunk = (usize, Vec<String>);
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "totals overflow")
+    }
+}
+
+impl Totals {
+    pub fn add(&mut self, mass: Mass) -> Result<(), Overflow> {
+        self.add_copies(mass, 1)
+    }
+
+    // on overflow the totals are left as they were
+    pub fn add_copies(&mut self, mass: Mass, quantity: u64) -> Result<(), Overflow> {
+        let copies = Totals {
+            modules: quantity,
+            direct: (fuel::direct_fuel(mass) as u128).checked_mul(quantity as u128).ok_or(Overflow)?,
+            total: (fuel::fuel_with_fuel(mass).0 as u128).checked_mul(quantity as u128).ok_or(Overflow)?
+        };
+
+        self.merge(&copies)
+    }
+
+    pub fn merge(&mut self, other: &Totals) -> Result<(), Overflow> {
+        *self = Totals {
+            modules: self.modules.checked_add(other.modules).ok_or(Overflow)?,
+            direct: self.direct.checked_add(other.direct).ok_or(Overflow)?,
+            total: self.total.checked_add(other.total).ok_or(Overflow)?
+        };
+
+        Ok(())
+    }
+}
+
+pub fn workers() -> usize {
+    thread::available_parallelism().map(|count| count.get()).unwrap_or(1)
+}
+
+fn sum_chunk(totals: &mut Totals, (first_line, lines): &Chunk) -> Result<(), ParseError> {
+    for (offset, line) in lines.iter().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let error = |message: String| ParseError { line: first_line + offset, message: message };
+
+        let mass = line.parse().map_err(|_| error(format!("not a mass: {}", line)))?;
+        totals.add(mass).map_err(|overflow| error(overflow.to_string()))?;
+    }
+
+    Ok(())
+}
+
+// one mass per line, blank lines skipped. a malformed line stops the sum and
+// is reported by number; if there are several, the first one
+pub fn sum_lines<R: BufRead>(reader: R, workers: usize) -> Result<Totals, ParseError> {
+    sum_in_chunks(reader, workers.max(1), CHUNK_LINES)
+}
+
+fn sum_in_chunks<R: BufRead>(reader: R, workers: usize, chunk_lines: usize) -> Result<Totals, ParseError> {
+    // bounded, so a fast reader can't run far ahead of the workers
+    let (sender, receiver) = mpsc::sync_channel::<Chunk>(workers * 2);
+    let receiver = Arc::new(Mutex::new(receiver));
+    let failed = AtomicBool::new(false);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                let receiver = receiver.clone();
+                let failed = &failed;
+
+                scope.spawn(move || {
+                    let mut totals = Totals::default();
+                    let mut first_error: Option<ParseError> = None;
+
+                    // keep draining after an error, since a chunk still queued
+                    // may hold an earlier one
+                    loop {
+                        let chunk = match receiver.lock().unwrap().recv() {
+                            Ok(chunk) => chunk,
+                            Err(_) => break
+                        };
+
+                        if let Err(err) = sum_chunk(&mut totals, &chunk) {
+                            failed.store(true, Ordering::Relaxed);
+
+                            if first_error.as_ref().is_none_or(|first| err.line < first.line) {
+                                first_error = Some(err);
+                            }
+                        }
+                    }
+
+                    match first_error {
+                        Some(err) => Err(err),
+                        None => Ok(totals)
+                    }
+                })
+            })
+            .collect();
+
+        let mut read_error = None;
+        let mut lines_read = 0;
+        let mut chunk: Chunk = (1, Vec::with_capacity(chunk_lines));
+
+        for (line_i, line) in reader.lines().enumerate() {
+            if failed.load(Ordering::Relaxed) {
+                break;
+            }
+            lines_read = line_i + 1;
+
+            match line {
+                Ok(line) => chunk.1.push(line),
+                Err(err) => {
+                    read_error = Some(ParseError { line: line_i + 1, message: err.to_string() });
+                    break;
+                }
+            }
+
+            if chunk.1.len() == chunk_lines {
+                let next = (line_i + 2, Vec::with_capacity(chunk_lines));
+                sender.send(std::mem::replace(&mut chunk, next)).expect("workers outlive the reader");
+            }
+        }
+
+        if !chunk.1.is_empty() {
+            sender.send(chunk).expect("workers outlive the reader");
+        }
+        drop(sender);
+
+        let mut totals = Totals::default();
+        let mut first_error = read_error;
+
+        for handle in handles {
+            let joined = handle.join().expect("summing worker panicked").and_then(|partial| {
+                // the partial sums only overflow together, by the end of the input
+                totals.merge(&partial).map_err(|overflow| ParseError { line: lines_read, message: overflow.to_string() })
+            });
+
+            match joined {
+                Ok(()) => (),
+                Err(err) => {
+                    if first_error.as_ref().is_none_or(|first| err.line < first.line) {
+                        first_error = Some(err);
+                    }
+                }
+            }
+        }
+
+        match first_error {
+            Some(err) => Err(err),
+            None => Ok(totals)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day1::fuel::FuelReport;
+
+    fn manifest(count: usize) -> String {
+        (0..count).map(|i| ((i * 7919) % 200_000 + 1).to_string() + "\n").collect()
+    }
+
+    #[test]
+    fn agrees_with_the_report() {
+        let text = manifest(10_000);
+        let report = FuelReport::from_reader(text.as_bytes()).unwrap();
+
+        for &(workers, chunk_lines) in [(1, 10_000), (4, 7), (3, 1)].iter() {
+            let totals = sum_in_chunks(text.as_bytes(), workers, chunk_lines).unwrap();
+
+            assert_eq!(totals.modules, 10_000);
+            assert_eq!(totals.direct, report.direct_total);
+            assert_eq!(totals.total, report.total);
+        }
+    }
+
+    #[test]
+    fn examples() {
+        let totals = sum_lines("12\n14\n\n1969\n100756\n".as_bytes(), 2).unwrap();
+
+        assert_eq!(totals, Totals { modules: 4, direct: 34241, total: 51316 });
+    }
+
+    #[test]
+    fn first_bad_line_wins() {
+        let mut lines: Vec<String> = manifest(1000).lines().map(String::from).collect();
+        lines[612] = String::from("heavy");
+        lines[37] = String::from("-5");
+        let text = lines.join("\n");
+
+        for &workers in [1, 2, 8].iter() {
+            let err = sum_in_chunks(text.as_bytes(), workers, 16).unwrap_err();
+
+            assert_eq!(err, ParseError { line: 38, message: String::from("not a mass: -5") });
+        }
+    }
+
+    #[test]
+    fn totals_past_u64() {
+        // each module needs about half its mass in fuel, so eight of these
+        // add up to more than a u64 holds
+        let text = format!("{}\n", Mass::MAX).repeat(8);
+
+        let totals = sum_lines(text.as_bytes(), 2).unwrap();
+
+        assert!(totals.total > Mass::MAX as u128);
+        assert_eq!(totals.direct, 8 * fuel::direct_fuel(Mass::MAX) as u128);
+    }
+
+    #[test]
+    fn copies_overflow() {
+        let mut totals = Totals { modules: Mass::MAX - 1, direct: 0, total: 0 };
+
+        assert_eq!(totals.add_copies(12, 1), Ok(()));
+        assert_eq!(totals.add_copies(12, 1), Err(Overflow));
+        assert_eq!(totals, Totals { modules: Mass::MAX, direct: 2, total: 2 });
+
+        let mut totals = Totals::default();
+        assert_eq!(totals.add_copies(Mass::MAX, Mass::MAX), Ok(()));
+        assert_eq!(totals.add_copies(Mass::MAX, 1), Err(Overflow));
+    }
+}
@@ -1,11 +1,10 @@
 #[allow(dead_code)]
+pub mod int_code;
 
-use crate::int_code;
 use crate::util;
 
 pub fn solve() {
     let input = util::get_input(2);
-    let input = util::to_csv_reader(input);
 
     let mut int_code = int_code::IntCode::new(input);
 
@@ -1,9 +1,8 @@
 #[allow(dead_code)]
-
 use std::fs::File;
 use std::vec::Vec;
 
-use csv::Reader;
+use crate::intcode::load;
 
 pub struct IntCode {
     memory: Vec<i32>,
@@ -12,22 +11,22 @@ pub struct IntCode {
 }
 
 impl IntCode {
-    pub fn new(mut input: Reader<File>) -> IntCode {
-        let mut opcodes: Vec<i32> = Vec::new(); 
-
-        for record in input.records() {
-            for code in record.unwrap().iter() {
-                opcodes.push(code.parse().unwrap());
-            }
-        }
+    pub fn new(input: File) -> IntCode {
+        IntCode::from_program(load::read_legacy_program(input).expect("unable to read program"))
+    }
 
+    pub fn from_program(program: Vec<i32>) -> IntCode {
         IntCode {
-            memory: opcodes,
+            memory: program,
             current_state: Vec::new(),
             instr_ptr: 0
         }
     }
 
+    pub fn current_state(&self) -> &[i32] {
+        &self.current_state
+    }
+
     pub fn process_inputs(&mut self, noun: i32, verb: i32) -> i32 {
         self.current_state = self.memory.to_vec();
         self.current_state[1] = noun;
@@ -1,5 +1,9 @@
 #[allow(dead_code)]
-
+pub mod ascii;
+pub mod crossings;
+pub mod proximity;
+pub mod segment;
+pub mod svg;
 pub mod wire;
 pub mod wire_grid;
 
@@ -16,20 +20,13 @@ pub fn solve() {
 
     let mut wires: Vec<Wire> = Vec::new();
     for line in line_reader.lines() {
-        let wire = line.unwrap();
-
-        let wire_reader = csv::ReaderBuilder::new()
-            .has_headers(false)
-            .from_reader(wire.as_bytes());
-
-        let wire = Wire::from_csv_reader(wire_reader);        
-        wires.push(wire);
+        wires.push(Wire::parse(&line.unwrap()));
     }
 
     let mut wire_grid = WireGrid::from_wires(&wires);
 
     for (id, wire) in wires.iter().enumerate() {
-        wire_grid.add_wire(&wire, id as i32);
+        wire_grid.add_wire(wire, id as i32);
     }
 
     println!("part 1: {}", wire_grid.closest);
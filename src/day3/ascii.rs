@@ -0,0 +1,214 @@
+use std::collections::HashSet;
+use std::vec::Vec;
+
+use super::segment::{ self, Point };
+use super::wire::Wire;
+
+// draws small layouts as text, the way the puzzle does:
+//
+//   ...........
+//   .+-----+...
+//   .|.....|...
+//   .|..+--X-+.
+//   .|..|..|.|.
+//   .|.-X--+.|.
+//   .|..|....|.
+//   .|.......|.
+//   .o-------+.
+//   ...........
+//
+// o is the origin, + a turn or a wire crossing itself, and X where wires
+// cross. every cell of the window is laid out, so keep to small windows
+
+// windows with more cells than this aren't drawn
+pub const MAX_CELLS: i64 = 1 << 20;
+
+// the cells to draw, corners included
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Window {
+    pub low: Point,
+    pub high: Point
+}
+
+impl Window {
+    // all of the wires, with a border of empty cells except where it would
+    // fall outside an i32
+    pub fn fit(wires: &[Wire]) -> Window {
+        let mut low = (0, 0);
+        let mut high = (0, 0);
+
+        for wire in wires.iter() {
+            high = (high.0.max(wire.extents.0), high.1.max(wire.extents.1));
+            low = (low.0.min(wire.extents.2), low.1.min(wire.extents.3));
+        }
+
+        Window {
+            low: (low.0.saturating_sub(1), low.1.saturating_sub(1)),
+            high: (high.0.saturating_add(1), high.1.saturating_add(1))
+        }
+    }
+
+    // this many cells either side of the origin
+    pub fn around_origin(across: i32, up: i32) -> Window {
+        Window {
+            low: (-across, -up),
+            high: (across, up)
+        }
+    }
+
+    fn contains(&self, point: Point) -> bool {
+        point.0 >= self.low.0 && point.0 <= self.high.0 && point.1 >= self.low.1 && point.1 <= self.high.1
+    }
+}
+
+// whether the wire changes axis at the middle point
+fn turns(points: &[Point]) -> bool {
+    (points[0].0 == points[1].0) != (points[1].0 == points[2].0)
+}
+
+// the points a move covers that fall in the window: like Wire::expand, those
+// after start up to and including end
+fn covered(start: Point, end: Point, window: &Window) -> Vec<Point> {
+    let horizontal = start.1 == end.1;
+
+    let (along, across) = if horizontal { (start.0, start.1) } else { (start.1, start.0) };
+    let finish = if horizontal { end.0 } else { end.1 };
+    let (low, high) = if horizontal { (window.low.0, window.high.0) } else { (window.low.1, window.high.1) };
+
+    let (first, last) = if finish > along { (along + 1, finish) } else { (finish, along - 1) };
+
+    (first.max(low)..=last.min(high))
+        .map(|at| if horizontal { (at, across) } else { (across, at) })
+        .filter(|&point| window.contains(point))
+        .collect()
+}
+
+// None when the window is empty or has more than MAX_CELLS cells
+pub fn render(wires: &[Wire], window: &Window) -> Option<String> {
+    let (width, height) = segment::delta(window.low, window.high);
+    let (width, height) = (width + 1, height + 1);
+
+    if width < 1 || height < 1 || width.saturating_mul(height) > MAX_CELLS {
+        return None;
+    }
+
+    let (width, height) = (width as usize, height as usize);
+    let mut cells: Vec<Vec<Option<(char, usize)>>> = vec![vec![None; height]; width];
+
+    for (id, wire) in wires.iter().enumerate() {
+        let corners: HashSet<Point> = wire.points.windows(3)
+            .filter(|points| turns(points))
+            .map(|points| points[1])
+            .collect();
+
+        for pair in wire.points.windows(2) {
+            let mark = if pair[0].1 == pair[1].1 { '-' } else { '|' };
+
+            for point in covered(pair[0], pair[1], window) {
+                let mark = if corners.contains(&point) { '+' } else { mark };
+                let cell = &mut cells[(point.0 - window.low.0) as usize][(point.1 - window.low.1) as usize];
+
+                *cell = Some(match *cell {
+                    Some(('X', _)) => ('X', id),
+                    Some((_, other)) if other != id => ('X', id),
+                    Some((existing, _)) if existing != mark => ('+', id),
+                    _ => (mark, id)
+                });
+            }
+        }
+    }
+
+    let mut text = String::new();
+
+    for y in (window.low.1..=window.high.1).rev() {
+        for x in window.low.0..=window.high.0 {
+            text.push(if (x, y) == (0, 0) {
+                'o'
+            } else {
+                cells[(x - window.low.0) as usize][(y - window.low.1) as usize].map_or('.', |(mark, _)| mark)
+            });
+        }
+        text.push('\n');
+    }
+
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wires(wires: &[&str]) -> Vec<Wire> {
+        wires.iter().map(|wire| Wire::parse(wire)).collect()
+    }
+
+    #[test]
+    fn puzzle_picture() {
+        let wires = wires(&["R8,U5,L5,D3", "U7,R6,D4,L4"]);
+
+        let expected = "\
+            ...........\n\
+            .+-----+...\n\
+            .|.....|...\n\
+            .|..+--X-+.\n\
+            .|..|..|.|.\n\
+            .|.-X--+.|.\n\
+            .|..|....|.\n\
+            .|.......|.\n\
+            .o-------+.\n\
+            ...........\n";
+
+        assert_eq!(render(&wires, &Window::fit(&wires)).unwrap(), expected);
+    }
+
+    #[test]
+    fn cropped() {
+        let wires = wires(&["R8,U5,L5,D3", "U7,R6,D4,L4"]);
+
+        assert_eq!(render(&wires, &Window::around_origin(2, 1)).unwrap(), "..|..\n..o--\n.....\n");
+
+        // the same cells as in the whole picture, which starts at (-1, -1)
+        let whole = render(&wires, &Window::fit(&wires)).unwrap();
+        let rows: Vec<&str> = whole.lines().collect();
+        let expected: String = [2, 3, 4].iter().map(|&row| format!("{}\n", &rows[row][6..10])).collect();
+
+        assert_eq!(render(&wires, &Window { low: (5, 4), high: (8, 6) }).unwrap(), expected);
+    }
+
+    #[test]
+    fn crops_wide_wires() {
+        // far too wide to lay out whole
+        let wires = wires(&["R2000000000,U3", "U2,R2000000001"]);
+
+        assert_eq!(render(&wires, &Window { low: (1999999999, 1), high: (2000000001, 3) }).unwrap(), ".|.\n-X-\n.|.\n");
+    }
+
+    #[test]
+    fn refuses_large_windows() {
+        // wider than an i32, with the border left off at its lowest value
+        let wires = wires(&["L2147483647,L1", "R2000000000,U1"]);
+        let window = Window::fit(&wires);
+
+        assert_eq!(window, Window { low: (i32::MIN, -1), high: (2000000001, 2) });
+        assert_eq!(render(&wires, &window), None);
+
+        assert_eq!(render(&wires, &Window::around_origin(512, 512)), None);
+        assert_eq!(render(&wires, &Window { low: (1, 0), high: (0, 0) }), None);
+    }
+
+    #[test]
+    fn crossing_itself() {
+        let wires = wires(&["R4,U2,L2,D4"]);
+
+        let expected = "\
+            .......\n\
+            ...+-+.\n\
+            ...|.|.\n\
+            .o-+-+.\n\
+            ...|...\n\
+            ...|...\n\
+            .......\n";
+
+        assert_eq!(render(&wires, &Window::fit(&wires)).unwrap(), expected);
+    }
+}
@@ -0,0 +1,224 @@
+use std::cmp;
+use std::collections::{ BTreeMap, HashMap };
+use std::vec::Vec;
+
+use super::segment::{ manhattan, Point, SegmentIndex, Span };
+use super::wire::Wire;
+
+// every point where two or more wires meet, rather than just the two answers
+// WireGrid keeps. wires are identified by their position in the slice given
+
+// overlaps longer than this many points only list their ends and the point
+// nearest the centre, which are all the answers need, so wires running along
+// each other for millions of points don't get an entry for every one
+pub const MAX_OVERLAP: usize = 1 << 16;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Intersection {
+    pub point: Point,
+    // each wire meeting here, and the fewest steps it takes to get here
+    pub steps: BTreeMap<usize, usize>
+}
+
+// the best crossing between one pair of wires
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Pair {
+    pub closest: usize,
+    pub shortest: usize
+}
+
+// a wire passing back over itself, with the steps to the earlier and the
+// later visit
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SelfCrossing {
+    pub wire: usize,
+    pub point: Point,
+    pub steps: (usize, usize)
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Crossings {
+    // ordered by point
+    pub intersections: Vec<Intersection>,
+    // keyed by the pair of wires, lower one first
+    pub pairs: BTreeMap<(usize, usize), Pair>,
+    pub self_crossings: Vec<SelfCrossing>
+}
+
+impl Intersection {
+    pub fn wires(&self) -> impl Iterator<Item = usize> + '_ {
+        self.steps.keys().cloned()
+    }
+
+    pub fn distance(&self) -> usize {
+        manhattan(self.point, (0, 0))
+    }
+
+    // the fewest steps between them for any two of the wires meeting here
+    pub fn signal_delay(&self) -> usize {
+        let mut fewest: Vec<usize> = self.steps.values().cloned().collect();
+        fewest.sort();
+
+        fewest[0] + fewest[1]
+    }
+}
+
+fn points_of(span: &Span) -> Vec<Point> {
+    if span.len() <= MAX_OVERLAP {
+        return span.points().collect();
+    }
+
+    let mut points = vec!(span.from, span.nearest((0, 0)), span.to);
+    points.dedup();
+    points
+}
+
+impl Crossings {
+    pub fn find(wires: &[Wire]) -> Crossings {
+        let indexes: Vec<SegmentIndex> = wires.iter().map(SegmentIndex::new).collect();
+
+        let mut points: HashMap<Point, BTreeMap<usize, usize>> = HashMap::new();
+        let mut pairs = BTreeMap::new();
+        let mut self_crossings = Vec::new();
+
+        for (i, index) in indexes.iter().enumerate() {
+            index.crossings(index, |mine, theirs, span| {
+                // each pair of segments turns up both ways round
+                if mine.index < theirs.index {
+                    for point in points_of(&span) {
+                        self_crossings.push(SelfCrossing {
+                            wire: i,
+                            point: point,
+                            steps: (mine.steps_to(point), theirs.steps_to(point))
+                        });
+                    }
+                }
+            });
+
+            for (j, other) in indexes.iter().enumerate().skip(i + 1) {
+                let mut pair = Pair { closest: usize::MAX, shortest: usize::MAX };
+
+                index.crossings(other, |mine, theirs, span| {
+                    for point in points_of(&span) {
+                        let steps = points.entry(point).or_default();
+
+                        for &(wire, taken) in [(i, mine.steps_to(point)), (j, theirs.steps_to(point))].iter() {
+                            let fewest = steps.entry(wire).or_insert(taken);
+                            *fewest = cmp::min(*fewest, taken);
+                        }
+                    }
+
+                    pair.closest = cmp::min(pair.closest, manhattan(span.nearest((0, 0)), (0, 0)));
+
+                    for &point in [span.from, span.to].iter() {
+                        pair.shortest = cmp::min(pair.shortest, mine.steps_to(point) + theirs.steps_to(point));
+                    }
+                });
+
+                if pair.closest != usize::MAX {
+                    pairs.insert((i, j), pair);
+                }
+            }
+        }
+
+        let mut intersections: Vec<Intersection> = points.into_iter()
+            .map(|(point, steps)| Intersection { point: point, steps: steps })
+            .collect();
+        intersections.sort_by_key(|intersection| intersection.point);
+
+        self_crossings.sort_by_key(|crossing| (crossing.wire, crossing.point, crossing.steps));
+
+        Crossings {
+            intersections: intersections,
+            pairs: pairs,
+            self_crossings: self_crossings
+        }
+    }
+
+    // where at least this many wires meet
+    pub fn shared(&self, wires: usize) -> impl Iterator<Item = &Intersection> {
+        self.intersections.iter().filter(move |intersection| intersection.steps.len() >= wires)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wires(wires: &[&str]) -> Vec<Wire> {
+        wires.iter().map(|wire| Wire::parse(wire)).collect()
+    }
+
+    #[test]
+    fn two_wires() {
+        let crossings = Crossings::find(&wires(&["R8,U5,L5,D10", "U7,R6,D4,L4"]));
+
+        let points: Vec<Point> = crossings.intersections.iter().map(|i| i.point).collect();
+        assert_eq!(points, vec!((3, 3), (6, 5)));
+
+        let steps: Vec<(usize, usize)> = crossings.intersections[1].steps.iter().map(|(&w, &s)| (w, s)).collect();
+        assert_eq!(steps, vec!((0, 15), (1, 15)));
+
+        assert_eq!(crossings.pairs[&(0, 1)], Pair { closest: 6, shortest: 30 });
+        // the first wire's last leg runs back across its first
+        assert_eq!(crossings.self_crossings, vec!(SelfCrossing { wire: 0, point: (3, 0), steps: (3, 23) }));
+    }
+
+    #[test]
+    fn three_wires_at_one_point() {
+        // the third wire runs along the first and then up through (6, 5),
+        // where the first two cross
+        let crossings = Crossings::find(&wires(&["R8,U5,L5,D10", "U7,R6,D4,L4", "R6,U10"]));
+
+        let shared: Vec<&Intersection> = crossings.shared(3).collect();
+        assert_eq!(shared.len(), 1);
+        assert_eq!(shared[0].point, (6, 5));
+        assert_eq!(shared[0].wires().collect::<Vec<usize>>(), vec!(0, 1, 2));
+        assert_eq!(shared[0].steps[&2], 11);
+        assert_eq!(shared[0].signal_delay(), 11 + 15);
+
+        assert_eq!(crossings.pairs.keys().cloned().collect::<Vec<_>>(), vec!((0, 1), (0, 2), (1, 2)));
+        assert_eq!(crossings.pairs[&(0, 2)], Pair { closest: 1, shortest: 2 });
+        assert_eq!(crossings.pairs[&(1, 2)], Pair { closest: 9, shortest: 26 });
+    }
+
+    #[test]
+    fn self_crossings_kept_apart() {
+        // loops back over its own start and first leg
+        let crossings = Crossings::find(&wires(&["R4,U2,L2,D4", "L1,U1,R10"]));
+
+        assert_eq!(crossings.self_crossings, vec!(SelfCrossing { wire: 0, point: (2, 0), steps: (2, 10) }));
+        assert!(crossings.intersections.iter().all(|i| i.steps.len() == 2));
+        assert_eq!(crossings.pairs[&(0, 1)], Pair { closest: 3, shortest: 12 });
+
+        // a wire doubling back runs over itself
+        let crossings = Crossings::find(&wires(&["R5,L3"]));
+        let points: Vec<Point> = crossings.self_crossings.iter().map(|c| c.point).collect();
+
+        assert_eq!(points, vec!((2, 0), (3, 0), (4, 0)));
+        assert_eq!(crossings.self_crossings[0].steps, (2, 8));
+    }
+
+    #[test]
+    fn overlaps_count_every_point() {
+        let crossings = Crossings::find(&wires(&["R10", "U1,R2,D1,R5"]));
+
+        let points: Vec<Point> = crossings.intersections.iter().map(|i| i.point).collect();
+        assert_eq!(points, vec!((2, 0), (3, 0), (4, 0), (5, 0), (6, 0), (7, 0)));
+        assert_eq!(crossings.pairs[&(0, 1)], Pair { closest: 2, shortest: 6 });
+    }
+
+    #[test]
+    fn long_overlaps_keep_their_ends() {
+        let crossings = Crossings::find(&wires(&["L5,R2000000000", "D1,L3,U1,R1000000000"]));
+
+        let points: Vec<Point> = crossings.intersections.iter().map(|i| i.point).collect();
+        assert_eq!(points, vec!((-3, 0), (-2, 0), (-1, 0), (0, 0), (999999997, 0)));
+        assert_eq!(crossings.pairs[&(0, 1)], Pair { closest: 0, shortest: 8 });
+
+        let crossings = Crossings::find(&wires(&["R2000000000,L1000000000"]));
+        let points: Vec<Point> = crossings.self_crossings.iter().map(|c| c.point).collect();
+
+        assert_eq!(points, vec!((1000000000, 0), (1999999999, 0)));
+    }
+}
@@ -0,0 +1,136 @@
+use std::cmp::Ordering;
+use std::vec::Vec;
+
+use super::crossings::{ Crossings, Intersection };
+use super::segment::{ self, Point };
+
+// how near an intersection is. the first three measure from an origin; the
+// signal delay is the fewest steps two wires take to meet there, wherever
+// the origin is
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Metric {
+    Manhattan,
+    Chebyshev,
+    Euclidean,
+    SignalDelay
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Query {
+    pub metric: Metric,
+    pub origin: Point
+}
+
+impl Default for Query {
+    // the puzzle's first question
+    fn default() -> Query {
+        Query {
+            metric: Metric::Manhattan,
+            origin: (0, 0)
+        }
+    }
+}
+
+impl Metric {
+    pub fn between(&self, a: Point, b: Point) -> Option<f64> {
+        let (dx, dy) = segment::delta(a, b);
+        let (dx, dy) = (dx.abs() as f64, dy.abs() as f64);
+
+        match self {
+            Metric::Manhattan => Some(segment::manhattan(a, b) as f64),
+            Metric::Chebyshev => Some(dx.max(dy)),
+            Metric::Euclidean => Some((dx * dx + dy * dy).sqrt()),
+            Metric::SignalDelay => None
+        }
+    }
+
+    pub fn measure(&self, origin: Point, intersection: &Intersection) -> f64 {
+        match self {
+            Metric::SignalDelay => intersection.signal_delay() as f64,
+            geometric => geometric.between(origin, intersection.point).unwrap()
+        }
+    }
+}
+
+impl Crossings {
+    // the k intersections nearest by the query, nearest first, each with its
+    // distance. ties go to the lower point
+    pub fn nearest(&self, query: &Query, k: usize) -> Vec<(&Intersection, f64)> {
+        let mut measured: Vec<(&Intersection, f64)> = self.intersections.iter()
+            .map(|intersection| (intersection, query.metric.measure(query.origin, intersection)))
+            .collect();
+
+        measured.sort_by(|a, b| {
+            a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal).then(a.0.point.cmp(&b.0.point))
+        });
+        measured.truncate(k);
+
+        measured
+    }
+
+    pub fn closest(&self, query: &Query) -> Option<(&Intersection, f64)> {
+        self.nearest(query, 1).pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day3::wire::Wire;
+
+    fn crossings(wires: &[&str]) -> Crossings {
+        let wires: Vec<Wire> = wires.iter().map(|wire| Wire::parse(wire)).collect();
+
+        Crossings::find(&wires)
+    }
+
+    fn nearest(crossings: &Crossings, metric: Metric, origin: Point, k: usize) -> Vec<(Point, f64)> {
+        crossings.nearest(&Query { metric: metric, origin: origin }, k)
+            .into_iter()
+            .map(|(intersection, distance)| (intersection.point, distance))
+            .collect()
+    }
+
+    #[test]
+    fn metrics() {
+        let crossings = crossings(&["R8,U5,L5,D10", "U7,R6,D4,L4"]);
+
+        assert_eq!(nearest(&crossings, Metric::Manhattan, (0, 0), 2), vec!(((3, 3), 6.0), ((6, 5), 11.0)));
+        assert_eq!(nearest(&crossings, Metric::Chebyshev, (0, 0), 2), vec!(((3, 3), 3.0), ((6, 5), 6.0)));
+        assert_eq!(nearest(&crossings, Metric::Euclidean, (0, 0), 1), vec!(((3, 3), 18f64.sqrt())));
+        assert_eq!(nearest(&crossings, Metric::SignalDelay, (0, 0), 2), vec!(((6, 5), 30.0), ((3, 3), 40.0)));
+
+        let answer = crossings.closest(&Query::default()).unwrap();
+        assert_eq!((answer.0.point, answer.1), ((3, 3), 6.0));
+    }
+
+    #[test]
+    fn other_origins() {
+        let crossings = crossings(&["R8,U5,L5,D10", "U7,R6,D4,L4"]);
+
+        assert_eq!(nearest(&crossings, Metric::Manhattan, (7, 6), 1), vec!(((6, 5), 2.0)));
+        assert_eq!(nearest(&crossings, Metric::Chebyshev, (5, 4), 2), vec!(((6, 5), 1.0), ((3, 3), 2.0)));
+
+        // delay doesn't depend on where it's measured from
+        assert_eq!(nearest(&crossings, Metric::SignalDelay, (100, -100), 1), vec!(((6, 5), 30.0)));
+        assert_eq!(Metric::SignalDelay.between((0, 0), (1, 1)), None);
+
+        // further from the crossings than an i32 reaches
+        let far = (-2_000_000_000, 0);
+        assert_eq!(nearest(&crossings, Metric::Chebyshev, far, 1), vec!(((3, 3), 2_000_000_003.0)));
+        assert_eq!(Metric::Euclidean.between(far, (2_000_000_000, 0)), Some(4_000_000_000.0));
+        assert_eq!(Metric::Manhattan.between(far, (2_000_000_000, 1)), Some(4_000_000_001.0));
+    }
+
+    #[test]
+    fn how_many() {
+        let crossings = crossings(&["R10", "U1,R2,D1,R5"]);
+
+        let points: Vec<Point> = nearest(&crossings, Metric::Euclidean, (5, 3), 3).iter().map(|&(point, _)| point).collect();
+        assert_eq!(points, vec!((5, 0), (4, 0), (6, 0)));
+
+        assert_eq!(nearest(&crossings, Metric::Manhattan, (0, 0), 100).len(), 6);
+        assert!(nearest(&crossings, Metric::Manhattan, (0, 0), 0).is_empty());
+        assert!(Crossings::default().closest(&Query::default()).is_none());
+    }
+}
@@ -0,0 +1,270 @@
+use std::cmp;
+use std::vec::Vec;
+
+use super::wire::Wire;
+
+pub type Point = (i32, i32);
+
+// one straight run of a wire. like Wire::expand, it covers the points after
+// start up to and including end, so the point a wire leaves from isn't on it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Segment {
+    pub start: Point,
+    pub end: Point,
+    // steps along the wire to reach start
+    pub steps: usize,
+    // position among the wire's segments
+    pub index: usize
+}
+
+// a straight run of points shared by two segments, from <= to. a single
+// point when the segments cross, longer when they overlap end to end
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Span {
+    pub from: Point,
+    pub to: Point
+}
+
+// a wire's segments split by orientation, each sorted by the line they lie
+// on and then where they begin, so crossings can be found by search rather
+// than by laying the wire out on a grid
+#[derive(Clone, PartialEq, Debug)]
+pub struct SegmentIndex {
+    horizontal: Vec<Segment>,
+    vertical: Vec<Segment>
+}
+
+// how far b is from a along each axis. in i64, since points at opposite ends
+// of the i32 range are further apart than an i32 holds
+pub fn delta(a: Point, b: Point) -> (i64, i64) {
+    (b.0 as i64 - a.0 as i64, b.1 as i64 - a.1 as i64)
+}
+
+pub fn manhattan(a: Point, b: Point) -> usize {
+    let (dx, dy) = delta(a, b);
+
+    (dx.abs() + dy.abs()) as usize
+}
+
+impl Segment {
+    pub fn is_horizontal(&self) -> bool {
+        self.start.1 == self.end.1
+    }
+
+    // the fixed coordinate
+    fn line(&self) -> i32 {
+        if self.is_horizontal() { self.start.1 } else { self.start.0 }
+    }
+
+    fn along(&self, point: Point) -> i32 {
+        if self.is_horizontal() { point.0 } else { point.1 }
+    }
+
+    fn at(&self, along: i32) -> Point {
+        if self.is_horizontal() { (along, self.line()) } else { (self.line(), along) }
+    }
+
+    // lowest and highest coordinate along the segment, start included
+    fn range(&self) -> (i32, i32) {
+        let (a, b) = (self.along(self.start), self.along(self.end));
+
+        (cmp::min(a, b), cmp::max(a, b))
+    }
+
+    pub fn steps_to(&self, point: Point) -> usize {
+        self.steps + manhattan(self.start, point)
+    }
+
+    pub fn intersect(&self, other: &Segment) -> Option<Span> {
+        if self.is_horizontal() != other.is_horizontal() {
+            let (h, v) = if self.is_horizontal() { (self, other) } else { (other, self) };
+            let point = (v.line(), h.line());
+
+            let (x_lo, x_hi) = h.range();
+            let (y_lo, y_hi) = v.range();
+
+            if point.0 < x_lo || point.0 > x_hi || point.1 < y_lo || point.1 > y_hi
+                || point == self.start || point == other.start
+            {
+                return None;
+            }
+
+            return Some(Span { from: point, to: point });
+        }
+
+        if self.line() != other.line() {
+            return None;
+        }
+
+        let (a_lo, a_hi) = self.range();
+        let (b_lo, b_hi) = other.range();
+        let mut lo = cmp::max(a_lo, b_lo);
+        let mut hi = cmp::min(a_hi, b_hi);
+
+        // a start can only sit at an end of the overlap, since the overlap
+        // lies within both segments
+        for &start in [self.start, other.start].iter() {
+            let start = self.along(start);
+
+            if start == lo {
+                lo += 1;
+            } else if start == hi {
+                hi -= 1;
+            }
+        }
+
+        if lo > hi {
+            return None;
+        }
+
+        Some(Span { from: self.at(lo), to: self.at(hi) })
+    }
+}
+
+impl Span {
+    // a span always holds at least one point, so it is never empty
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        manhattan(self.from, self.to) + 1
+    }
+
+    // the point of the span closest to a given one
+    pub fn nearest(&self, point: Point) -> Point {
+        (cmp::min(cmp::max(point.0, self.from.0), self.to.0),
+         cmp::min(cmp::max(point.1, self.from.1), self.to.1))
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = Point> {
+        let from = (self.from.0 as i64, self.from.1 as i64);
+        let (dx, dy) = ((self.to.0 as i64 - from.0).signum(), (self.to.1 as i64 - from.1).signum());
+
+        // every point lies between from and to, so back in i32 range
+        (0..self.len() as i64).map(move |i| ((from.0 + i * dx) as i32, (from.1 + i * dy) as i32))
+    }
+}
+
+pub fn segments(wire: &Wire) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut steps = 0;
+
+    for (index, pair) in wire.points.windows(2).enumerate() {
+        let (start, end) = (pair[0], pair[1]);
+
+        // a zero length move covers no points
+        if start != end {
+            segments.push(Segment { start: start, end: end, steps: steps, index: index });
+        }
+
+        steps += manhattan(start, end);
+    }
+
+    segments
+}
+
+impl SegmentIndex {
+    pub fn new(wire: &Wire) -> SegmentIndex {
+        let (mut horizontal, mut vertical): (Vec<Segment>, Vec<Segment>) = segments(wire)
+            .into_iter()
+            .partition(Segment::is_horizontal);
+
+        horizontal.sort_by_key(|segment| (segment.line(), segment.range().0));
+        vertical.sort_by_key(|segment| (segment.line(), segment.range().0));
+
+        SegmentIndex {
+            horizontal: horizontal,
+            vertical: vertical
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.horizontal.len() + self.vertical.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.horizontal.is_empty() && self.vertical.is_empty()
+    }
+
+    // calls found with each of this wire's segments that meets one of the
+    // other's, that segment, and where they meet
+    pub fn crossings<F: FnMut(&Segment, &Segment, Span)>(&self, other: &SegmentIndex, mut found: F) {
+        let mut visit = |mine: &[Segment], theirs: &[Segment]| {
+            for segment in mine.iter() {
+                let (lo, hi) = segment.range();
+
+                // perpendicular segments whose line falls within this one's range
+                let perpendicular = if segment.is_horizontal() { &other.vertical } else { &other.horizontal };
+                let first = perpendicular.partition_point(|s| s.line() < lo);
+                let last = perpendicular.partition_point(|s| s.line() <= hi);
+
+                // and parallel ones on the same line that begin before it ends
+                let first_parallel = theirs.partition_point(|s| s.line() < segment.line());
+                let last_parallel = theirs.partition_point(|s| (s.line(), s.range().0) <= (segment.line(), hi));
+
+                let candidates = perpendicular[first..last].iter()
+                    .chain(theirs[first_parallel..last_parallel].iter());
+
+                for candidate in candidates {
+                    if let Some(span) = segment.intersect(candidate) {
+                        found(segment, candidate, span);
+                    }
+                }
+            }
+        };
+
+        visit(&self.horizontal, &other.horizontal);
+        visit(&self.vertical, &other.vertical);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: Point, end: Point) -> Segment {
+        Segment { start: start, end: end, steps: 0, index: 0 }
+    }
+
+    #[test]
+    fn crossing_segments() {
+        let h = segment((0, 2), (10, 2));
+        let v = segment((4, -3), (4, 8));
+
+        assert_eq!(h.intersect(&v), Some(Span { from: (4, 2), to: (4, 2) }));
+        assert_eq!(v.intersect(&h), Some(Span { from: (4, 2), to: (4, 2) }));
+
+        // touching at an end counts, but not at the start
+        assert_eq!(h.intersect(&segment((10, 2), (10, 5))), None);
+        assert_eq!(h.intersect(&segment((10, 0), (10, 5))), Some(Span { from: (10, 2), to: (10, 2) }));
+        assert_eq!(h.intersect(&segment((0, 5), (0, -1))), None);
+        assert_eq!(h.intersect(&segment((11, 5), (11, -1))), None);
+    }
+
+    #[test]
+    fn overlapping_segments() {
+        let a = segment((0, 0), (10, 0));
+
+        assert_eq!(a.intersect(&segment((15, 0), (3, 0))), Some(Span { from: (3, 0), to: (10, 0) }));
+        assert_eq!(a.intersect(&segment((5, 0), (8, 0))), Some(Span { from: (6, 0), to: (8, 0) }));
+        assert_eq!(a.intersect(&segment((5, 0), (-8, 0))), Some(Span { from: (1, 0), to: (4, 0) }));
+        assert_eq!(a.intersect(&segment((0, 0), (-8, 0))), None);
+        assert_eq!(a.intersect(&segment((10, 0), (20, 0))), None);
+        assert_eq!(a.intersect(&segment((10, 1), (20, 1))), None);
+
+        let span = Span { from: (-3, 7), to: (5, 7) };
+        assert_eq!(span.len(), 9);
+        assert_eq!(span.nearest((0, 0)), (0, 7));
+        assert_eq!(span.points().nth(2), Some((-1, 7)));
+    }
+
+    #[test]
+    fn far_apart() {
+        assert_eq!(manhattan((i32::MIN, i32::MAX), (i32::MAX, i32::MIN)), 4 * (i32::MAX as usize) + 2);
+
+        let span = Span { from: (-2_000_000_000, 3), to: (2_000_000_000, 3) };
+        assert_eq!(span.len(), 4_000_000_001);
+        assert_eq!(span.points().nth(1), Some((-1_999_999_999, 3)));
+
+        let long = Segment { start: (-2_000_000_000, 0), end: (2_000_000_000, 0), steps: 5, index: 0 };
+        assert_eq!(long.steps_to((1_000_000_000, 0)), 3_000_000_005);
+    }
+}
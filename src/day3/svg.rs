@@ -0,0 +1,214 @@
+use std::cmp;
+use std::fmt::Write;
+use std::vec::Vec;
+
+use super::crossings::{ Crossings, Intersection };
+use super::segment::{ delta, Point };
+use super::wire::Wire;
+
+// draws wires as an svg picture for reviewing an input: one coloured line per
+// wire, the origin, a dot at every intersection, and the closest and fewest
+// steps crossings ringed and labelled. up is up, as in the puzzle
+
+const COLOURS: [&str; 6] = ["#d62728", "#1f77b4", "#2ca02c", "#9467bd", "#ff7f0e", "#17becf"];
+const HIGHLIGHT: &str = "#000000";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Scale {
+    // pixels per step of wire
+    pub unit: f64,
+    // pixels of space around the wires
+    pub margin: f64,
+    // line width and marker size, in pixels
+    pub stroke: f64
+}
+
+impl Default for Scale {
+    fn default() -> Scale {
+        Scale {
+            unit: 1.0,
+            margin: 20.0,
+            stroke: 1.0
+        }
+    }
+}
+
+impl Scale {
+    // whatever unit makes the wires fill a picture about this wide or high
+    pub fn fit(wires: &[Wire], size: f64) -> Scale {
+        let (low, high) = bounds(wires);
+        let (dx, dy) = delta(low, high);
+        let extent = cmp::max(dx, dy).max(1) as f64;
+
+        Scale {
+            unit: size / extent,
+            ..Scale::default()
+        }
+    }
+}
+
+// lowest and highest x and y over all the wires and the origin
+fn bounds(wires: &[Wire]) -> (Point, Point) {
+    let mut low = (0, 0);
+    let mut high = (0, 0);
+
+    for wire in wires.iter() {
+        high.0 = cmp::max(high.0, wire.extents.0);
+        high.1 = cmp::max(high.1, wire.extents.1);
+        low.0 = cmp::min(low.0, wire.extents.2);
+        low.1 = cmp::min(low.1, wire.extents.3);
+    }
+
+    (low, high)
+}
+
+// two decimal places at most, without trailing zeros
+fn number(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+
+    if text == "-0" { String::from("0") } else { String::from(text) }
+}
+
+struct Canvas {
+    low: Point,
+    high: Point,
+    scale: Scale
+}
+
+impl Canvas {
+    fn size(&self) -> (f64, f64) {
+        let (dx, dy) = delta(self.low, self.high);
+
+        (dx as f64 * self.scale.unit + 2.0 * self.scale.margin,
+         dy as f64 * self.scale.unit + 2.0 * self.scale.margin)
+    }
+
+    // y is flipped, so counts down from the top
+    fn at(&self, point: Point) -> (String, String) {
+        let (x, _) = delta(self.low, point);
+        let (_, y) = delta(point, self.high);
+
+        (number(self.scale.margin + x as f64 * self.scale.unit),
+         number(self.scale.margin + y as f64 * self.scale.unit))
+    }
+}
+
+pub fn render(wires: &[Wire], crossings: &Crossings, scale: &Scale) -> String {
+    let (low, high) = bounds(wires);
+    let canvas = Canvas { low: low, high: high, scale: *scale };
+    let (width, height) = canvas.size();
+    let stroke = number(scale.stroke);
+
+    let mut svg = String::new();
+
+    writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">",
+        number(width), number(height)).unwrap();
+    writeln!(svg, "  <rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>").unwrap();
+
+    for (id, wire) in wires.iter().enumerate() {
+        let points: Vec<String> = wire.points.iter()
+            .map(|&point| {
+                let (x, y) = canvas.at(point);
+                format!("{},{}", x, y)
+            })
+            .collect();
+
+        writeln!(svg, "  <polyline class=\"wire\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" points=\"{}\"><title>wire {}</title></polyline>",
+            COLOURS[id % COLOURS.len()], stroke, points.join(" "), id).unwrap();
+    }
+
+    for intersection in crossings.intersections.iter() {
+        let (x, y) = canvas.at(intersection.point);
+
+        writeln!(svg, "  <circle class=\"crossing\" cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>",
+            x, y, number(scale.stroke * 2.0), HIGHLIGHT).unwrap();
+    }
+
+    let (x, y) = canvas.at((0, 0));
+    writeln!(svg, "  <circle class=\"origin\" cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"/>",
+        x, y, number(scale.stroke * 4.0), HIGHLIGHT, stroke).unwrap();
+
+    let closest = crossings.intersections.iter().min_by_key(|intersection| intersection.distance());
+    let fewest = crossings.intersections.iter().min_by_key(|intersection| intersection.signal_delay());
+
+    if let Some(closest) = closest {
+        highlight(&mut svg, &canvas, closest, &format!("closest {}", closest.distance()));
+    }
+    if let Some(fewest) = fewest {
+        highlight(&mut svg, &canvas, fewest, &format!("fewest steps {}", fewest.signal_delay()));
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn highlight(svg: &mut String, canvas: &Canvas, intersection: &Intersection, label: &str) {
+    let (x, y) = canvas.at(intersection.point);
+    let stroke = canvas.scale.stroke;
+
+    writeln!(svg, "  <circle class=\"highlight\" cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"/>",
+        x, y, number(stroke * 6.0), HIGHLIGHT, number(stroke)).unwrap();
+    writeln!(svg, "  <text x=\"{}\" y=\"{}\" dx=\"{}\" font-family=\"monospace\" font-size=\"{}\">{}</text>",
+        x, y, number(stroke * 8.0), number(stroke * 10.0), label).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wires(wires: &[&str]) -> Vec<Wire> {
+        wires.iter().map(|wire| Wire::parse(wire)).collect()
+    }
+
+    #[test]
+    fn draws_the_example() {
+        let wires = wires(&["R8,U5,L5,D10", "U7,R6,D4,L4"]);
+        let crossings = Crossings::find(&wires);
+        let scale = Scale { unit: 10.0, margin: 5.0, stroke: 1.0 };
+
+        let svg = render(&wires, &crossings, &scale);
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"90\" height=\"130\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("class=\"wire\"").count(), 2);
+        assert_eq!(svg.matches("class=\"crossing\"").count(), 2);
+        assert_eq!(svg.matches("class=\"highlight\"").count(), 2);
+
+        // x runs from 0 and y down from 7, with a margin of 5
+        assert!(svg.contains("points=\"5,75 85,75 85,25 35,25 35,125\""));
+        assert!(svg.contains("class=\"origin\" cx=\"5\" cy=\"75\""));
+        assert!(svg.contains(">closest 6</text>"));
+        assert!(svg.contains(">fewest steps 30</text>"));
+    }
+
+    #[test]
+    fn without_crossings() {
+        let wires = wires(&["R8"]);
+        let svg = render(&wires, &Crossings::find(&wires), &Scale::default());
+
+        assert!(!svg.contains("crossing"));
+        assert!(!svg.contains("<text"));
+    }
+
+    #[test]
+    fn wider_than_an_i32() {
+        let wires = wires(&["L2000000000,U5", "R2000000000,U5"]);
+        let scale = Scale::fit(&wires, 400.0);
+
+        let svg = render(&wires, &Crossings::find(&wires), &scale);
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"440\" height=\"40\""));
+        assert!(svg.contains("class=\"origin\" cx=\"220\" cy=\"20\""));
+    }
+
+    #[test]
+    fn fitting() {
+        let wires = wires(&["R800,U200", "U400"]);
+        let scale = Scale::fit(&wires, 200.0);
+
+        assert_eq!(scale.unit, 0.25);
+        assert_eq!(number(0.1 + 0.2), "0.3");
+        assert_eq!(number(-0.001), "0");
+    }
+}
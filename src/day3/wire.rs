@@ -1,5 +1,4 @@
 use std::vec::Vec;
-use std::ops::Range;
 use std::cmp;
 
 #[derive(PartialEq, Debug)]
@@ -56,6 +55,11 @@ impl Wire {
         }
     }
 
+    // one line of the puzzle input, like "R8,U5,L5,D10"
+    pub fn parse(steps: &str) -> Wire {
+        Wire::from_csv_reader(csv::ReaderBuilder::new().has_headers(false).from_reader(steps.as_bytes()))
+    }
+
     pub fn expand(&self) -> Vec<(i32, i32)> {
         let mut pts = Vec::new();
 
@@ -96,6 +100,7 @@ impl Wire {
         pts
     }
 
+    #[allow(dead_code)]
     fn eq(&self, other: &Self) -> bool {
         let extents_eq = self.extents == other.extents;
         let points_eq = self.points == other.points;
@@ -124,6 +129,7 @@ mod tests {
         let expected = Wire { extents: extents, points: points };
 
         assert_eq!(wire, expected);
+        assert_eq!(Wire::parse(csv_input), wire);
     }
 
     #[test]
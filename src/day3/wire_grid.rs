@@ -1,26 +1,21 @@
 use std::vec::Vec;
 use std::cmp;
 
+use super::segment::SegmentIndex;
 use super::wire::Wire;
 
-fn unsigned_diff(n1: usize, n2: usize) -> usize {
-    if n1 < n2 {
-        n2 - n1
-    } else {
-        n1 - n2
-    }
-}
-
+// crossings between wires, found segment against segment, so memory grows
+// with the number of segments rather than with the area the wires cover
 #[derive(PartialEq, Debug)]
 pub struct WireGrid {
-    grid: Vec<Vec<(i32, usize)>>,
+    wires: Vec<(i32, SegmentIndex)>,
     center: (usize, usize),
     pub closest: usize,
     pub shortest: usize
 }
 
 impl WireGrid {
-    pub fn from_wires(wires: &Vec<Wire>) -> WireGrid {
+    pub fn from_wires(wires: &[Wire]) -> WireGrid {
         let mut extremes = (0, 0, 0, 0);
 
         for wire in wires.iter() {
@@ -30,62 +25,157 @@ impl WireGrid {
             extremes.3 = cmp::min(extremes.3, wire.extents.3);
         }
 
-        let size = ((extremes.0 + extremes.2.abs() + 1) as usize, 
-                   (extremes.1 + extremes.3.abs() + 1) as usize);
-
-        let grid: Vec<Vec<(i32, usize)>> = vec![vec![(-1, 0); size.1]; size.0];
-
-        let center = (extremes.2.abs() as usize,
-                      extremes.3.abs() as usize);
+        let center = (extremes.2.unsigned_abs() as usize,
+                      extremes.3.unsigned_abs() as usize);
 
         WireGrid {
-            grid: grid,
+            wires: Vec::new(),
             center: center,
-            closest: std::usize::MAX,
-            shortest: std::usize::MAX
-        }        
+            closest: usize::MAX,
+            shortest: usize::MAX
+        }
     }
-    
+
+    // a wire only crosses wires with another id. steps count to the first
+    // time each wire reaches a crossing
     pub fn add_wire(&mut self, wire: &Wire, id: i32) {
-        let pts = wire.expand();
+        let index = SegmentIndex::new(wire);
 
-        for (i, pt) in pts.iter().enumerate() {
-            let (x, y) = self.to_grid(&pt);
+        let mut nearest = Vec::new();
+        let mut shortest = self.shortest;
 
-            let grid_id = self.grid[x][y].0;
-            let grid_time = self.grid[x][y].1;
+        for (other_id, other) in self.wires.iter() {
+            if *other_id == id {
+                continue;
+            }
 
-            if grid_id != -1 && grid_id != id {
-                self.closest = cmp::min(self.closest,
-                                        self.distance_to_center(&(x, y)));
+            index.crossings(other, |mine, theirs, span| {
+                nearest.push(span.nearest((0, 0)));
 
-                self.shortest = cmp::min(self.shortest,
-                                         grid_time + i + 1);
-            }
+                // steps change by one per point along a span for both wires,
+                // so the fewest are at one of its ends
+                for &point in [span.from, span.to].iter() {
+                    shortest = cmp::min(shortest, mine.steps_to(point) + theirs.steps_to(point));
+                }
+            });
+        }
 
-            self.grid[x][y] = (id, i + 1);
+        for point in nearest.iter() {
+            self.closest = cmp::min(self.closest, self.distance_to_center(&self.to_grid(point)));
         }
+        self.shortest = shortest;
+
+        self.wires.push((id, index));
     }
 
     fn to_grid(&self, point: &(i32, i32)) -> (usize, usize) {
-        ((point.0 + self.center.0 as i32) as usize,
-         (point.1 + self.center.1 as i32) as usize)
+        ((point.0 as i64 + self.center.0 as i64) as usize,
+         (point.1 as i64 + self.center.1 as i64) as usize)
     }
 
     fn distance_to_center(&self, grid_point: &(usize, usize)) -> usize {
-        unsigned_diff(self.center.0, grid_point.0) + 
-            unsigned_diff(self.center.1, grid_point.1)
+        self.center.0.abs_diff(grid_point.0) + self.center.1.abs_diff(grid_point.1)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::day3::ascii::{ self, Window };
+
+    fn parse(wires: &[&str]) -> Vec<Wire> {
+        wires.iter().map(|wire| Wire::parse(wire)).collect()
+    }
+
+    fn solve(wires: &[&str]) -> (usize, usize) {
+        let wires = parse(wires);
+
+        let mut grid = WireGrid::from_wires(&wires);
+        for (id, wire) in wires.iter().enumerate() {
+            grid.add_wire(wire, id as i32);
+        }
+
+        (grid.closest, grid.shortest)
+    }
+
+    // what the dense grid found, one unit point at a time
+    fn expanded(wires: &[&str]) -> (usize, usize) {
+        let wires: Vec<Vec<(i32, i32)>> = wires.iter()
+            .map(|wire| Wire::parse(wire).expand())
+            .collect();
+
+        let mut closest = usize::MAX;
+        let mut shortest = usize::MAX;
+
+        for (i, pt) in wires[1].iter().enumerate() {
+            if let Some(j) = wires[0].iter().position(|other| other == pt) {
+                closest = cmp::min(closest, (pt.0.abs() + pt.1.abs()) as usize);
+                shortest = cmp::min(shortest, i + j + 2);
+            }
+        }
+
+        (closest, shortest)
+    }
+
+    #[test]
+    fn examples() {
+        assert_eq!(solve(&["R8,U5,L5,D10", "U7,R6,D4,L4"]), (6, 30));
+        assert_eq!(solve(&["R75,D30,R83,U83,L12,D49,R71,U7,L72",
+                           "U62,R66,U55,R34,D71,R55,D58,R83"]), (159, 610));
+        assert_eq!(solve(&["R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51",
+                           "U98,R91,D20,R16,D67,R40,U7,R15,U6,R7"]), (135, 410));
+    }
+
+    #[test]
+    fn overlaps_match_the_expanded_wires() {
+        let cases: [[&str; 2]; 4] = [
+            ["R10,U3,L4,D5", "U1,R12,L9,D1,R3"],
+            ["L5,R9,U2", "D1,L3,U1,R20"],
+            ["U4,R0,D8,L2", "R2,U4,L2,D1"],
+            ["R5,L3,U1", "R1,U2,R5,D7"]
+        ];
+
+        // the message, and so the picture, is only built when the check fails
+        for case in cases.iter() {
+            let wires = parse(case);
+
+            assert_eq!(solve(case), expanded(case), "{:?}\n{}", case, ascii::render(&wires, &Window::fit(&wires)).unwrap_or_default());
+        }
+    }
+
+    #[test]
+    fn wide_wires() {
+        // far too large to lay out on a grid
+        let wires = ["R900000000,U900000000,L800000000,D1000000000",
+                     "U700000000,R600000000,D400000000,L400000000"];
+
+        assert_eq!(solve(&wires), (800000000, 3600000000));
+
+        let scaled = ["R9,U9,L8,D10", "U7,R6,D4,L4"];
+        assert_eq!(expanded(&scaled), (8, 36));
+
+        // the crossing is further from the wires' left edge than an i32 holds
+        let wires = ["L1200000000,U5,R1200000000,R1000000000,D10", "R1000000000,U10"];
+        assert_eq!(solve(&wires), (1000000000, 4400000010));
+    }
+
+    #[test]
+    fn crossing_past_i32() {
+        // both the distance and the steps to the crossing overflow an i32
+        let wires = ["R1200000000,U1200000000", "U1200000000,R1300000000"];
+
+        assert_eq!(solve(&wires), (2400000000, 4800000000));
+    }
+
+    #[test]
+    fn one_wire_alone() {
+        assert_eq!(solve(&["R8,U5,L5,D10"]), (usize::MAX, usize::MAX));
+    }
 
     #[test]
     fn to_grid() {
         let grid = WireGrid {
-            grid: Vec::new(),
+            wires: Vec::new(),
             center: (5, 10),
             closest: 0,
             shortest: 0
@@ -99,7 +189,7 @@ mod tests {
     #[test]
     fn distance_to_center() {
         let grid = WireGrid {
-            grid: Vec::new(),
+            wires: Vec::new(),
             center: (5, 10),
             closest: 0,
             shortest: 0
@@ -1,4 +1,4 @@
-mod int_code;
+pub mod int_code;
 
 use int_code::IntCode;
 use crate::util;
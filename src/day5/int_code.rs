@@ -1,9 +1,9 @@
 #[allow(dead_code)]
-
 use std::fs::File;
 use std::vec::Vec;
+use std::collections::VecDeque;
 
-use csv::ReaderBuilder;
+use crate::intcode::load;
 
 struct Operation {
     pub opcode: i32,
@@ -13,33 +13,41 @@ struct Operation {
 pub struct IntCode {
     memory: Vec<i32>,
     current_state: Vec<i32>,
-    instr_ptr: usize
+    instr_ptr: usize,
+    input_pool: VecDeque<i32>,
+    outputs: Vec<i32>
 }
 
 impl IntCode {
     pub fn new(input: File) -> IntCode {
-        let mut input = ReaderBuilder::new()
-            .has_headers(false)
-            .from_reader(input);
-
-        let mut opcodes: Vec<i32> = Vec::new(); 
-
-        for record in input.records() {
-            for code in record.unwrap().iter() {
-                opcodes.push(code.parse().unwrap());
-            }
-        }
+        IntCode::from_program(load::read_legacy_program(input).expect("unable to read program"))
+    }
 
+    pub fn from_program(program: Vec<i32>) -> IntCode {
         IntCode {
-            memory: opcodes,
+            memory: program,
             current_state: Vec::new(),
-            instr_ptr: 0
+            instr_ptr: 0,
+            input_pool: VecDeque::new(),
+            outputs: Vec::new()
         }
     }
 
+    pub fn current_state(&self) -> &[i32] {
+        &self.current_state
+    }
+
     pub fn run_diagnostic(&mut self) {
+        for code in self.run(vec!(5)) {
+            println!("diagnostic code: {}", code);
+        }
+    }
+
+    pub fn run(&mut self, inputs: Vec<i32>) -> Vec<i32> {
         self.current_state = self.memory.to_vec();
         self.instr_ptr = 0; 
+        self.input_pool = VecDeque::from(inputs);
+        self.outputs = Vec::new();
 
         loop {
             let instr = self.current_state[self.instr_ptr];
@@ -61,6 +69,8 @@ impl IntCode {
                 }
             };
         }
+
+        self.outputs.to_vec()
     }
 
     fn jump_instr(&mut self, jump_if_zero: bool) {
@@ -106,7 +116,7 @@ impl IntCode {
 
         let [dest, _, _]  = operation.parameters;
 
-        self.current_state[dest as usize] = 5;
+        self.current_state[dest as usize] = self.input_pool.pop_front().expect("no input left for input instruction");
     }
 
     fn output_instr(&mut self) {
@@ -115,14 +125,14 @@ impl IntCode {
         self.mode_switch(&mut operation, 1);
         let [output, _, _] = operation.parameters;
 
-        println!("diagnostic code: {}", output);
+        self.outputs.push(output);
     }
 
     fn collect_operation(&mut self, param_cnt: usize) -> Operation {
         let c_state = &self.current_state;
         let i_ptr = self.instr_ptr;
 
-        if c_state.len() >= param_cnt + 1 && i_ptr >= c_state.len() - param_cnt - 1 {
+        if c_state.len() > param_cnt && i_ptr >= c_state.len() - param_cnt - 1 {
             panic!("Not enough params left in memory for operation");
         }
 
@@ -150,7 +160,7 @@ impl IntCode {
         let mut op = 0;
 
         for digit in 0..2 {
-            op += (opcode % 10) * (10 as i32).pow(digit);
+            op += (opcode % 10) * 10_i32.pow(digit);
             opcode /= 10;
         }
 
@@ -176,7 +186,7 @@ impl IntCode {
 
     fn get_mode(opcode: i32, param_num: u32) -> i32 {
         // remove digits that identify op type and preceding params
-        let stripped = opcode / (100 * (10 as i32).pow(param_num));
+        let stripped = opcode / (100 * 10_i32.pow(param_num));
 
         // return last digit
         stripped % 10
@@ -208,12 +218,14 @@ mod tests {
 
     #[test]
     fn collect_params() {
-        let memory = vec!(0, 1, 2, 3, 4, 5);
+        let memory = [0, 1, 2, 3, 4, 5];
 
         let mut machine = IntCode {
             memory: memory.to_vec(),
             current_state: memory.to_vec(),
-            instr_ptr: 0
+            instr_ptr: 0,
+            input_pool: VecDeque::new(),
+            outputs: Vec::new()
         };
 
         let expected = [1, 2, 3];
@@ -223,12 +235,14 @@ mod tests {
 
     #[test]
     fn do_add() {
-        let memory = vec!(1, 1, 1, 4, 4);
+        let memory = [1, 1, 1, 4, 4];
 
         let mut machine = IntCode {
             memory: Vec::new(),
             current_state: memory.to_vec(),
-            instr_ptr: 0
+            instr_ptr: 0,
+            input_pool: VecDeque::new(),
+            outputs: Vec::new()
         };
 
         machine.math_instr(|n1, n2| n1 + n2);
@@ -239,12 +253,14 @@ mod tests {
 
     #[test]
     fn mode_switch() {
-        let memory = vec!(1001, 4, 78, 4, 50);
+        let memory = [1001, 4, 78, 4, 50];
 
         let machine = IntCode {
             memory: Vec::new(),
             current_state: memory.to_vec(),
-            instr_ptr: 0
+            instr_ptr: 0,
+            input_pool: VecDeque::new(),
+            outputs: Vec::new()
         };
 
         let mut op = Operation {
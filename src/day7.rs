@@ -1,4 +1,4 @@
-mod int_code;
+pub mod int_code;
 mod permutation;
 
 use std::cmp;
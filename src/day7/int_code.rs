@@ -1,10 +1,9 @@
 #[allow(dead_code)]
-
 use std::fs::File;
 use std::vec::Vec;
 use std::collections::VecDeque;
 
-use csv::ReaderBuilder;
+use crate::intcode::load;
 
 struct Operation {
     pub opcode: i32,
@@ -20,26 +19,22 @@ pub struct IntCode {
 
 impl IntCode {
     pub fn new(input: File) -> IntCode {
-        let mut input = ReaderBuilder::new()
-            .has_headers(false)
-            .from_reader(input);
-
-        let mut opcodes: Vec<i32> = Vec::new(); 
-
-        for record in input.records() {
-            for code in record.unwrap().iter() {
-                opcodes.push(code.parse().unwrap());
-            }
-        }
+        IntCode::from_program(load::read_legacy_program(input).expect("unable to read program"))
+    }
 
+    pub fn from_program(program: Vec<i32>) -> IntCode {
         IntCode {
-            memory: opcodes,
+            memory: program,
             current_state: Vec::new(),
             instr_ptr: 0,
             input_pool: VecDeque::new()
         }
     }
 
+    pub fn current_state(&self) -> &[i32] {
+        &self.current_state
+    }
+
     pub fn process_phase_sequence(&mut self, phases: Vec<i32>) -> i32 {
         let mut amp_input = 0;
 
@@ -51,10 +46,19 @@ impl IntCode {
     }
 
     fn process_amp(&mut self, phase: i32, input: i32) -> i32 {
+        self.reset(vec!(phase, input));
+
+        self.run_until_output().expect("halt instruction should not have been reached")
+    }
+
+    pub fn reset(&mut self, inputs: Vec<i32>) {
         self.current_state = self.memory.to_vec();
         self.instr_ptr = 0; 
-        self.input_pool = VecDeque::from(vec!(phase, input));
+        self.input_pool = VecDeque::from(inputs);
+    }
 
+    // runs from where the last call stopped, returning None once halted
+    pub fn run_until_output(&mut self) -> Option<i32> {
         loop {
             let instr = self.current_state[self.instr_ptr];
             let instr = IntCode::extract_op(instr);
@@ -68,7 +72,7 @@ impl IntCode {
                 6 => self.jump_instr(true),
                 7 => self.cmp_instr(|n1, n2| n1 < n2),
                 8 => self.cmp_instr(|n1, n2| n1 == n2),
-                99 => panic!("halt instruction should not have been reached"),
+                99 => return None,
                 _ => {
                     println!("invalid instr reached, {}, at {}", instr, self.instr_ptr);
                     None
@@ -76,7 +80,7 @@ impl IntCode {
             };
 
             if let Some(output) = result {
-                return output;
+                return Some(output);
             }
         }
     }
@@ -141,14 +145,14 @@ impl IntCode {
         self.mode_switch(&mut operation, 1);
         let [output, _, _] = operation.parameters;
 
-        return Some(output);
+        Some(output)
     }
 
     fn collect_operation(&mut self, param_cnt: usize) -> Operation {
         let c_state = &self.current_state;
         let i_ptr = self.instr_ptr;
 
-        if c_state.len() >= param_cnt + 1 && i_ptr >= c_state.len() - param_cnt - 1 {
+        if c_state.len() > param_cnt && i_ptr >= c_state.len() - param_cnt - 1 {
             panic!("Not enough params left in memory for operation");
         }
 
@@ -176,7 +180,7 @@ impl IntCode {
         let mut op = 0;
 
         for digit in 0..2 {
-            op += (opcode % 10) * (10 as i32).pow(digit);
+            op += (opcode % 10) * 10_i32.pow(digit);
             opcode /= 10;
         }
 
@@ -202,7 +206,7 @@ impl IntCode {
 
     fn get_mode(opcode: i32, param_num: u32) -> i32 {
         // remove digits that identify op type and preceding params
-        let stripped = opcode / (100 * (10 as i32).pow(param_num));
+        let stripped = opcode / (100 * 10_i32.pow(param_num));
 
         // return last digit
         stripped % 10
@@ -234,7 +238,7 @@ mod tests {
 
     #[test]
     fn collect_params() {
-        let memory = vec!(0, 1, 2, 3, 4, 5);
+        let memory = [0, 1, 2, 3, 4, 5];
 
         let mut machine = IntCode {
             memory: memory.to_vec(),
@@ -250,7 +254,7 @@ mod tests {
 
     #[test]
     fn do_add() {
-        let memory = vec!(1, 1, 1, 4, 4);
+        let memory = [1, 1, 1, 4, 4];
 
         let mut machine = IntCode {
             memory: Vec::new(),
@@ -267,7 +271,7 @@ mod tests {
 
     #[test]
     fn mode_switch() {
-        let memory = vec!(1001, 4, 78, 4, 50);
+        let memory = [1001, 4, 78, 4, 50];
 
         let machine = IntCode {
             memory: Vec::new(),
@@ -6,7 +6,7 @@ pub fn find_permutations(nums: Vec<i32>) -> Vec<Vec<i32>> {
     permutations
 }
 
-fn permute(nums: &Vec<i32>, left: usize, right: usize, permutations: &mut Vec<Vec<i32>>) {
+fn permute(nums: &[i32], left: usize, right: usize, permutations: &mut Vec<Vec<i32>>) {
     if left == right {
         // at bottom of permute tree
         permutations.push(nums.to_vec());
@@ -0,0 +1,33 @@
+pub mod analyzer;
+pub mod async_machine;
+pub mod cfg;
+pub mod cli;
+pub mod compiler;
+pub mod conformance;
+pub mod dap;
+pub mod decompiler;
+pub mod disasm;
+pub mod heatmap;
+pub mod load;
+pub mod local_pool;
+pub mod engine;
+pub mod equivalence;
+pub mod fuzz;
+pub mod minimizer;
+pub mod optimizer;
+pub mod rng;
+pub mod session;
+
+use alloc::vec::Vec;
+use core::num::ParseIntError;
+
+pub use intcode_core::{ instruction, machine, Word };
+pub use intcode_ffi as ffi;
+
+// a program as written in the puzzle inputs: comma separated words
+pub fn parse_program(text: &str) -> Result<Vec<Word>, ParseIntError> {
+    text.trim()
+        .split(',')
+        .map(|word| word.trim().parse())
+        .collect()
+}
@@ -0,0 +1,277 @@
+use std::collections::{ BTreeMap, BTreeSet };
+use std::vec::Vec;
+
+use serde::Serialize;
+
+use super::Word;
+use super::instruction::{ Instruction, Mode, Opcode };
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Finding {
+    UnknownOpcode { address: usize, word: Word },
+    InvalidMode { address: usize, word: Word },
+    Truncated { address: usize },
+    ImmediateWrite { address: usize, param: usize },
+    NegativeWrite { address: usize, target: Word },
+    ReadOutOfBounds { address: usize, target: Word },
+    JumpOutOfBounds { address: usize, target: Word },
+    JumpIntoInstruction { address: usize, target: usize, instruction: usize },
+    SelfModifying { address: usize, target: usize, instruction: usize }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct Report {
+    pub program_len: usize,
+    // start addresses of every instruction statically reachable from 0
+    pub reachable: Vec<usize>,
+    pub findings: Vec<Finding>
+}
+
+impl Finding {
+    pub fn address(&self) -> usize {
+        match *self {
+            Finding::UnknownOpcode { address, .. } |
+            Finding::InvalidMode { address, .. } |
+            Finding::Truncated { address } |
+            Finding::ImmediateWrite { address, .. } |
+            Finding::NegativeWrite { address, .. } |
+            Finding::ReadOutOfBounds { address, .. } |
+            Finding::JumpOutOfBounds { address, .. } |
+            Finding::JumpIntoInstruction { address, .. } |
+            Finding::SelfModifying { address, .. } => address
+        }
+    }
+}
+
+impl Report {
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("report is always serializable")
+    }
+}
+
+// like Instruction::decode, but lets immediate-mode writes through so they
+// can be reported instead of stopping the walk
+fn decode_lenient(word: Word) -> Result<Instruction, Finding> {
+    match Instruction::decode(word) {
+        Ok(instr) => return Ok(instr),
+        Err(_) if word < 0 => return Err(Finding::UnknownOpcode { address: 0, word: word }),
+        Err(_) => ()
+    }
+
+    let opcode = Opcode::from_code(word % 100)
+        .ok_or(Finding::UnknownOpcode { address: 0, word: word })?;
+
+    let mut modes = [Mode::Position; 3];
+    let mut mode_digits = word / 100;
+
+    for mode in modes.iter_mut().take(opcode.param_count()) {
+        *mode = Mode::from_code(mode_digits % 10)
+            .ok_or(Finding::InvalidMode { address: 0, word: word })?;
+        mode_digits /= 10;
+    }
+
+    if mode_digits != 0 {
+        return Err(Finding::InvalidMode { address: 0, word: word });
+    }
+
+    Ok(Instruction::new(opcode, modes))
+}
+
+fn with_address(finding: Finding, addr: usize) -> Finding {
+    match finding {
+        Finding::UnknownOpcode { word, .. } => Finding::UnknownOpcode { address: addr, word: word },
+        Finding::InvalidMode { word, .. } => Finding::InvalidMode { address: addr, word: word },
+        other => other
+    }
+}
+
+pub fn analyze(program: &[Word]) -> Report {
+    let len = program.len();
+    let word_at = |addr: usize| program.get(addr).cloned().unwrap_or(0);
+
+    let mut instructions: BTreeMap<usize, Instruction> = BTreeMap::new();
+    let mut findings: BTreeSet<Finding> = BTreeSet::new();
+    let mut jumps: Vec<(usize, usize)> = Vec::new();
+    let mut writes: Vec<(usize, usize)> = Vec::new();
+
+    let mut worklist = vec!(0);
+    let mut visited = BTreeSet::new();
+
+    while let Some(addr) = worklist.pop() {
+        if !visited.insert(addr) {
+            continue;
+        }
+
+        let instr = match decode_lenient(word_at(addr)) {
+            Ok(instr) => instr,
+            Err(finding) => {
+                findings.insert(with_address(finding, addr));
+                continue;
+            }
+        };
+
+        instructions.insert(addr, instr);
+
+        if addr + instr.len() > len {
+            findings.insert(Finding::Truncated { address: addr });
+            continue;
+        }
+
+        let params: Vec<Word> = (0..instr.opcode.param_count())
+            .map(|param_i| word_at(addr + 1 + param_i))
+            .collect();
+
+        for (param_i, &raw) in params.iter().enumerate() {
+            let is_dest = instr.opcode.dest_param() == Some(param_i);
+
+            match instr.modes[param_i] {
+                Mode::Immediate if is_dest => {
+                    findings.insert(Finding::ImmediateWrite { address: addr, param: param_i });
+                },
+                Mode::Position if is_dest && raw < 0 => {
+                    findings.insert(Finding::NegativeWrite { address: addr, target: raw });
+                },
+                Mode::Position if is_dest => writes.push((addr, raw as usize)),
+                Mode::Position if raw < 0 || raw as usize >= len => {
+                    findings.insert(Finding::ReadOutOfBounds { address: addr, target: raw });
+                },
+                _ => ()
+            }
+        }
+
+        let next = addr + instr.len();
+
+        match instr.opcode {
+            Opcode::Halt => (),
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                let jump_if_zero = instr.opcode == Opcode::JumpIfFalse;
+
+                // conditions known statically only ever go one way
+                let (may_jump, may_fall) = match instr.modes[0] {
+                    Mode::Immediate => {
+                        let jumps = (params[0] == 0) == jump_if_zero;
+                        (jumps, !jumps)
+                    },
+                    _ => (true, true)
+                };
+
+                if may_fall {
+                    worklist.push(next);
+                }
+
+                // position and relative targets are only known at runtime
+                if may_jump && instr.modes[1] == Mode::Immediate {
+                    let target = params[1];
+
+                    if target < 0 || target as usize >= len {
+                        findings.insert(Finding::JumpOutOfBounds { address: addr, target: target });
+                    } else {
+                        jumps.push((addr, target as usize));
+                        worklist.push(target as usize);
+                    }
+                }
+            },
+            _ => worklist.push(next)
+        }
+    }
+
+    // instructions are at most four words long, so only the last few starts
+    // can cover an address
+    let containing = |target: usize, inclusive: bool| {
+        let first = target.saturating_sub(3);
+        let last = if inclusive { target + 1 } else { target };
+
+        instructions.range(first..last).rev()
+            .find(|(&start, instr)| target < start + instr.len())
+            .map(|(&start, _)| start)
+    };
+
+    for &(addr, target) in jumps.iter() {
+        if let Some(start) = containing(target, false) {
+            findings.insert(Finding::JumpIntoInstruction { address: addr, target: target, instruction: start });
+        }
+    }
+
+    for &(addr, target) in writes.iter() {
+        if let Some(start) = containing(target, true) {
+            findings.insert(Finding::SelfModifying { address: addr, target: target, instruction: start });
+        }
+    }
+
+    let mut findings: Vec<Finding> = findings.into_iter().collect();
+    findings.sort_by_key(|finding| finding.address());
+
+    Report {
+        program_len: len,
+        reachable: instructions.keys().cloned().collect(),
+        findings: findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_program() {
+        let report = analyze(&[3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8]);
+
+        assert!(report.is_clean());
+        assert_eq!(report.reachable, vec!(0, 2, 6, 8));
+    }
+
+    #[test]
+    fn immediate_write() {
+        let report = analyze(&[11101, 1, 1, 5, 99, 0]);
+
+        assert_eq!(report.findings, vec!(Finding::ImmediateWrite { address: 0, param: 2 }));
+    }
+
+    #[test]
+    fn jumps() {
+        let report = analyze(&[1105, 1, 50, 99]);
+        assert_eq!(report.findings, vec!(Finding::JumpOutOfBounds { address: 0, target: 50 }));
+
+        let report = analyze(&[1005, 8, 4, 1101, 1, 1, 8, 99, 0]);
+        let expected = Finding::JumpIntoInstruction { address: 0, target: 4, instruction: 3 };
+        assert!(report.findings.contains(&expected));
+    }
+
+    #[test]
+    fn unknown_opcodes_only_when_reachable() {
+        let report = analyze(&[1101, 0, 0, 5, 42, 0]);
+        assert_eq!(report.findings, vec!(Finding::UnknownOpcode { address: 4, word: 42 }));
+
+        let report = analyze(&[1105, 1, 4, 42, 99]);
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn out_of_bounds_read_and_self_modification() {
+        let report = analyze(&[1, 100, 0, 0, 99]);
+        assert!(report.findings.contains(&Finding::ReadOutOfBounds { address: 0, target: 100 }));
+
+        // the day 2 example writes over its own operands
+        let report = analyze(&[1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+        let expected = vec!(
+            Finding::SelfModifying { address: 0, target: 3, instruction: 0 },
+            Finding::SelfModifying { address: 4, target: 0, instruction: 0 }
+        );
+        assert_eq!(report.findings, expected);
+    }
+
+    #[test]
+    fn json_report() {
+        let json = analyze(&[11101, 1, 1, 5, 99, 0]).to_json();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["findings"][0]["kind"], "immediate_write");
+        assert_eq!(value["findings"][0]["address"], 0);
+        assert_eq!(value["program_len"], 6);
+    }
+}
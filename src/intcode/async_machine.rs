@@ -0,0 +1,324 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::{ self, Future };
+use std::rc::Rc;
+use std::task::{ Context, Poll, Waker };
+use std::vec::Vec;
+
+use super::Word;
+use super::machine::{ Event, Machine, VmError };
+
+// instructions run between voluntary yields, so a machine stuck in a long
+// computation doesn't starve the others sharing its executor
+const STEP_BUDGET: usize = 10_000;
+
+pub trait Source {
+    // Ready(None) means no more input will ever arrive
+    fn poll_input(&mut self, cx: &mut Context) -> Poll<Option<Word>>;
+}
+
+pub trait Sink {
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<()>;
+
+    fn send(&mut self, value: Word);
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RunError {
+    Vm(VmError),
+    InputClosed,
+    StepLimit
+}
+
+struct Shared {
+    queue: VecDeque<Word>,
+    waker: Option<Waker>,
+    closed: bool
+}
+
+// single-threaded unbounded channel for wiring machines together
+pub struct Sender {
+    shared: Rc<RefCell<Shared>>
+}
+
+pub struct Receiver {
+    shared: Rc<RefCell<Shared>>
+}
+
+pub fn channel() -> (Sender, Receiver) {
+    let shared = Rc::new(RefCell::new(Shared {
+        queue: VecDeque::new(),
+        waker: None,
+        closed: false
+    }));
+
+    (Sender { shared: shared.clone() }, Receiver { shared: shared })
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunError::Vm(err) => write!(f, "{}", err),
+            RunError::InputClosed => write!(f, "input closed while waiting for a value"),
+            RunError::StepLimit => write!(f, "step limit reached")
+        }
+    }
+}
+
+impl Sender {
+    pub fn push(&self, value: Word) {
+        let mut shared = self.shared.borrow_mut();
+
+        shared.queue.push_back(value);
+
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for Sender {
+    fn drop(&mut self) {
+        let mut shared = self.shared.borrow_mut();
+
+        shared.closed = true;
+
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Source for Receiver {
+    fn poll_input(&mut self, cx: &mut Context) -> Poll<Option<Word>> {
+        let mut shared = self.shared.borrow_mut();
+
+        if let Some(value) = shared.queue.pop_front() {
+            return Poll::Ready(Some(value));
+        }
+
+        if shared.closed {
+            return Poll::Ready(None);
+        }
+
+        shared.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Sink for Sender {
+    fn poll_ready(&mut self, _cx: &mut Context) -> Poll<()> {
+        Poll::Ready(())
+    }
+
+    fn send(&mut self, value: Word) {
+        self.push(value);
+    }
+}
+
+impl Source for VecDeque<Word> {
+    fn poll_input(&mut self, _cx: &mut Context) -> Poll<Option<Word>> {
+        Poll::Ready(self.pop_front())
+    }
+}
+
+impl Sink for Vec<Word> {
+    fn poll_ready(&mut self, _cx: &mut Context) -> Poll<()> {
+        Poll::Ready(())
+    }
+
+    fn send(&mut self, value: Word) {
+        self.push(value);
+    }
+}
+
+fn yield_now() -> impl Future<Output = ()> {
+    let mut yielded = false;
+
+    future::poll_fn(move |cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+}
+
+// runs the machine to completion, awaiting input whenever it blocks instead of
+// giving up its place
+pub async fn run<S, K>(machine: &mut Machine, input: &mut S, output: &mut K) -> Result<(), RunError> where
+    S: Source,
+    K: Sink {
+
+    run_limited(machine, input, output, usize::MAX).await
+}
+
+// as run, but gives up once the machine has taken step_limit steps
+pub async fn run_limited<S, K>(machine: &mut Machine, input: &mut S, output: &mut K, step_limit: usize) -> Result<(), RunError> where
+    S: Source,
+    K: Sink {
+
+    let mut budget = STEP_BUDGET;
+
+    loop {
+        if machine.steps() >= step_limit {
+            return Err(RunError::StepLimit);
+        }
+
+        if budget == 0 {
+            yield_now().await;
+            budget = STEP_BUDGET;
+        }
+        budget -= 1;
+
+        match machine.step().map_err(RunError::Vm)? {
+            None => (),
+            Some(Event::Output(value)) => {
+                future::poll_fn(|cx| output.poll_ready(cx)).await;
+                output.send(value);
+            },
+            Some(Event::NeedInput) => {
+                match future::poll_fn(|cx| input.poll_input(cx)).await {
+                    Some(value) => machine.push_input(value),
+                    None => return Err(RunError::InputClosed)
+                }
+            },
+            Some(Event::Halted) => return Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::local_pool::LocalPool;
+
+    const FEEDBACK_EXAMPLE: [Word; 29] = [3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26,
+        27, 4, 27, 1001, 28, -1, 28, 1005, 28, 6, 99, 0, 0, 5];
+
+    // day 7 part 2: five amplifiers in a loop, each its own task
+    fn feedback_loop(program: &[Word], phases: &[Word]) -> Word {
+        let mut pool = LocalPool::new();
+        let channels: Vec<(Sender, Receiver)> = phases.iter().map(|_| channel()).collect();
+
+        for (i, &phase) in phases.iter().enumerate() {
+            channels[i].0.push(phase);
+        }
+        channels[0].0.push(0);
+
+        // amplifier i reads channel i and writes channel i + 1
+        let (senders, receivers): (Vec<Sender>, Vec<Receiver>) = channels.into_iter().unzip();
+        let mut senders: VecDeque<Sender> = senders.into_iter().collect();
+        senders.rotate_left(1);
+
+        let last = Rc::new(RefCell::new(0));
+
+        for (mut input, output) in receivers.into_iter().zip(senders) {
+            let program = program.to_vec();
+            let last = last.clone();
+
+            pool.spawn(async move {
+                let mut machine = Machine::new(program);
+                let mut output = Recorder { inner: output, last: last };
+
+                run(&mut machine, &mut input, &mut output).await.unwrap();
+            });
+        }
+
+        pool.run();
+
+        let result = *last.borrow();
+        result
+    }
+
+    // remembers the last value sent, which is the loop's result once the
+    // final amplifier halts
+    struct Recorder {
+        inner: Sender,
+        last: Rc<RefCell<Word>>
+    }
+
+    impl Sink for Recorder {
+        fn poll_ready(&mut self, cx: &mut Context) -> Poll<()> {
+            self.inner.poll_ready(cx)
+        }
+
+        fn send(&mut self, value: Word) {
+            *self.last.borrow_mut() = value;
+            self.inner.send(value);
+        }
+    }
+
+    #[test]
+    fn runs_with_plain_buffers() {
+        let mut machine = Machine::new(vec!(3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8));
+        let mut input: VecDeque<Word> = VecDeque::from(vec!(8));
+        let mut output: Vec<Word> = Vec::new();
+
+        let mut pool = LocalPool::new();
+        let result = pool.run_until(run(&mut machine, &mut input, &mut output));
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(output, vec!(1));
+    }
+
+    #[test]
+    fn reports_closed_input() {
+        let mut machine = Machine::new(vec!(3, 0, 99));
+        let mut input: VecDeque<Word> = VecDeque::new();
+        let mut output: Vec<Word> = Vec::new();
+
+        let result = LocalPool::new().run_until(run(&mut machine, &mut input, &mut output));
+
+        assert_eq!(result, Err(RunError::InputClosed));
+    }
+
+    #[test]
+    fn stops_at_the_step_limit() {
+        let mut machine = Machine::new(vec!(1105, 1, 0));
+        let mut input: VecDeque<Word> = VecDeque::new();
+        let mut output: Vec<Word> = Vec::new();
+
+        let result = LocalPool::new().run_until(run_limited(&mut machine, &mut input, &mut output, 50));
+
+        assert_eq!(result, Err(RunError::StepLimit));
+        assert_eq!(machine.steps(), 50);
+    }
+
+    #[test]
+    fn amplifier_feedback_loop() {
+        assert_eq!(feedback_loop(&FEEDBACK_EXAMPLE, &[9, 8, 7, 6, 5]), 139629729);
+    }
+
+    #[test]
+    fn long_computation_yields() {
+        // counts [20] up to 50000 before outputting it
+        let program = vec!(1001, 20, 1, 20, 1007, 20, 50000, 21, 1005, 21, 0, 4, 20, 99,
+            0, 0, 0, 0, 0, 0, 0, 0);
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut pool = LocalPool::new();
+
+        let machine_log = log.clone();
+        pool.spawn(async move {
+            let mut machine = Machine::new(program);
+            let mut input: VecDeque<Word> = VecDeque::new();
+            let mut output: Vec<Word> = Vec::new();
+
+            run(&mut machine, &mut input, &mut output).await.unwrap();
+            machine_log.borrow_mut().push(output[0]);
+        });
+
+        let other_log = log.clone();
+        pool.spawn(async move {
+            other_log.borrow_mut().push(-1);
+        });
+
+        pool.run();
+
+        assert_eq!(*log.borrow(), vec!(-1, 50000));
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::{ BTreeMap, BTreeSet };
+use std::vec::Vec;
+
+use super::Word;
+use super::disasm::{ self, Decoded };
+use super::instruction::{ Mode, Opcode };
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Exit {
+    Fall(usize),
+    Jump(usize),
+    // jumps to target when the condition's truth equals jump_if_true
+    Branch { target: usize, next: usize, jump_if_true: bool },
+    // target only known at runtime
+    Dynamic,
+    Halt,
+    // runs into a word that doesn't decode or off the end of the program
+    Invalid
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Block {
+    pub start: usize,
+    pub end: usize,
+    pub instrs: Vec<Decoded>,
+    pub exit: Exit
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Cfg {
+    pub blocks: BTreeMap<usize, Block>
+}
+
+impl Block {
+    // the jump ending the block, if any
+    pub fn terminator(&self) -> Option<&Decoded> {
+        self.instrs.last().filter(|decoded| decoded.instr.opcode.is_jump())
+    }
+
+    pub fn successors(&self) -> Vec<usize> {
+        match self.exit {
+            Exit::Fall(next) | Exit::Jump(next) => vec!(next),
+            Exit::Branch { target, next, .. } => vec!(target, next),
+            _ => Vec::new()
+        }
+    }
+}
+
+impl Cfg {
+    pub fn block_ending_at(&self, end: usize) -> Option<&Block> {
+        self.blocks.range(..end).next_back()
+            .map(|(_, block)| block)
+            .filter(|block| block.end == end)
+    }
+
+    pub fn predecessors(&self, addr: usize) -> Vec<usize> {
+        self.blocks.values()
+            .filter(|block| block.successors().contains(&addr))
+            .map(|block| block.start)
+            .collect()
+    }
+}
+
+fn immediate_target(decoded: &Decoded) -> Option<usize> {
+    match decoded.instr.modes[1] {
+        Mode::Immediate if decoded.params[1] >= 0 => Some(decoded.params[1] as usize),
+        _ => None
+    }
+}
+
+// follows every statically known path from address 0
+pub fn build(program: &[Word]) -> Cfg {
+    let mut instrs: BTreeMap<usize, Option<Decoded>> = BTreeMap::new();
+    let mut leaders: BTreeSet<usize> = BTreeSet::new();
+    let mut worklist = vec!(0);
+
+    leaders.insert(0);
+
+    while let Some(addr) = worklist.pop() {
+        if instrs.contains_key(&addr) {
+            continue;
+        }
+
+        let decoded = disasm::decode_at(program, addr);
+        instrs.insert(addr, decoded.clone());
+
+        let decoded = match decoded {
+            Some(decoded) => decoded,
+            None => continue
+        };
+
+        match decoded.instr.opcode {
+            Opcode::Halt => (),
+            op if op.is_jump() => {
+                let always = decoded.instr.modes[0] == Mode::Immediate &&
+                    (decoded.params[0] != 0) == (op == Opcode::JumpIfTrue);
+
+                if !always {
+                    leaders.insert(decoded.next());
+                    worklist.push(decoded.next());
+                }
+
+                if let Some(target) = immediate_target(&decoded) {
+                    leaders.insert(target);
+                    worklist.push(target);
+                }
+            },
+            _ => worklist.push(decoded.next())
+        }
+    }
+
+    let mut blocks = BTreeMap::new();
+
+    for &start in leaders.iter() {
+        let mut block_instrs = Vec::new();
+        let mut addr = start;
+
+        let exit = loop {
+            let decoded = match instrs.get(&addr).cloned().unwrap_or(None) {
+                Some(decoded) => decoded,
+                None => break Exit::Invalid
+            };
+
+            let next = decoded.next();
+            let op = decoded.instr.opcode;
+            block_instrs.push(decoded.clone());
+
+            if op == Opcode::Halt {
+                break Exit::Halt;
+            }
+
+            if op.is_jump() {
+                let always = decoded.instr.modes[0] == Mode::Immediate &&
+                    (decoded.params[0] != 0) == (op == Opcode::JumpIfTrue);
+                let never = decoded.instr.modes[0] == Mode::Immediate && !always;
+
+                break match immediate_target(&decoded) {
+                    _ if never => Exit::Fall(next),
+                    Some(target) if always => Exit::Jump(target),
+                    Some(target) => Exit::Branch { target: target, next: next, jump_if_true: op == Opcode::JumpIfTrue },
+                    None => Exit::Dynamic
+                };
+            }
+
+            if leaders.contains(&next) {
+                break Exit::Fall(next);
+            }
+
+            addr = next;
+        };
+
+        let end = block_instrs.last().map_or(start, |decoded: &Decoded| decoded.next());
+
+        blocks.insert(start, Block {
+            start: start,
+            end: end,
+            instrs: block_instrs,
+            exit: exit
+        });
+    }
+
+    Cfg { blocks: blocks }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loop_blocks() {
+        // reads n, then outputs n, n-1, ..., 1
+        let program = [3, 15, 1006, 15, 14, 4, 15, 1001, 15, -1, 15, 1105, 1, 2, 99, 0];
+        let cfg = build(&program);
+
+        let starts: Vec<usize> = cfg.blocks.keys().cloned().collect();
+        assert_eq!(starts, vec!(0, 2, 5, 14));
+
+        assert_eq!(cfg.blocks[&0].exit, Exit::Fall(2));
+        assert_eq!(cfg.blocks[&2].exit, Exit::Branch { target: 14, next: 5, jump_if_true: false });
+        assert_eq!(cfg.blocks[&5].exit, Exit::Jump(2));
+        assert_eq!(cfg.blocks[&14].exit, Exit::Halt);
+
+        assert_eq!(cfg.block_ending_at(14).unwrap().start, 5);
+        assert_eq!(cfg.predecessors(2), vec!(0, 5));
+    }
+
+    #[test]
+    fn dynamic_and_invalid_exits() {
+        let cfg = build(&[1105, 1, 3, 2105, 1, 0]);
+        assert_eq!(cfg.blocks[&3].exit, Exit::Dynamic);
+
+        let cfg = build(&[1101, 1, 1, 5, 42]);
+        assert_eq!(cfg.blocks[&0].exit, Exit::Invalid);
+    }
+}
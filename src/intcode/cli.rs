@@ -0,0 +1,371 @@
+use std::fs;
+use std::io::{ Read, Write };
+use std::vec::Vec;
+
+use serde_json::json;
+
+use super::Word;
+use super::engine::Termination;
+use super::load;
+use super::machine::{ Event, Machine };
+
+// the `intcode` binary: executes an Intcode program file from the command
+// line, e.g.
+//
+//   intcode data/2.txt --noun 12 --verb 2 --output json
+//   echo 5 | intcode data/5.txt --stdin
+//   intcode data/25.txt --stdin --ascii-input --output ascii
+
+pub const EXIT_HALTED: i32 = 0;
+pub const EXIT_VM_ERROR: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_STEP_LIMIT: i32 = 3;
+pub const EXIT_INPUT_EXHAUSTED: i32 = 4;
+pub const EXIT_IO_ERROR: i32 = 5;
+
+pub const USAGE: &str = "usage: intcode <program> [options]
+
+  -i, --input <a,b,...>    inputs, in order; may be repeated
+  --stdin                  read inputs from stdin
+  --input-file <path>      read inputs from a file
+  --ascii-input            inputs from stdin or a file are text, one word per character
+  -o, --output <format>    numbers (default), ascii or json
+  --noun <word>            write to address 1 before running
+  --verb <word>            write to address 2 before running
+  --steps <count>          stop after this many instructions
+
+exit codes: 0 halted, 1 machine error, 2 bad usage, 3 step limit, 4 ran out of input,
+  5 couldn't load the program or inputs or write the output";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Numbers,
+    Ascii,
+    Json
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Options {
+    pub program: String,
+    pub inputs: Vec<Word>,
+    pub read_stdin: bool,
+    pub input_file: Option<String>,
+    pub ascii_input: bool,
+    pub format: Format,
+    pub noun: Option<Word>,
+    pub verb: Option<Word>,
+    pub step_limit: Option<usize>
+}
+
+fn parse_words(text: &str) -> Result<Vec<Word>, String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.parse().map_err(|_| format!("not a number: {}", word)))
+        .collect()
+}
+
+fn parse_word(option: &str, value: &str) -> Result<Word, String> {
+    value.parse().map_err(|_| format!("{} expects a number, got {}", option, value))
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            program: String::new(),
+            inputs: Vec::new(),
+            read_stdin: false,
+            input_file: None,
+            ascii_input: false,
+            format: Format::Numbers,
+            noun: None,
+            verb: None,
+            step_limit: None
+        };
+
+        let mut program = None;
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} expects a value", arg));
+
+            match arg.as_str() {
+                "-i" | "--input" => options.inputs.extend(parse_words(value()?)?),
+                "--stdin" => options.read_stdin = true,
+                "--input-file" => options.input_file = Some(value()?.clone()),
+                "--ascii-input" => options.ascii_input = true,
+                "-o" | "--output" => options.format = match value()?.as_str() {
+                    "numbers" => Format::Numbers,
+                    "ascii" => Format::Ascii,
+                    "json" => Format::Json,
+                    other => return Err(format!("unknown output format {}", other))
+                },
+                "--noun" => options.noun = Some(parse_word(arg, value()?)?),
+                "--verb" => options.verb = Some(parse_word(arg, value()?)?),
+                "--steps" => options.step_limit = Some(value()?.parse().map_err(|_| String::from("--steps expects a count"))?),
+                other if other.starts_with('-') && other.len() > 1 => return Err(format!("unknown option {}", other)),
+                other => {
+                    if program.is_some() {
+                        return Err(format!("unexpected argument {}", other));
+                    }
+                    program = Some(other.to_string());
+                }
+            }
+        }
+
+        if options.read_stdin && options.input_file.is_some() {
+            return Err(String::from("--stdin and --input-file can't be combined"));
+        }
+
+        options.program = program.ok_or("no program given")?;
+        Ok(options)
+    }
+
+    fn decode_inputs(&self, text: &str) -> Result<Vec<Word>, String> {
+        if self.ascii_input {
+            return Ok(text.bytes().map(|byte| byte as Word).collect());
+        }
+
+        parse_words(text)
+    }
+}
+
+fn termination_name(termination: &Termination) -> &'static str {
+    match termination {
+        Termination::Halted => "halted",
+        Termination::Error(_) => "error",
+        Termination::InputExhausted => "input_exhausted",
+        Termination::StepLimit => "step_limit"
+    }
+}
+
+fn exit_code(termination: &Termination) -> i32 {
+    match termination {
+        Termination::Halted => EXIT_HALTED,
+        Termination::Error(_) => EXIT_VM_ERROR,
+        Termination::InputExhausted => EXIT_INPUT_EXHAUSTED,
+        Termination::StepLimit => EXIT_STEP_LIMIT
+    }
+}
+
+// ascii output prints printable values as text and anything else, like the
+// large answers some puzzles end with, as a number on its own line
+fn write_output(out: &mut dyn Write, format: Format, value: Word) -> std::io::Result<()> {
+    match format {
+        Format::Numbers => writeln!(out, "{}", value),
+        Format::Ascii if (0..128).contains(&value) => write!(out, "{}", value as u8 as char),
+        Format::Ascii => writeln!(out, "{}", value),
+        Format::Json => Ok(())
+    }
+}
+
+fn execute(options: &Options, stdin: &mut dyn Read, stdout: &mut dyn Write, stderr: &mut dyn Write) -> Result<i32, String> {
+    let mut program = load::load_file(&options.program)?;
+
+    for &(addr, patch) in [(1, options.noun), (2, options.verb)].iter() {
+        if let Some(value) = patch {
+            if program.len() <= addr {
+                return Err(format!("program is too short to patch address {}", addr));
+            }
+            program[addr] = value;
+        }
+    }
+
+    let mut inputs = options.inputs.clone();
+
+    if options.read_stdin {
+        let mut text = String::new();
+        stdin.read_to_string(&mut text).map_err(|err| format!("stdin: {}", err))?;
+        inputs.extend(options.decode_inputs(&text)?);
+    }
+
+    if let Some(path) = &options.input_file {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+        inputs.extend(options.decode_inputs(&text)?);
+    }
+
+    let mut machine = Machine::new(program);
+    machine.extend_input(&inputs);
+
+    let mut outputs = Vec::new();
+    let mut error = None;
+
+    let termination = loop {
+        if options.step_limit.is_some_and(|limit| machine.steps() >= limit) {
+            break Termination::StepLimit;
+        }
+
+        match machine.step() {
+            Ok(None) => (),
+            Ok(Some(Event::Output(value))) => {
+                write_output(stdout, options.format, value).map_err(|err| err.to_string())?;
+                outputs.push(value);
+            },
+            Ok(Some(Event::NeedInput)) => break Termination::InputExhausted,
+            Ok(Some(Event::Halted)) => break Termination::Halted,
+            Err(err) => {
+                error = Some(err.to_string());
+                break Termination::Error(err.kind);
+            }
+        }
+    };
+
+    if options.format == Format::Json {
+        let report = json!({
+            "outputs": outputs,
+            "termination": termination_name(&termination),
+            "error": error,
+            "steps": machine.steps(),
+            "memory0": machine.memory().first().cloned().unwrap_or(0)
+        });
+
+        writeln!(stdout, "{}", report).map_err(|err| err.to_string())?;
+    } else {
+        let message = match &termination {
+            Termination::Halted => None,
+            Termination::Error(_) => error.clone(),
+            Termination::InputExhausted => Some(String::from("program wants more input")),
+            Termination::StepLimit => Some(format!("stopped after {} steps", machine.steps()))
+        };
+
+        if let Some(message) = message {
+            writeln!(stderr, "{}", message).map_err(|err| err.to_string())?;
+        }
+    }
+
+    Ok(exit_code(&termination))
+}
+
+// runs the command with the given arguments (not including the command
+// name) and returns the exit code
+pub fn main(args: &[String], stdin: &mut dyn Read, stdout: &mut dyn Write, stderr: &mut dyn Write) -> i32 {
+    let options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(message) => {
+            let _ = writeln!(stderr, "{}\n\n{}", message, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+
+    match execute(&options, stdin, stdout, stderr) {
+        Ok(code) => code,
+        Err(message) => {
+            let _ = writeln!(stderr, "{}", message);
+            EXIT_IO_ERROR
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::path::PathBuf;
+
+    struct Run {
+        code: i32,
+        stdout: String,
+        stderr: String
+    }
+
+    fn program_file(name: &str, text: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("intcode_cli_{}_{}.txt", std::process::id(), name));
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    fn run(args: &[&str], stdin: &str) -> Run {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+
+        let code = main(&args, &mut stdin.as_bytes(), &mut stdout, &mut stderr);
+
+        Run {
+            code: code,
+            stdout: String::from_utf8(stdout).unwrap(),
+            stderr: String::from_utf8(stderr).unwrap()
+        }
+    }
+
+    // outputs 999, 1000 or 1001 as the input is below, equal to or above 8
+    const COMPARE: &str = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99\n";
+
+    #[test]
+    fn inputs_from_arguments() {
+        let path = program_file("args", COMPARE);
+        let result = run(&[path.to_str().unwrap(), "-i", "8"], "");
+
+        assert_eq!(result.code, EXIT_HALTED);
+        assert_eq!(result.stdout, "1000\n");
+    }
+
+    #[test]
+    fn inputs_from_stdin_and_file() {
+        let path = program_file("stdin", "3,0,3,1,4,0,4,1,99");
+        let inputs = program_file("stdin_inputs", "7\n9\n");
+
+        let result = run(&[path.to_str().unwrap(), "--stdin"], "1, 2");
+        assert_eq!(result.stdout, "1\n2\n");
+
+        let result = run(&[path.to_str().unwrap(), "--input-file", inputs.to_str().unwrap()], "");
+        assert_eq!(result.stdout, "7\n9\n");
+    }
+
+    #[test]
+    fn ascii_in_and_out() {
+        // echoes two characters then a large number
+        let path = program_file("ascii", "3,0,3,1,4,0,4,1,104,1125899906842624,99");
+        let result = run(&[path.to_str().unwrap(), "--stdin", "--ascii-input", "-o", "ascii"], "hi");
+
+        assert_eq!(result.stdout, "hi1125899906842624\n");
+    }
+
+    #[test]
+    fn json_and_noun_verb() {
+        let path = program_file("json", "1,0,0,0,99,30,40,50");
+        let result = run(&[path.to_str().unwrap(), "--noun", "5", "--verb", "6", "--output", "json"], "");
+
+        let report: serde_json::Value = serde_json::from_str(&result.stdout).unwrap();
+
+        assert_eq!(result.code, EXIT_HALTED);
+        assert_eq!(report["memory0"], 70);
+        assert_eq!(report["termination"], "halted");
+        assert_eq!(report["steps"], 2);
+    }
+
+    #[test]
+    fn exit_codes() {
+        let path = program_file("error", "1,0,0,0,42");
+        let result = run(&[path.to_str().unwrap()], "");
+        assert_eq!(result.code, EXIT_VM_ERROR);
+        assert_eq!(result.stderr, "invalid opcode 42 at 4\n");
+
+        let path = program_file("loop", "1105,1,0");
+        let result = run(&[path.to_str().unwrap(), "--steps", "10"], "");
+        assert_eq!(result.code, EXIT_STEP_LIMIT);
+
+        let path = program_file("hungry", "3,0,99");
+        assert_eq!(run(&[path.to_str().unwrap()], "").code, EXIT_INPUT_EXHAUSTED);
+    }
+
+    #[test]
+    fn usage_errors() {
+        assert_eq!(run(&[], "").code, EXIT_USAGE);
+        assert_eq!(run(&["a.txt", "b.txt"], "").code, EXIT_USAGE);
+        assert_eq!(run(&["a.txt", "--output", "xml"], "").code, EXIT_USAGE);
+        assert_eq!(run(&["a.txt", "--stdin", "--input-file", "b.txt"], "").code, EXIT_USAGE);
+    }
+
+    #[test]
+    fn io_errors() {
+        let missing = run(&["/nonexistent/program.txt"], "");
+        assert_eq!(missing.code, EXIT_IO_ERROR);
+        assert!(missing.stderr.starts_with("/nonexistent/program.txt"));
+
+        let path = program_file("no_inputs", "3,0,4,0,99");
+        let missing = run(&[path.to_str().unwrap(), "--input-file", "/nonexistent/inputs.txt"], "");
+        assert_eq!(missing.code, EXIT_IO_ERROR);
+
+        let garbled = program_file("garbled", "1,x,99");
+        assert_eq!(run(&[garbled.to_str().unwrap()], "").code, EXIT_IO_ERROR);
+    }
+}
@@ -0,0 +1,190 @@
+mod codegen;
+mod lexer;
+mod parser;
+
+use std::fmt;
+use std::vec::Vec;
+
+use super::Word;
+
+// compiles a small structured language to Intcode:
+//
+//   fn square(n) { return n * n; }
+//
+//   fn main() {
+//       let limit = input();
+//       let i = 0;
+//       while i < limit {
+//           output(square(i));
+//           i = i + 1;
+//       }
+//   }
+//
+// every value is a word. there are +, -, *, comparisons, !, && and ||, and
+// functions keep their frames on a stack addressed through the relative base.
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct CompileError {
+    pub line: usize,
+    pub message: String
+}
+
+impl CompileError {
+    pub fn new(line: usize, message: &str) -> CompileError {
+        CompileError {
+            line: line,
+            message: String::from(message)
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+pub fn compile(source: &str) -> Result<Vec<Word>, CompileError> {
+    let tokens = lexer::tokenize(source)?;
+    let functions = parser::parse(tokens)?;
+
+    codegen::generate(&functions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::analyzer;
+    use crate::intcode::engine::{ self, Termination };
+    use crate::intcode::machine::Machine;
+
+    fn run(source: &str, inputs: &[Word]) -> Vec<Word> {
+        let program = compile(source).unwrap();
+
+        let mut machine = Machine::new(program);
+        machine.extend_input(inputs);
+
+        let (outputs, termination) = engine::run_machine(&mut machine, 1_000_000);
+        assert_eq!(termination, Termination::Halted);
+
+        outputs
+    }
+
+    #[test]
+    fn arithmetic() {
+        let source = "fn main() { output(2 + 3 * 4); output(10 - 7 - 1); output(-(5)); output(-5 * -5); }";
+        assert_eq!(run(source, &[]), vec!(14, 2, -5, 25));
+
+        let source = "fn main() { output(20 - 2 * 3); output((1 + 2) - 3 * 4); }";
+        assert_eq!(run(source, &[]), vec!(14, -9));
+    }
+
+    #[test]
+    fn comparisons() {
+        let source = "
+            fn main() {
+                let a = input();
+                let b = input();
+                output(a < b); output(a > b); output(a <= b);
+                output(a >= b); output(a == b); output(a != b);
+                output(!a);
+            }";
+
+        assert_eq!(run(source, &[3, 5]), vec!(1, 0, 1, 0, 0, 1, 0));
+        assert_eq!(run(source, &[5, 5]), vec!(0, 0, 1, 1, 1, 0, 0));
+        assert_eq!(run(source, &[0, -1]), vec!(0, 1, 0, 1, 0, 1, 1));
+    }
+
+    #[test]
+    fn loops_and_branches() {
+        let source = "
+            fn main() {
+                let n = input();
+                let i = 0;
+                while i < n {
+                    if i == 1 {
+                        output(100);
+                    } else if i == 2 {
+                        output(200);
+                    } else {
+                        output(i);
+                    }
+                    i = i + 1;
+                }
+            }";
+
+        assert_eq!(run(source, &[5]), vec!(0, 100, 200, 3, 4));
+        assert!(run(source, &[0]).is_empty());
+    }
+
+    #[test]
+    fn recursion() {
+        let source = "
+            fn fact(n) {
+                if n <= 1 { return 1; }
+                return n * fact(n - 1);
+            }
+
+            fn fib(n) {
+                if n < 2 { return n; }
+                return fib(n - 1) + fib(n - 2);
+            }
+
+            fn main() {
+                output(fact(10));
+                output(fib(15));
+            }";
+
+        assert_eq!(run(source, &[]), vec!(3628800, 610));
+    }
+
+    #[test]
+    fn nested_calls_and_scopes() {
+        let source = "
+            fn add3(a, b, c) { return a + b + c; }
+            fn noop() { }
+
+            fn main() {
+                let x = 1;
+                if 1 {
+                    let x = 10;
+                    output(x);
+                }
+                output(add3(x, add3(1, 2, 3), noop()));
+            }";
+
+        assert_eq!(run(source, &[]), vec!(10, 7));
+    }
+
+    #[test]
+    fn short_circuit() {
+        // input() on the right must not run when the left decides the result
+        let source = "
+            fn main() {
+                output(0 && input());
+                output(1 || input());
+                output(1 && input());
+                output(input() || 0);
+            }";
+
+        assert_eq!(run(source, &[7, 0]), vec!(0, 1, 1, 0));
+    }
+
+    #[test]
+    fn output_is_statically_clean() {
+        let program = compile("fn f(a) { return a; } fn main() { output(f(input())); }").unwrap();
+
+        assert!(analyzer::analyze(&program).is_clean());
+    }
+
+    #[test]
+    fn errors() {
+        let err = |source: &str| compile(source).unwrap_err();
+
+        assert_eq!(err("fn f() { }").message, "no main function");
+        assert_eq!(err("fn main() {\n output(y);\n}").line, 2);
+        assert_eq!(err("fn f(a) { } fn main() { f(); }").message, "f takes 1 arguments but 0 were given");
+        assert_eq!(err("fn main() { g(); }").message, "unknown function g");
+        assert_eq!(err("fn main() { }\nfn main() { }").line, 2);
+    }
+}
@@ -0,0 +1,427 @@
+use std::collections::HashMap;
+use std::vec::Vec;
+
+use super::CompileError;
+use super::parser::{ BinOp, Expr, Function, Stmt, UnaryOp };
+use crate::intcode::Word;
+use crate::intcode::instruction::{ Instruction, Mode, Opcode };
+
+// frame layout, relative to the base: return address, parameters, locals,
+// then temporaries. the return value is handed back in slot 1.
+const RETURN_ADDR_SLOT: Word = 0;
+const RETURN_VALUE_SLOT: Word = 1;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Value {
+    Const(Word),
+    Label(usize),
+    // offset from the end of the current frame, where a callee's frame starts
+    PastFrame(Word),
+    // negated size of the current frame
+    Unframe,
+    Temp(usize)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Arg {
+    mode: Mode,
+    value: Value
+}
+
+struct FnState {
+    scopes: Vec<HashMap<String, Word>>,
+    next_slot: Word,
+    temps_in_use: usize,
+    max_temps: usize,
+    start: usize
+}
+
+struct Codegen<'a> {
+    code: Vec<Value>,
+    labels: Vec<Option<usize>>,
+    functions: HashMap<&'a str, (usize, usize)>,
+    state: FnState
+}
+
+fn imm(value: Word) -> Arg {
+    Arg { mode: Mode::Immediate, value: Value::Const(value) }
+}
+
+fn imm_value(value: Value) -> Arg {
+    Arg { mode: Mode::Immediate, value: value }
+}
+
+fn slot(slot: Word) -> Arg {
+    Arg { mode: Mode::Relative, value: Value::Const(slot) }
+}
+
+pub fn generate(functions: &[Function]) -> Result<Vec<Word>, CompileError> {
+    let mut gen = Codegen {
+        code: Vec::new(),
+        labels: Vec::new(),
+        functions: HashMap::new(),
+        state: FnState::new(0, 0)
+    };
+
+    for function in functions.iter() {
+        let label = gen.new_label();
+
+        if gen.functions.insert(&function.name, (label, function.params.len())).is_some() {
+            return Err(CompileError::new(function.line, &format!("function {} defined twice", function.name)));
+        }
+    }
+
+    let (main, main_arity) = *gen.functions.get("main")
+        .ok_or(CompileError::new(1, "no main function"))?;
+
+    if main_arity != 0 {
+        return Err(CompileError::new(1, "main takes no parameters"));
+    }
+
+    // entry: put the stack after the program, call main, then halt
+    let stack = gen.new_label();
+    let halt = gen.new_label();
+
+    gen.emit(Opcode::AdjustBase, &[imm_value(Value::Label(stack))]);
+    gen.emit(Opcode::Add, &[imm_value(Value::Label(halt)), imm(0), slot(RETURN_ADDR_SLOT)]);
+    gen.emit(Opcode::JumpIfTrue, &[imm(1), imm_value(Value::Label(main))]);
+    gen.place_label(halt);
+    gen.emit(Opcode::Halt, &[]);
+
+    for function in functions.iter() {
+        gen.function(function)?;
+    }
+
+    gen.place_label(stack);
+
+    Ok(gen.finish())
+}
+
+impl FnState {
+    fn new(param_cnt: usize, start: usize) -> FnState {
+        FnState {
+            scopes: vec!(HashMap::new()),
+            next_slot: 1 + param_cnt as Word,
+            temps_in_use: 0,
+            max_temps: 0,
+            start: start
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Word> {
+        self.scopes.iter().rev().filter_map(|scope| scope.get(name)).next().cloned()
+    }
+}
+
+impl<'a> Codegen<'a> {
+    fn new_label(&mut self) -> usize {
+        self.labels.push(None);
+        self.labels.len() - 1
+    }
+
+    fn place_label(&mut self, label: usize) {
+        self.labels[label] = Some(self.code.len());
+    }
+
+    fn emit(&mut self, opcode: Opcode, args: &[Arg]) {
+        let mut instr = Instruction::new(opcode, [Mode::Position; 3]);
+
+        for (param_i, arg) in args.iter().enumerate() {
+            instr.modes[param_i] = arg.mode;
+        }
+
+        self.code.push(Value::Const(instr.encode()));
+        self.code.extend(args.iter().map(|arg| arg.value));
+    }
+
+    fn alloc_temp(&mut self) -> Arg {
+        let temp = self.state.temps_in_use;
+
+        self.state.temps_in_use += 1;
+        if self.state.temps_in_use > self.state.max_temps {
+            self.state.max_temps = self.state.temps_in_use;
+        }
+
+        Arg { mode: Mode::Relative, value: Value::Temp(temp) }
+    }
+
+    // temporaries are freed in the reverse order they were allocated
+    fn release(&mut self, arg: Arg) {
+        if let Value::Temp(_) = arg.value {
+            self.state.temps_in_use -= 1;
+        }
+    }
+
+    fn function(&mut self, function: &'a Function) -> Result<(), CompileError> {
+        let (label, _) = self.functions[function.name.as_str()];
+
+        self.place_label(label);
+        self.state = FnState::new(function.params.len(), self.code.len());
+
+        for (i, param) in function.params.iter().enumerate() {
+            self.state.scopes[0].insert(param.clone(), 1 + i as Word);
+        }
+
+        for stmt in function.body.iter() {
+            self.statement(stmt)?;
+        }
+
+        // falling off the end returns 0
+        self.statement(&Stmt::Return(None))?;
+
+        self.resolve_frame();
+
+        Ok(())
+    }
+
+    fn resolve_frame(&mut self) {
+        let temp_base = self.state.next_slot;
+        let frame_size = temp_base + self.state.max_temps as Word;
+
+        for value in self.code[self.state.start..].iter_mut() {
+            *value = match *value {
+                Value::PastFrame(offset) => Value::Const(frame_size + offset),
+                Value::Unframe => Value::Const(-frame_size),
+                Value::Temp(temp) => Value::Const(temp_base + temp as Word),
+                other => other
+            };
+        }
+    }
+
+    fn finish(self) -> Vec<Word> {
+        let labels = self.labels;
+
+        self.code.iter().map(|value| match *value {
+            Value::Const(word) => word,
+            Value::Label(label) => labels[label].expect("label never placed") as Word,
+            other => panic!("unresolved value {:?}", other)
+        }).collect()
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) -> Result<(), CompileError> {
+        self.state.scopes.push(HashMap::new());
+
+        for stmt in stmts.iter() {
+            self.statement(stmt)?;
+        }
+
+        self.state.scopes.pop();
+
+        Ok(())
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Result<(), CompileError> {
+        match stmt {
+            Stmt::Let(name, value) => {
+                let value = self.expr(value)?;
+                self.release(value);
+
+                // every let gets its own slot, so shadowed values survive
+                let slot_i = self.state.next_slot;
+                self.state.next_slot += 1;
+
+                self.emit(Opcode::Add, &[value, imm(0), slot(slot_i)]);
+                self.state.scopes.last_mut().unwrap().insert(name.clone(), slot_i);
+            },
+            Stmt::Assign(name, value, line) => {
+                let slot_i = self.state.lookup(name)
+                    .ok_or(CompileError::new(*line, &format!("unknown variable {}", name)))?;
+
+                let value = self.expr(value)?;
+                self.release(value);
+
+                self.emit(Opcode::Add, &[value, imm(0), slot(slot_i)]);
+            },
+            Stmt::If(cond, then_block, else_block) => {
+                let else_label = self.new_label();
+                let end_label = self.new_label();
+
+                let cond = self.expr(cond)?;
+                self.release(cond);
+                self.emit(Opcode::JumpIfFalse, &[cond, imm_value(Value::Label(else_label))]);
+
+                self.block(then_block)?;
+
+                if else_block.is_empty() {
+                    self.place_label(else_label);
+                } else {
+                    self.emit(Opcode::JumpIfTrue, &[imm(1), imm_value(Value::Label(end_label))]);
+                    self.place_label(else_label);
+                    self.block(else_block)?;
+                }
+
+                self.place_label(end_label);
+            },
+            Stmt::While(cond, body) => {
+                let top_label = self.new_label();
+                let end_label = self.new_label();
+
+                self.place_label(top_label);
+                let cond = self.expr(cond)?;
+                self.release(cond);
+                self.emit(Opcode::JumpIfFalse, &[cond, imm_value(Value::Label(end_label))]);
+
+                self.block(body)?;
+
+                self.emit(Opcode::JumpIfTrue, &[imm(1), imm_value(Value::Label(top_label))]);
+                self.place_label(end_label);
+            },
+            Stmt::Return(value) => {
+                let value = match value {
+                    Some(value) => self.expr(value)?,
+                    None => imm(0)
+                };
+                self.release(value);
+
+                self.emit(Opcode::Add, &[value, imm(0), slot(RETURN_VALUE_SLOT)]);
+                self.emit(Opcode::JumpIfTrue, &[imm(1), slot(RETURN_ADDR_SLOT)]);
+            },
+            Stmt::Output(value) => {
+                let value = self.expr(value)?;
+                self.release(value);
+
+                self.emit(Opcode::Output, &[value]);
+            },
+            Stmt::Expr(expr) => {
+                let value = self.expr(expr)?;
+                self.release(value);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<Arg, CompileError> {
+        match expr {
+            Expr::Number(n) => Ok(imm(*n)),
+            Expr::Var(name, line) => {
+                let slot_i = self.state.lookup(name)
+                    .ok_or(CompileError::new(*line, &format!("unknown variable {}", name)))?;
+
+                Ok(slot(slot_i))
+            },
+            Expr::Input => {
+                let temp = self.alloc_temp();
+                self.emit(Opcode::Input, &[temp]);
+
+                Ok(temp)
+            },
+            Expr::Call(name, args, line) => self.call(name, args, *line),
+            Expr::Unary(op, operand) => {
+                let operand = self.expr(operand)?;
+                self.release(operand);
+                let temp = self.alloc_temp();
+
+                match op {
+                    UnaryOp::Neg => self.emit(Opcode::Mul, &[operand, imm(-1), temp]),
+                    UnaryOp::Not => self.emit(Opcode::Equals, &[operand, imm(0), temp])
+                }
+
+                Ok(temp)
+            },
+            Expr::Binary(BinOp::And, left, right) => self.short_circuit(left, right, true),
+            Expr::Binary(BinOp::Or, left, right) => self.short_circuit(left, right, false),
+            Expr::Binary(op, left, right) => {
+                let left = self.expr(left)?;
+                let right = self.expr(right)?;
+                self.release(right);
+                self.release(left);
+                let temp = self.alloc_temp();
+
+                match op {
+                    BinOp::Add => self.emit(Opcode::Add, &[left, right, temp]),
+                    BinOp::Mul => self.emit(Opcode::Mul, &[left, right, temp]),
+                    BinOp::Sub => {
+                        // temp may share a slot with left, so negate into a scratch slot
+                        let scratch = self.alloc_temp();
+                        self.emit(Opcode::Mul, &[right, imm(-1), scratch]);
+                        self.emit(Opcode::Add, &[left, scratch, temp]);
+                        self.release(scratch);
+                    },
+                    BinOp::Less => self.emit(Opcode::LessThan, &[left, right, temp]),
+                    BinOp::Greater => self.emit(Opcode::LessThan, &[right, left, temp]),
+                    BinOp::LessEq => {
+                        self.emit(Opcode::LessThan, &[right, left, temp]);
+                        self.emit(Opcode::Equals, &[temp, imm(0), temp]);
+                    },
+                    BinOp::GreaterEq => {
+                        self.emit(Opcode::LessThan, &[left, right, temp]);
+                        self.emit(Opcode::Equals, &[temp, imm(0), temp]);
+                    },
+                    BinOp::Eq => self.emit(Opcode::Equals, &[left, right, temp]),
+                    BinOp::NotEq => {
+                        self.emit(Opcode::Equals, &[left, right, temp]);
+                        self.emit(Opcode::Equals, &[temp, imm(0), temp]);
+                    },
+                    BinOp::And | BinOp::Or => unreachable!()
+                }
+
+                Ok(temp)
+            }
+        }
+    }
+
+    // && and || only evaluate the right side when the left doesn't decide it
+    fn short_circuit(&mut self, left: &Expr, right: &Expr, is_and: bool) -> Result<Arg, CompileError> {
+        let end_label = self.new_label();
+        let result = self.alloc_temp();
+
+        let left = self.expr(left)?;
+        self.release(left);
+        self.emit(Opcode::Equals, &[left, imm(0), result]);
+        // result is now !left; flip it into a boolean of left
+        self.emit(Opcode::Equals, &[result, imm(0), result]);
+
+        let skip = if is_and { Opcode::JumpIfFalse } else { Opcode::JumpIfTrue };
+        self.emit(skip, &[result, imm_value(Value::Label(end_label))]);
+
+        let right = self.expr(right)?;
+        self.release(right);
+        self.emit(Opcode::Equals, &[right, imm(0), result]);
+        self.emit(Opcode::Equals, &[result, imm(0), result]);
+
+        self.place_label(end_label);
+
+        Ok(result)
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr], line: usize) -> Result<Arg, CompileError> {
+        let (label, arity) = *self.functions.get(name)
+            .ok_or(CompileError::new(line, &format!("unknown function {}", name)))?;
+
+        if arity != args.len() {
+            let message = format!("{} takes {} arguments but {} were given", name, arity, args.len());
+            return Err(CompileError::new(line, &message));
+        }
+
+        let mut values = Vec::new();
+        for arg in args.iter() {
+            values.push(self.expr(arg)?);
+        }
+
+        // arguments go straight into the callee's parameter slots
+        for (i, value) in values.iter().enumerate() {
+            let dest = Arg { mode: Mode::Relative, value: Value::PastFrame(1 + i as Word) };
+            self.emit(Opcode::Add, &[*value, imm(0), dest]);
+        }
+
+        for value in values.into_iter().rev() {
+            self.release(value);
+        }
+
+        let return_label = self.new_label();
+        let return_slot = Arg { mode: Mode::Relative, value: Value::PastFrame(RETURN_ADDR_SLOT) };
+
+        self.emit(Opcode::Add, &[imm_value(Value::Label(return_label)), imm(0), return_slot]);
+        self.emit(Opcode::AdjustBase, &[imm_value(Value::PastFrame(0))]);
+        self.emit(Opcode::JumpIfTrue, &[imm(1), imm_value(Value::Label(label))]);
+        self.place_label(return_label);
+        self.emit(Opcode::AdjustBase, &[imm_value(Value::Unframe)]);
+
+        let result = self.alloc_temp();
+        let returned = Arg { mode: Mode::Relative, value: Value::PastFrame(RETURN_VALUE_SLOT) };
+        self.emit(Opcode::Add, &[returned, imm(0), result]);
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,166 @@
+use std::vec::Vec;
+
+use super::CompileError;
+use crate::intcode::Word;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Token {
+    Number(Word),
+    Ident(String),
+    Fn,
+    Let,
+    If,
+    Else,
+    While,
+    Return,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    Comma,
+    Semi,
+    Assign,
+    Plus,
+    Minus,
+    Star,
+    Bang,
+    Less,
+    Greater,
+    LessEq,
+    GreaterEq,
+    EqEq,
+    NotEq,
+    AndAnd,
+    OrOr
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Spanned {
+    pub token: Token,
+    pub line: usize
+}
+
+fn keyword(ident: &str) -> Option<Token> {
+    match ident {
+        "fn" => Some(Token::Fn),
+        "let" => Some(Token::Let),
+        "if" => Some(Token::If),
+        "else" => Some(Token::Else),
+        "while" => Some(Token::While),
+        "return" => Some(Token::Return),
+        _ => None
+    }
+}
+
+pub fn tokenize(source: &str) -> Result<Vec<Spanned>, CompileError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).cloned();
+
+        if c == '\n' {
+            line += 1;
+            i += 1;
+            continue;
+        }
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        // line comments
+        if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+
+            let digits: String = chars[start..i].iter().collect();
+            let number = digits.parse()
+                .map_err(|_| CompileError::new(line, &format!("number too large: {}", digits)))?;
+
+            tokens.push(Spanned { token: Token::Number(number), line: line });
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+
+            let ident: String = chars[start..i].iter().collect();
+            let token = keyword(&ident).unwrap_or(Token::Ident(ident));
+
+            tokens.push(Spanned { token: token, line: line });
+            continue;
+        }
+
+        let (token, width) = match (c, next) {
+            ('<', Some('=')) => (Token::LessEq, 2),
+            ('>', Some('=')) => (Token::GreaterEq, 2),
+            ('=', Some('=')) => (Token::EqEq, 2),
+            ('!', Some('=')) => (Token::NotEq, 2),
+            ('&', Some('&')) => (Token::AndAnd, 2),
+            ('|', Some('|')) => (Token::OrOr, 2),
+            ('(', _) => (Token::LParen, 1),
+            (')', _) => (Token::RParen, 1),
+            ('{', _) => (Token::LBrace, 1),
+            ('}', _) => (Token::RBrace, 1),
+            (',', _) => (Token::Comma, 1),
+            (';', _) => (Token::Semi, 1),
+            ('=', _) => (Token::Assign, 1),
+            ('+', _) => (Token::Plus, 1),
+            ('-', _) => (Token::Minus, 1),
+            ('*', _) => (Token::Star, 1),
+            ('!', _) => (Token::Bang, 1),
+            ('<', _) => (Token::Less, 1),
+            ('>', _) => (Token::Greater, 1),
+            _ => return Err(CompileError::new(line, &format!("unexpected character '{}'", c)))
+        };
+
+        tokens.push(Spanned { token: token, line: line });
+        i += width;
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_statement() {
+        let tokens: Vec<Token> = tokenize("let x = y <= 10; // trailing")
+            .unwrap()
+            .into_iter()
+            .map(|s| s.token)
+            .collect();
+
+        let expected = vec!(Token::Let, Token::Ident(String::from("x")), Token::Assign,
+            Token::Ident(String::from("y")), Token::LessEq, Token::Number(10), Token::Semi);
+
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn tracks_lines() {
+        let tokens = tokenize("fn\n\nmain").unwrap();
+
+        assert_eq!(tokens[1].line, 3);
+        assert_eq!(tokenize("\n$").unwrap_err().line, 2);
+    }
+}
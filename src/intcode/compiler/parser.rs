@@ -0,0 +1,383 @@
+use std::vec::Vec;
+
+use super::CompileError;
+use super::lexer::{ Spanned, Token };
+use crate::intcode::Word;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Less,
+    Greater,
+    LessEq,
+    GreaterEq,
+    Eq,
+    NotEq,
+    And,
+    Or
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum UnaryOp {
+    Neg,
+    Not
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Expr {
+    Number(Word),
+    Var(String, usize),
+    Input,
+    Call(String, Vec<Expr>, usize),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>)
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Stmt {
+    Let(String, Expr),
+    Assign(String, Expr, usize),
+    If(Expr, Vec<Stmt>, Vec<Stmt>),
+    While(Expr, Vec<Stmt>),
+    Return(Option<Expr>),
+    Output(Expr),
+    Expr(Expr)
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Vec<Stmt>,
+    pub line: usize
+}
+
+struct Parser {
+    tokens: Vec<Spanned>,
+    pos: usize
+}
+
+pub fn parse(tokens: Vec<Spanned>) -> Result<Vec<Function>, CompileError> {
+    let mut parser = Parser { tokens: tokens, pos: 0 };
+    let mut functions = Vec::new();
+
+    while parser.peek().is_some() {
+        functions.push(parser.function()?);
+    }
+
+    Ok(functions)
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|s| &s.token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos)
+            .or(self.tokens.last())
+            .map_or(1, |s| s.line)
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, CompileError> {
+        let found = match self.peek() {
+            Some(token) => format!("{:?}", token),
+            None => String::from("end of input")
+        };
+
+        Err(CompileError::new(self.line(), &format!("{}, found {}", message, found)))
+    }
+
+    fn accept(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            return true;
+        }
+
+        false
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), CompileError> {
+        if self.accept(&token) {
+            return Ok(());
+        }
+
+        self.error(&format!("expected {:?}", token))
+    }
+
+    fn ident(&mut self) -> Result<String, CompileError> {
+        if let Some(Token::Ident(name)) = self.peek() {
+            let name = name.clone();
+            self.pos += 1;
+            return Ok(name);
+        }
+
+        self.error("expected identifier")
+    }
+
+    fn function(&mut self) -> Result<Function, CompileError> {
+        let line = self.line();
+        self.expect(Token::Fn)?;
+
+        let name = self.ident()?;
+        let mut params = Vec::new();
+
+        self.expect(Token::LParen)?;
+        if !self.accept(&Token::RParen) {
+            loop {
+                params.push(self.ident()?);
+
+                if self.accept(&Token::RParen) {
+                    break;
+                }
+                self.expect(Token::Comma)?;
+            }
+        }
+
+        Ok(Function {
+            name: name,
+            params: params,
+            body: self.block()?,
+            line: line
+        })
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, CompileError> {
+        self.expect(Token::LBrace)?;
+
+        let mut stmts = Vec::new();
+        while !self.accept(&Token::RBrace) {
+            if self.peek().is_none() {
+                return self.error("expected '}'");
+            }
+
+            stmts.push(self.statement()?);
+        }
+
+        Ok(stmts)
+    }
+
+    fn statement(&mut self) -> Result<Stmt, CompileError> {
+        let line = self.line();
+
+        if self.accept(&Token::Let) {
+            let name = self.ident()?;
+            self.expect(Token::Assign)?;
+            let value = self.expr()?;
+            self.expect(Token::Semi)?;
+
+            return Ok(Stmt::Let(name, value));
+        }
+
+        if self.accept(&Token::If) {
+            return self.if_rest();
+        }
+
+        if self.accept(&Token::While) {
+            let cond = self.expr()?;
+            return Ok(Stmt::While(cond, self.block()?));
+        }
+
+        if self.accept(&Token::Return) {
+            let value = if self.peek() == Some(&Token::Semi) { None } else { Some(self.expr()?) };
+            self.expect(Token::Semi)?;
+
+            return Ok(Stmt::Return(value));
+        }
+
+        // assignment needs two tokens of lookahead to tell apart from an expression
+        if let (Some(Token::Ident(name)), Some(Token::Assign)) =
+            (self.peek().cloned(), self.tokens.get(self.pos + 1).map(|s| &s.token)) {
+            self.pos += 2;
+            let value = self.expr()?;
+            self.expect(Token::Semi)?;
+
+            return Ok(Stmt::Assign(name, value, line));
+        }
+
+        let expr = self.expr()?;
+        self.expect(Token::Semi)?;
+
+        // output is a statement since it has no value to give back
+        match expr {
+            Expr::Call(ref name, ref args, _) if name == "output" && args.len() == 1 =>
+                Ok(Stmt::Output(args[0].clone())),
+            Expr::Call(ref name, _, line) if name == "output" =>
+                Err(CompileError::new(line, "output takes exactly one argument")),
+            expr => Ok(Stmt::Expr(expr))
+        }
+    }
+
+    fn if_rest(&mut self) -> Result<Stmt, CompileError> {
+        let cond = self.expr()?;
+        let then_block = self.block()?;
+
+        let else_block = if !self.accept(&Token::Else) {
+            Vec::new()
+        } else if self.accept(&Token::If) {
+            vec!(self.if_rest()?)
+        } else {
+            self.block()?
+        };
+
+        Ok(Stmt::If(cond, then_block, else_block))
+    }
+
+    fn expr(&mut self) -> Result<Expr, CompileError> {
+        self.binary(0)
+    }
+
+    // precedence climbing, loosest binding level first
+    fn binary(&mut self, level: usize) -> Result<Expr, CompileError> {
+        const LEVELS: [&[(Token, BinOp)]; 5] = [
+            &[(Token::OrOr, BinOp::Or)],
+            &[(Token::AndAnd, BinOp::And)],
+            &[(Token::EqEq, BinOp::Eq), (Token::NotEq, BinOp::NotEq)],
+            &[(Token::Less, BinOp::Less), (Token::Greater, BinOp::Greater),
+              (Token::LessEq, BinOp::LessEq), (Token::GreaterEq, BinOp::GreaterEq)],
+            &[(Token::Plus, BinOp::Add), (Token::Minus, BinOp::Sub)]
+        ];
+
+        if level == LEVELS.len() {
+            return self.product();
+        }
+
+        let mut left = self.binary(level + 1)?;
+
+        'outer: loop {
+            for (token, op) in LEVELS[level].iter() {
+                if self.accept(token) {
+                    let right = self.binary(level + 1)?;
+                    left = Expr::Binary(*op, Box::new(left), Box::new(right));
+                    continue 'outer;
+                }
+            }
+
+            return Ok(left);
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, CompileError> {
+        let mut left = self.unary()?;
+
+        while self.accept(&Token::Star) {
+            let right = self.unary()?;
+            left = Expr::Binary(BinOp::Mul, Box::new(left), Box::new(right));
+        }
+
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, CompileError> {
+        if self.accept(&Token::Minus) {
+            return Ok(match self.unary()? {
+                Expr::Number(n) => Expr::Number(-n),
+                expr => Expr::Unary(UnaryOp::Neg, Box::new(expr))
+            });
+        }
+
+        if self.accept(&Token::Bang) {
+            return Ok(Expr::Unary(UnaryOp::Not, Box::new(self.unary()?)));
+        }
+
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, CompileError> {
+        let line = self.line();
+
+        match self.peek().cloned() {
+            Some(Token::Number(n)) => {
+                self.pos += 1;
+                Ok(Expr::Number(n))
+            },
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            },
+            Some(Token::Ident(name)) => {
+                self.pos += 1;
+
+                if !self.accept(&Token::LParen) {
+                    return Ok(Expr::Var(name, line));
+                }
+
+                let mut args = Vec::new();
+                if !self.accept(&Token::RParen) {
+                    loop {
+                        args.push(self.expr()?);
+
+                        if self.accept(&Token::RParen) {
+                            break;
+                        }
+                        self.expect(Token::Comma)?;
+                    }
+                }
+
+                if name == "input" {
+                    if !args.is_empty() {
+                        return Err(CompileError::new(line, "input takes no arguments"));
+                    }
+                    return Ok(Expr::Input);
+                }
+
+                Ok(Expr::Call(name, args, line))
+            },
+            _ => self.error("expected expression")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::lexer::tokenize;
+
+    fn parse_expr(source: &str) -> Expr {
+        let source = format!("fn main() {{ {}; }}", source);
+        let functions = parse(tokenize(&source).unwrap()).unwrap();
+
+        match &functions[0].body[0] {
+            Stmt::Expr(expr) => expr.clone(),
+            other => panic!("expected expression, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        let expr = parse_expr("1 + 2 * 3 < 4");
+
+        let product = Expr::Binary(BinOp::Mul, Box::new(Expr::Number(2)), Box::new(Expr::Number(3)));
+        let sum = Expr::Binary(BinOp::Add, Box::new(Expr::Number(1)), Box::new(product));
+        let expected = Expr::Binary(BinOp::Less, Box::new(sum), Box::new(Expr::Number(4)));
+
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    fn negative_literal() {
+        assert_eq!(parse_expr("-7"), Expr::Number(-7));
+    }
+
+    #[test]
+    fn else_if_chain() {
+        let source = "fn main() { if 1 { } else if 2 { output(1); } else { output(2); } }";
+        let functions = parse(tokenize(source).unwrap()).unwrap();
+
+        match &functions[0].body[0] {
+            Stmt::If(_, _, else_block) => assert!(matches!(else_block[0], Stmt::If(_, _, _))),
+            other => panic!("expected if, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn reports_line() {
+        let err = parse(tokenize("fn main() {\n let = 3;\n}").unwrap()).unwrap_err();
+
+        assert_eq!(err.line, 2);
+    }
+}
@@ -0,0 +1,439 @@
+use std::fmt;
+use std::vec::Vec;
+
+use super::{ parse_program, Word };
+use super::engine::{ Dialect, Engine, Outcome, Termination };
+use super::machine::ErrorKind;
+
+// the instruction set written down as data. each suite is a file of cases:
+//
+//   # comment
+//   [add in position mode]
+//   dialect = day2
+//   program = 1,5,6,7,99,3,4,0
+//   memory = 1,5,6,7,99,3,4,7
+//
+// keys are `dialect` (required: day2, day5 or day9), `program` (required),
+// `inputs`, `outputs`, `memory` (a prefix of the final memory), `ends`
+// (halted, input_exhausted, step_limit or `error <kind> [word]`, halted by
+// default) and `steps`. `amplifiers = <phases>` runs a day 7 chain instead,
+// with `feedback = true` for a loop; its `outputs` is the final signal.
+//
+// every case runs on each engine that implements its dialect. engines that
+// can't report errors only get cases that halt.
+
+pub const SUITES: [(&str, &str); 7] = [
+    ("opcodes", include_str!("conformance/opcodes.cases")),
+    ("modes", include_str!("conformance/modes.cases")),
+    ("edges", include_str!("conformance/edges.cases")),
+    ("day2", include_str!("conformance/day2.cases")),
+    ("day5", include_str!("conformance/day5.cases")),
+    ("day7", include_str!("conformance/day7.cases")),
+    ("day9", include_str!("conformance/day9.cases"))
+];
+
+const DEFAULT_STEPS: usize = 100_000;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Amplifiers {
+    pub phases: Vec<Word>,
+    pub feedback: bool
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Case {
+    pub name: String,
+    pub dialect: Dialect,
+    pub program: Vec<Word>,
+    pub inputs: Vec<Word>,
+    pub outputs: Vec<Word>,
+    pub memory: Option<Vec<Word>>,
+    pub ends: Termination,
+    pub steps: usize,
+    pub amplifiers: Option<Amplifiers>
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Failure {
+    pub case: String,
+    pub engine: &'static str,
+    pub message: String
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] on {}: {}", self.case, self.engine, self.message)
+    }
+}
+
+fn parse_words(text: &str) -> Result<Vec<Word>, String> {
+    if text.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    parse_program(text).map_err(|err| err.to_string())
+}
+
+fn parse_dialect(text: &str) -> Result<Dialect, String> {
+    match text {
+        "day2" => Ok(Dialect::Day2),
+        "day5" => Ok(Dialect::Day5),
+        "day9" => Ok(Dialect::Day9),
+        _ => Err(format!("unknown dialect {}", text))
+    }
+}
+
+fn parse_ends(text: &str) -> Result<Termination, String> {
+    let mut words = text.split_whitespace();
+    let bad = || format!("unknown ending `{}`", text);
+
+    let ends = match words.next().ok_or_else(bad)? {
+        "halted" => Termination::Halted,
+        "input_exhausted" => Termination::InputExhausted,
+        "step_limit" => Termination::StepLimit,
+        "error" => {
+            let kind = words.next().ok_or_else(bad)?;
+            let mut word = || -> Result<Word, String> {
+                words.next().and_then(|word| word.parse().ok()).ok_or_else(bad)
+            };
+
+            Termination::Error(match kind {
+                "invalid_opcode" => ErrorKind::InvalidOpcode(word()?),
+                "invalid_mode" => ErrorKind::InvalidMode(word()?),
+                "invalid_address" => ErrorKind::InvalidAddress(word()?),
+                "immediate_write" => ErrorKind::ImmediateWrite,
+                "arithmetic_overflow" => ErrorKind::ArithmeticOverflow,
+                _ => return Err(bad())
+            })
+        },
+        _ => return Err(bad())
+    };
+
+    match words.next() {
+        Some(_) => Err(bad()),
+        None => Ok(ends)
+    }
+}
+
+struct Draft {
+    name: String,
+    line: usize,
+    dialect: Option<Dialect>,
+    program: Option<Vec<Word>>,
+    case: Case
+}
+
+impl Draft {
+    fn new(name: &str, line: usize) -> Draft {
+        Draft {
+            name: String::from(name),
+            line: line,
+            dialect: None,
+            program: None,
+            case: Case {
+                name: String::from(name),
+                dialect: Dialect::Day2,
+                program: Vec::new(),
+                inputs: Vec::new(),
+                outputs: Vec::new(),
+                memory: None,
+                ends: Termination::Halted,
+                steps: DEFAULT_STEPS,
+                amplifiers: None
+            }
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "dialect" => self.dialect = Some(parse_dialect(value)?),
+            "program" => self.program = Some(parse_words(value)?),
+            "inputs" => self.case.inputs = parse_words(value)?,
+            "outputs" => self.case.outputs = parse_words(value)?,
+            "memory" => self.case.memory = Some(parse_words(value)?),
+            "ends" => self.case.ends = parse_ends(value)?,
+            "steps" => self.case.steps = value.parse().map_err(|_| format!("bad step count {}", value))?,
+            "amplifiers" => {
+                let feedback = self.case.amplifiers.as_ref().is_some_and(|amps| amps.feedback);
+                self.case.amplifiers = Some(Amplifiers { phases: parse_words(value)?, feedback: feedback });
+            },
+            "feedback" => {
+                let amps = self.case.amplifiers.as_mut().ok_or("feedback without amplifiers")?;
+                amps.feedback = value == "true";
+            },
+            _ => return Err(format!("unknown key {}", key))
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Case, ParseError> {
+        let error = |message: &str| ParseError {
+            line: self.line,
+            message: format!("[{}] {}", self.name, message)
+        };
+
+        let mut case = self.case.clone();
+        case.dialect = self.dialect.ok_or_else(|| error("has no dialect"))?;
+        case.program = self.program.clone().ok_or_else(|| error("has no program"))?;
+
+        Ok(case)
+    }
+}
+
+pub fn parse(text: &str) -> Result<Vec<Case>, ParseError> {
+    let mut cases = Vec::new();
+    let mut draft: Option<Draft> = None;
+
+    for (line_i, line) in text.lines().enumerate() {
+        let line_no = line_i + 1;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            if let Some(done) = draft.take() {
+                cases.push(done.finish()?);
+            }
+
+            draft = Some(Draft::new(line[1..line.len() - 1].trim(), line_no));
+            continue;
+        }
+
+        let error = |message: String| ParseError { line: line_no, message: message };
+
+        let current = draft.as_mut().ok_or_else(|| error(String::from("setting outside a case")))?;
+        let eq = line.find('=').ok_or_else(|| error(String::from("expected `key = value`")))?;
+
+        current.set(line[..eq].trim(), line[eq + 1..].trim()).map_err(error)?;
+    }
+
+    if let Some(done) = draft {
+        cases.push(done.finish()?);
+    }
+
+    Ok(cases)
+}
+
+// day 7's amplifiers, each fed its phase then the signal from the one before.
+// a feedback loop reruns the chain from scratch with every signal seen so far
+// until nothing new comes out, which only needs engines to run to completion
+fn amplify(engine: &dyn Engine, case: &Case, amps: &Amplifiers) -> Result<Word, String> {
+    let count = amps.phases.len();
+    let mut received: Vec<Vec<Word>> = vec!(Vec::new(); count);
+    received[0].push(0);
+
+    let mut outcomes: Vec<Outcome> = Vec::new();
+
+    loop {
+        outcomes.clear();
+
+        for (i, &phase) in amps.phases.iter().enumerate() {
+            let mut inputs = vec!(phase);
+            inputs.extend(received[i].iter().cloned());
+
+            let outcome = engine.execute(&case.program, &inputs, case.steps);
+
+            match &outcome.termination {
+                Termination::Halted => (),
+                Termination::InputExhausted if amps.feedback => (),
+                other => return Err(format!("amplifier {} stopped with {:?}", i, other))
+            }
+
+            if i + 1 < count {
+                received[i + 1] = outcome.outputs.clone();
+            }
+            outcomes.push(outcome);
+        }
+
+        let last = &outcomes[count - 1];
+        if !amps.feedback || last.termination == Termination::Halted {
+            return last.outputs.last().cloned().ok_or_else(|| String::from("no signal came out"));
+        }
+
+        let mut looped = vec!(0);
+        looped.extend(last.outputs.iter().cloned());
+
+        if looped == received[0] {
+            return Err(String::from("the loop stopped making progress"));
+        }
+        received[0] = looped;
+    }
+}
+
+// a rewritten program is held to less, see Outcome::agrees_after_rewrite
+fn compare(case: &Case, outcome: &Outcome, rewritten: bool) -> Option<String> {
+    if rewritten && case.ends == Termination::StepLimit {
+        if !outcome.outputs.starts_with(&case.outputs) {
+            return Some(format!("output {:?}, expected it to start {:?}", outcome.outputs, case.outputs));
+        }
+
+        return None;
+    }
+
+    if outcome.termination != case.ends {
+        return Some(format!("ended with {:?}, expected {:?}", outcome.termination, case.ends));
+    }
+
+    if outcome.outputs != case.outputs {
+        return Some(format!("output {:?}, expected {:?}", outcome.outputs, case.outputs));
+    }
+
+    if let Some(memory) = case.memory.as_ref().filter(|_| !rewritten) {
+        let actual: Vec<Word> = (0..memory.len()).map(|addr| outcome.memory.get(addr).cloned().unwrap_or(0)).collect();
+
+        if &actual != memory {
+            return Some(format!("memory {:?}, expected {:?}", actual, memory));
+        }
+    }
+
+    None
+}
+
+pub fn takes(engine: &dyn Engine, case: &Case) -> bool {
+    let halts = case.ends == Termination::Halted && !case.amplifiers.as_ref().is_some_and(|amps| amps.feedback);
+
+    engine.dialect() >= case.dialect && (halts || engine.checked())
+}
+
+pub fn run(case: &Case, engines: &[Box<dyn Engine>]) -> Vec<Failure> {
+    let mut failures = Vec::new();
+
+    for engine in engines.iter().filter(|engine| takes(engine.as_ref(), case)) {
+        let message = match &case.amplifiers {
+            Some(amps) => match amplify(engine.as_ref(), case, amps) {
+                Ok(signal) if case.outputs == [signal] => None,
+                Ok(signal) => Some(format!("signal {}, expected {:?}", signal, case.outputs)),
+                Err(message) => Some(message)
+            },
+            None => compare(case, &engine.execute(&case.program, &case.inputs, case.steps), engine.rewrites())
+        };
+
+        if let Some(message) = message {
+            failures.push(Failure {
+                case: case.name.clone(),
+                engine: engine.name(),
+                message: message
+            });
+        }
+    }
+
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::engine;
+
+    fn run_suite(name: &str) {
+        let text = SUITES.iter().find(|suite| suite.0 == name).unwrap().1;
+        let cases = parse(text).unwrap_or_else(|err| panic!("{}: {}", name, err));
+        let engines = engine::all();
+
+        assert!(!cases.is_empty());
+
+        let failures: Vec<String> = cases.iter()
+            .flat_map(|case| run(case, &engines))
+            .map(|failure| failure.to_string())
+            .collect();
+
+        assert!(failures.is_empty(), "{} failures:\n{}", name, failures.join("\n"));
+    }
+
+    #[test]
+    fn opcodes() {
+        run_suite("opcodes");
+    }
+
+    #[test]
+    fn modes() {
+        run_suite("modes");
+    }
+
+    #[test]
+    fn edges() {
+        run_suite("edges");
+    }
+
+    #[test]
+    fn day2_examples() {
+        run_suite("day2");
+    }
+
+    #[test]
+    fn day5_examples() {
+        run_suite("day5");
+    }
+
+    #[test]
+    fn day7_examples() {
+        run_suite("day7");
+    }
+
+    #[test]
+    fn day9_examples() {
+        run_suite("day9");
+    }
+
+    #[test]
+    fn every_engine_is_exercised() {
+        let engines = engine::all();
+        let cases: Vec<Case> = SUITES.iter().flat_map(|suite| parse(suite.1).unwrap()).collect();
+
+        for engine in engines.iter() {
+            let taken = cases.iter().filter(|case| takes(engine.as_ref(), case)).count();
+            assert!(taken > 0, "{} runs no cases", engine.name());
+        }
+    }
+
+    #[test]
+    fn faults_and_day9_cases_have_several_engines() {
+        let engines = engine::all();
+        let cases: Vec<Case> = SUITES.iter().flat_map(|suite| parse(suite.1).unwrap()).collect();
+
+        for case in cases.iter().filter(|case| case.ends != Termination::Halted || case.dialect == Dialect::Day9) {
+            let names: Vec<&str> = engines.iter()
+                .filter(|engine| takes(engine.as_ref(), case))
+                .map(|engine| engine.name())
+                .collect();
+
+            assert_eq!(names, vec!("reference", "traced", "async", "ffi", "optimized"), "[{}]", case.name);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse("program = 1").unwrap_err().line, 1);
+        assert_eq!(parse("[a]\ndialect = day5").unwrap_err().message, "[a] has no program");
+        assert_eq!(parse("[a]\ndialect = day5\nprogram = 99\nends = error bogus").unwrap_err().line, 4);
+        assert!(parse("[a]\ndialect = day3\nprogram = 99").is_err());
+
+        let cases = parse("[a]\ndialect = day9\nprogram = 99\nends = error invalid_mode 1104").unwrap();
+        assert_eq!(cases[0].ends, Termination::Error(ErrorKind::InvalidMode(1104)));
+    }
+
+    #[test]
+    fn reports_failures() {
+        let cases = parse("[wrong]\ndialect = day5\nprogram = 104,1,99\noutputs = 2").unwrap();
+        let failures = run(&cases[0], &engine::all());
+
+        let engines: Vec<&str> = failures.iter().map(|failure| failure.engine).collect();
+        assert_eq!(engines, vec!("reference", "traced", "async", "ffi", "optimized", "day5", "day7"));
+        assert_eq!(failures[0].message, "output [1], expected [2]");
+    }
+}
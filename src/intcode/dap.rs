@@ -0,0 +1,879 @@
+mod protocol;
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{ self, BufRead, Write };
+use std::vec::Vec;
+
+use serde_json::{ json, Value };
+
+use super::{ parse_program, Word };
+use super::disasm;
+use super::machine::{ Event, Machine };
+
+pub use self::protocol::{ read_message, write_message };
+
+// a debug adapter speaking the Debug Adapter Protocol, so editors can drive
+// the reference machine. there's a single thread, and the "source" is the
+// disassembly listing taken at launch: one instruction per line, which is
+// what line breakpoints refer to. breakpoints can also be set by address
+// through instruction breakpoints.
+
+const THREAD_ID: u64 = 1;
+const LISTING_REFERENCE: u64 = 1;
+
+// variables references; frames are numbered from FRAME_SCOPES up
+const REGISTERS: u64 = 1;
+const MEMORY: u64 = 2;
+const FRAME_SCOPES: u64 = 100;
+
+// slots shown for each frame, counting from its base
+const FRAME_SLOTS: usize = 16;
+
+// a runaway continue gives control back after this many instructions
+const STEP_BUDGET: usize = 10_000_000;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Resume {
+    Continue,
+    StepIn,
+    // run until back at the same call depth or shallower
+    StepOver(usize),
+    // run until shallower than the given depth
+    StepOut(usize)
+}
+
+struct Session {
+    machine: Machine,
+    name: String,
+    listing: Vec<(usize, String)>,
+    line_breakpoints: BTreeSet<usize>,
+    address_breakpoints: BTreeSet<usize>,
+    // relative bases of the callers, innermost last
+    callers: Vec<Word>,
+    stop_on_entry: bool,
+    finished: bool
+}
+
+pub struct Server<W: Write> {
+    writer: W,
+    seq: u64,
+    session: Option<Session>,
+    disconnected: bool
+}
+
+type Reply = Result<Value, String>;
+
+impl Session {
+    fn line_of(&self, addr: usize) -> usize {
+        self.listing.iter().rposition(|&(start, _)| start <= addr).unwrap_or(0) + 1
+    }
+
+    fn source(&self) -> Value {
+        json!({ "name": format!("{}.disasm", self.name), "sourceReference": LISTING_REFERENCE })
+    }
+
+    fn at_breakpoint(&self) -> bool {
+        let addr = self.machine.instr_ptr();
+
+        if self.address_breakpoints.contains(&addr) {
+            return true;
+        }
+
+        // a line breakpoint only fires at the start of its instruction
+        self.listing.binary_search_by_key(&addr, |&(start, _)| start)
+            .map(|index| self.line_breakpoints.contains(&(index + 1)))
+            .unwrap_or(false)
+    }
+
+    // each frame as (instruction address, relative base), innermost first.
+    // a callee's return address is found in slot 0 of its frame
+    fn frames(&self) -> Vec<(usize, Word)> {
+        let mut frames = vec!((self.machine.instr_ptr(), self.machine.relative_base()));
+
+        for &base in self.callers.iter().rev() {
+            let callee_base = frames[frames.len() - 1].1;
+            let return_addr = self.machine.read(callee_base).unwrap_or(0).max(0) as usize;
+
+            frames.push((return_addr, base));
+        }
+
+        frames
+    }
+
+    fn step(&mut self) -> Result<Option<Event>, String> {
+        let base = self.machine.relative_base();
+        let event = self.machine.step().map_err(|err| err.to_string())?;
+        let new_base = self.machine.relative_base();
+
+        if new_base > base {
+            self.callers.push(base);
+        } else if new_base < base {
+            while self.callers.last().is_some_and(|&caller| caller >= new_base) {
+                self.callers.pop();
+            }
+        }
+
+        Ok(event)
+    }
+
+    fn evaluate(&mut self, expression: &str) -> Result<Word, String> {
+        let expression = expression.trim();
+
+        // lets a paused program be fed from the debug console
+        if let Some(value) = expression.strip_prefix("input ") {
+            let value = value.trim().parse().map_err(|_| format!("not a number: {}", value.trim()))?;
+            self.machine.push_input(value);
+
+            return Ok(value);
+        }
+
+        let mut parser = Expr { text: expression.as_bytes(), at: 0, machine: &self.machine };
+        let value = parser.sum()?;
+
+        parser.skip_space();
+        if parser.at != parser.text.len() {
+            return Err(format!("unexpected input at column {}", parser.at + 1));
+        }
+
+        Ok(value)
+    }
+}
+
+fn overflow(at: usize) -> String {
+    format!("overflow at column {}", at + 1)
+}
+
+// sum := term (('+' | '-') term)*
+// term := number | 'ip' | 'rb' | 'steps' | '[' sum ']' | '(' sum ')'
+struct Expr<'a> {
+    text: &'a [u8],
+    at: usize,
+    machine: &'a Machine
+}
+
+impl<'a> Expr<'a> {
+    fn skip_space(&mut self) {
+        while self.at < self.text.len() && self.text[self.at].is_ascii_whitespace() {
+            self.at += 1;
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_space();
+
+        if self.text.get(self.at) == Some(&byte) {
+            self.at += 1;
+            return true;
+        }
+
+        false
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if !self.eat(byte) {
+            return Err(format!("expected `{}` at column {}", byte as char, self.at + 1));
+        }
+
+        Ok(())
+    }
+
+    fn sum(&mut self) -> Result<Word, String> {
+        let mut value = self.term()?;
+
+        loop {
+            self.skip_space();
+            let start = self.at;
+
+            let result = if self.eat(b'+') {
+                value.checked_add(self.term()?)
+            } else if self.eat(b'-') {
+                value.checked_sub(self.term()?)
+            } else {
+                return Ok(value);
+            };
+
+            value = result.ok_or_else(|| overflow(start))?;
+        }
+    }
+
+    fn term(&mut self) -> Result<Word, String> {
+        if self.eat(b'[') {
+            let addr = self.sum()?;
+            self.expect(b']')?;
+
+            return self.machine.read(addr).map_err(|err| err.to_string());
+        }
+
+        if self.eat(b'(') {
+            let value = self.sum()?;
+            self.expect(b')')?;
+
+            return Ok(value);
+        }
+
+        self.skip_space();
+        let start = self.at;
+        if self.eat(b'-') {
+            return self.term()?.checked_neg().ok_or_else(|| overflow(start));
+        }
+
+        let start = self.at;
+        while self.at < self.text.len() && self.text[self.at].is_ascii_alphanumeric() {
+            self.at += 1;
+        }
+
+        let word = String::from_utf8_lossy(&self.text[start..self.at]);
+
+        match word.as_ref() {
+            "ip" => Ok(self.machine.instr_ptr() as Word),
+            "rb" => Ok(self.machine.relative_base()),
+            "steps" => Ok(self.machine.steps() as Word),
+            "" => Err(format!("expected a value at column {}", start + 1)),
+            word => word.parse().map_err(|_| format!("unknown name: {}", word))
+        }
+    }
+}
+
+fn launch_program(args: &Value) -> Result<(Vec<Word>, String), String> {
+    if let Some(path) = args["program"].as_str() {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+        let program = parse_program(&text).map_err(|err| format!("{}: {}", path, err))?;
+        let name = path.rsplit('/').next().unwrap_or(path);
+
+        return Ok((program, String::from(name)));
+    }
+
+    if let Some(code) = args["code"].as_str() {
+        return parse_program(code).map(|program| (program, String::from("program"))).map_err(|err| err.to_string());
+    }
+
+    Err(String::from("launch needs either `program` (a file) or `code`"))
+}
+
+fn words(value: &Value) -> Result<Vec<Word>, String> {
+    match value {
+        Value::Null => Ok(Vec::new()),
+        Value::Array(items) => items.iter()
+            .map(|item| item.as_i64().ok_or_else(|| format!("not a word: {}", item)))
+            .collect(),
+        _ => Err(String::from("expected an array of words"))
+    }
+}
+
+fn variable(name: String, value: Word) -> Value {
+    json!({ "name": name, "value": value.to_string(), "variablesReference": 0 })
+}
+
+impl<W: Write> Server<W> {
+    pub fn new(writer: W) -> Server<W> {
+        Server {
+            writer: writer,
+            seq: 0,
+            session: None,
+            disconnected: false
+        }
+    }
+
+    pub fn is_disconnected(&self) -> bool {
+        self.disconnected
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn send(&mut self, mut message: Value) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+
+        write_message(&mut self.writer, &message)
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> io::Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn stopped(&mut self, reason: &str, description: Option<String>) -> io::Result<()> {
+        let mut body = json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true });
+
+        if let Some(description) = description {
+            body["description"] = json!(description.clone());
+            body["text"] = json!(description);
+        }
+
+        self.event("stopped", body)
+    }
+
+    pub fn handle(&mut self, request: &Value) -> io::Result<()> {
+        let command = request["command"].as_str().unwrap_or("");
+        let args = &request["arguments"];
+
+        let reply = match command {
+            "initialize" => Ok(json!({
+                "supportsConfigurationDoneRequest": true,
+                "supportsInstructionBreakpoints": true,
+                "supportsSteppingGranularity": false
+            })),
+            "launch" => self.launch(args),
+            "setBreakpoints" => self.set_breakpoints(args),
+            "setInstructionBreakpoints" => self.set_instruction_breakpoints(args),
+            "configurationDone" | "continue" | "next" | "stepIn" | "stepOut" => self.with_session(|_| Ok(json!({}))),
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "intcode" }] })),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => self.scopes(args),
+            "variables" => self.variables(args),
+            "source" => self.with_session(|session| {
+                let content: Vec<String> = session.listing.iter()
+                    .map(|(addr, text)| format!("{:>6}  {}", addr, text))
+                    .collect();
+
+                Ok(json!({ "content": content.join("\n") }))
+            }),
+            "evaluate" => {
+                let expression = args["expression"].as_str().unwrap_or("").to_string();
+
+                self.with_session_mut(|session| {
+                    session.evaluate(&expression).map(|value| json!({ "result": value.to_string(), "variablesReference": 0 }))
+                })
+            },
+            "disconnect" => {
+                self.disconnected = true;
+                Ok(json!({}))
+            },
+            _ => Err(format!("unsupported request: {}", command))
+        };
+
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": command,
+            "success": reply.is_ok()
+        });
+
+        match &reply {
+            Ok(body) => response["body"] = body.clone(),
+            Err(message) => response["message"] = json!(message)
+        }
+
+        self.send(response)?;
+
+        if command == "initialize" {
+            self.event("initialized", json!({}))?;
+        }
+
+        if reply.is_err() {
+            return Ok(());
+        }
+
+        // execution requests are answered first, then report where they stopped
+        match command {
+            "configurationDone" => {
+                if self.session.as_ref().is_some_and(|session| session.stop_on_entry) {
+                    return self.stopped("entry", None);
+                }
+
+                self.resume(Resume::Continue)
+            },
+            "continue" => self.resume(Resume::Continue),
+            "stepIn" => self.resume(Resume::StepIn),
+            "next" => {
+                let depth = self.session.as_ref().map_or(0, |session| session.callers.len());
+                self.resume(Resume::StepOver(depth))
+            },
+            "stepOut" => {
+                let depth = self.session.as_ref().map_or(0, |session| session.callers.len());
+                self.resume(Resume::StepOut(depth))
+            },
+            _ => Ok(())
+        }
+    }
+
+    fn with_session<F: FnOnce(&Session) -> Reply>(&self, f: F) -> Reply {
+        self.session.as_ref().map_or(Err(String::from("no program launched")), f)
+    }
+
+    fn with_session_mut<F: FnOnce(&mut Session) -> Reply>(&mut self, f: F) -> Reply {
+        self.session.as_mut().map_or(Err(String::from("no program launched")), f)
+    }
+
+    fn launch(&mut self, args: &Value) -> Reply {
+        let (program, name) = launch_program(args)?;
+
+        let mut machine = Machine::new(program.clone());
+        machine.extend_input(&words(&args["inputs"])?);
+
+        self.session = Some(Session {
+            machine: machine,
+            name: name,
+            listing: disasm::disassemble(&program),
+            line_breakpoints: BTreeSet::new(),
+            address_breakpoints: BTreeSet::new(),
+            callers: Vec::new(),
+            stop_on_entry: args["stopOnEntry"].as_bool().unwrap_or(false),
+            finished: false
+        });
+
+        Ok(json!({}))
+    }
+
+    fn set_breakpoints(&mut self, args: &Value) -> Reply {
+        let lines: Vec<u64> = args["breakpoints"].as_array()
+            .map(|breakpoints| breakpoints.iter().filter_map(|bp| bp["line"].as_u64()).collect())
+            .unwrap_or_default();
+
+        self.with_session_mut(|session| {
+            let line_cnt = session.listing.len() as u64;
+
+            session.line_breakpoints = lines.iter()
+                .filter(|&&line| line >= 1 && line <= line_cnt)
+                .map(|&line| line as usize)
+                .collect();
+
+            let breakpoints: Vec<Value> = lines.iter()
+                .map(|&line| json!({ "verified": line >= 1 && line <= line_cnt, "line": line }))
+                .collect();
+
+            Ok(json!({ "breakpoints": breakpoints }))
+        })
+    }
+
+    fn set_instruction_breakpoints(&mut self, args: &Value) -> Reply {
+        let references: Vec<Option<usize>> = args["breakpoints"].as_array()
+            .map(|breakpoints| breakpoints.iter()
+                .map(|bp| {
+                    let offset = bp["offset"].as_i64().unwrap_or(0);
+
+                    bp["instructionReference"].as_str()
+                        .and_then(|reference| reference.trim().parse::<i64>().ok())
+                        .map(|addr| addr + offset)
+                        .filter(|&addr| addr >= 0)
+                        .map(|addr| addr as usize)
+                })
+                .collect())
+            .unwrap_or_default();
+
+        self.with_session_mut(|session| {
+            session.address_breakpoints = references.iter().filter_map(|&addr| addr).collect();
+
+            let breakpoints: Vec<Value> = references.iter()
+                .map(|addr| match addr {
+                    Some(addr) => json!({ "verified": true, "line": session.line_of(*addr), "instructionReference": addr.to_string() }),
+                    None => json!({ "verified": false, "message": "not an address" })
+                })
+                .collect();
+
+            Ok(json!({ "breakpoints": breakpoints }))
+        })
+    }
+
+    fn stack_trace(&self) -> Reply {
+        self.with_session(|session| {
+            let frames: Vec<Value> = session.frames().iter()
+                .enumerate()
+                .map(|(id, &(addr, base))| json!({
+                    "id": id,
+                    "name": format!("frame at rb={}", base),
+                    "source": session.source(),
+                    "line": session.line_of(addr),
+                    "column": 1,
+                    "instructionPointerReference": addr.to_string()
+                }))
+                .collect();
+
+            Ok(json!({ "stackFrames": frames, "totalFrames": frames.len() }))
+        })
+    }
+
+    fn scopes(&self, args: &Value) -> Reply {
+        let frame_id = args["frameId"].as_u64().unwrap_or(0);
+
+        self.with_session(|session| {
+            let memory_len = session.machine.memory().len();
+
+            Ok(json!({ "scopes": [
+                { "name": "Frame", "variablesReference": FRAME_SCOPES + frame_id, "expensive": false },
+                { "name": "Registers", "variablesReference": REGISTERS, "expensive": false },
+                { "name": "Memory", "variablesReference": MEMORY, "indexedVariables": memory_len, "expensive": true }
+            ]}))
+        })
+    }
+
+    fn variables(&self, args: &Value) -> Reply {
+        let reference = args["variablesReference"].as_u64().unwrap_or(0);
+        let start = args["start"].as_u64().unwrap_or(0) as usize;
+        let count = args["count"].as_u64().map(|count| count as usize);
+
+        self.with_session(|session| {
+            let machine = &session.machine;
+
+            let variables: Vec<Value> = match reference {
+                REGISTERS => vec!(
+                    variable(String::from("ip"), machine.instr_ptr() as Word),
+                    variable(String::from("rb"), machine.relative_base()),
+                    variable(String::from("steps"), machine.steps() as Word),
+                    variable(String::from("pending input"), machine.pending_input() as Word)
+                ),
+                MEMORY => {
+                    let memory = machine.memory();
+                    let end = count.map_or(memory.len(), |count| start.saturating_add(count).min(memory.len()));
+
+                    (start.min(end)..end).map(|addr| variable(format!("[{}]", addr), memory[addr])).collect()
+                },
+                frame if frame >= FRAME_SCOPES => {
+                    let frames = session.frames();
+                    let &(_, base) = frames.get((frame - FRAME_SCOPES) as usize).ok_or("no such frame")?;
+
+                    (0..FRAME_SLOTS as Word)
+                        .map(|slot| {
+                            let value = base.checked_add(slot).and_then(|addr| machine.read(addr).ok());
+                            variable(format!("rb+{}", slot), value.unwrap_or(0))
+                        })
+                        .collect()
+                },
+                _ => return Err(format!("no variables for reference {}", reference))
+            };
+
+            Ok(json!({ "variables": variables }))
+        })
+    }
+
+    fn resume(&mut self, how: Resume) -> io::Result<()> {
+        let mut first = true;
+
+        for _ in 0..STEP_BUDGET {
+            let session = match self.session.as_mut() {
+                Some(session) if !session.finished => session,
+                _ => return Ok(())
+            };
+
+            if !first && session.at_breakpoint() {
+                return self.stopped("breakpoint", None);
+            }
+            first = false;
+
+            match session.step() {
+                Err(message) => {
+                    session.finished = true;
+                    return self.stopped("exception", Some(message));
+                },
+                Ok(Some(Event::NeedInput)) => {
+                    return self.stopped("exception", Some(String::from("waiting for input; evaluate `input <value>` to supply it")));
+                },
+                Ok(Some(Event::Halted)) => {
+                    session.finished = true;
+                    self.event("exited", json!({ "exitCode": 0 }))?;
+                    return self.event("terminated", json!({}));
+                },
+                Ok(Some(Event::Output(value))) => {
+                    self.event("output", json!({ "category": "stdout", "output": format!("{}\n", value) }))?;
+                },
+                Ok(None) => ()
+            }
+
+            let depth = self.session.as_ref().map_or(0, |session| session.callers.len());
+
+            let done = match how {
+                Resume::Continue => false,
+                Resume::StepIn => true,
+                Resume::StepOver(start) => depth <= start,
+                Resume::StepOut(start) => depth < start
+            };
+
+            if done {
+                return self.stopped("step", None);
+            }
+        }
+
+        self.stopped("pause", Some(format!("paused after {} instructions", STEP_BUDGET)))
+    }
+}
+
+// serves one debug session, returning when the client disconnects or the
+// stream ends
+pub fn serve<R: BufRead, W: Write>(mut reader: R, writer: W) -> io::Result<W> {
+    let mut server = Server::new(writer);
+
+    while !server.is_disconnected() {
+        match read_message(&mut reader)? {
+            Some(request) => server.handle(&request)?,
+            None => break
+        }
+    }
+
+    Ok(server.into_inner())
+}
+
+pub fn serve_stdio() -> io::Result<()> {
+    let stdin = io::stdin();
+
+    serve(stdin.lock(), io::stdout()).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::compiler;
+    use std::io::Cursor;
+
+    // a scripted client: sends every request up front, then splits what came
+    // back into responses (by command) and events
+    struct Transcript {
+        messages: Vec<Value>
+    }
+
+    fn session(requests: &[Value]) -> Transcript {
+        let mut input = Vec::new();
+
+        for (i, request) in requests.iter().enumerate() {
+            let mut request = request.clone();
+            request["seq"] = json!(i + 1);
+            request["type"] = json!("request");
+
+            write_message(&mut input, &request).unwrap();
+        }
+
+        let output = serve(Cursor::new(input), Vec::new()).unwrap();
+        let mut reader = Cursor::new(output);
+        let mut messages = Vec::new();
+
+        while let Some(message) = read_message(&mut reader).unwrap() {
+            messages.push(message);
+        }
+
+        Transcript { messages: messages }
+    }
+
+    fn request(command: &str, arguments: Value) -> Value {
+        json!({ "command": command, "arguments": arguments })
+    }
+
+    impl Transcript {
+        fn responses(&self, command: &str) -> Vec<&Value> {
+            self.messages.iter()
+                .filter(|message| message["type"] == "response" && message["command"] == command)
+                .collect()
+        }
+
+        fn response(&self, command: &str) -> &Value {
+            self.responses(command)[0]
+        }
+
+        fn events(&self, event: &str) -> Vec<&Value> {
+            self.messages.iter()
+                .filter(|message| message["type"] == "event" && message["event"] == event)
+                .collect()
+        }
+
+        fn outputs(&self) -> Vec<String> {
+            self.events("output").iter().map(|event| event["body"]["output"].as_str().unwrap().to_string()).collect()
+        }
+    }
+
+    // outputs 1 if the input is 8, 0 otherwise
+    const EQUALS_EIGHT: &str = "3,9,8,9,10,9,4,9,99,-1,8";
+
+    fn start(code: &str, inputs: Value, stop_on_entry: bool) -> Vec<Value> {
+        vec!(
+            request("initialize", json!({ "adapterID": "intcode" })),
+            request("launch", json!({ "code": code, "inputs": inputs, "stopOnEntry": stop_on_entry }))
+        )
+    }
+
+    #[test]
+    fn runs_to_completion() {
+        let mut requests = start(EQUALS_EIGHT, json!([8]), false);
+        requests.push(request("configurationDone", json!({})));
+        requests.push(request("disconnect", json!({})));
+
+        let transcript = session(&requests);
+
+        assert_eq!(transcript.response("initialize")["body"]["supportsInstructionBreakpoints"], true);
+        assert_eq!(transcript.events("initialized").len(), 1);
+        assert_eq!(transcript.outputs(), vec!("1\n"));
+        assert_eq!(transcript.events("exited")[0]["body"]["exitCode"], 0);
+        assert_eq!(transcript.events("terminated").len(), 1);
+
+        // sequence numbers are unique and increasing
+        let seqs: Vec<u64> = transcript.messages.iter().map(|message| message["seq"].as_u64().unwrap()).collect();
+        assert!(seqs.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn line_breakpoints_and_stepping() {
+        let mut requests = start(EQUALS_EIGHT, json!([5]), false);
+        requests.push(request("setBreakpoints", json!({
+            "source": { "sourceReference": 1 },
+            "breakpoints": [{ "line": 3 }, { "line": 40 }]
+        })));
+        requests.push(request("configurationDone", json!({})));
+        requests.push(request("stackTrace", json!({ "threadId": 1 })));
+        requests.push(request("stepIn", json!({ "threadId": 1 })));
+        requests.push(request("stackTrace", json!({ "threadId": 1 })));
+        requests.push(request("continue", json!({ "threadId": 1 })));
+        requests.push(request("source", json!({ "sourceReference": 1 })));
+
+        let transcript = session(&requests);
+
+        let verified: Vec<&Value> = transcript.response("setBreakpoints")["body"]["breakpoints"].as_array().unwrap().iter()
+            .map(|bp| &bp["verified"])
+            .collect();
+        assert_eq!(verified, vec!(&json!(true), &json!(false)));
+
+        let stops: Vec<&Value> = transcript.events("stopped").iter().map(|event| &event["body"]["reason"]).collect();
+        assert_eq!(stops, vec!(&json!("breakpoint"), &json!("step")));
+
+        // line 3 is the output at address 6, one step later comes the halt
+        let traces = transcript.responses("stackTrace");
+        assert_eq!(traces[0]["body"]["stackFrames"][0]["line"], 3);
+        assert_eq!(traces[0]["body"]["stackFrames"][0]["instructionPointerReference"], "6");
+        assert_eq!(traces[1]["body"]["stackFrames"][0]["line"], 4);
+
+        assert_eq!(transcript.outputs(), vec!("0\n"));
+        assert!(transcript.response("source")["body"]["content"].as_str().unwrap().contains("eq [9], [10], [9]"));
+    }
+
+    #[test]
+    fn instruction_breakpoints_and_input() {
+        let mut requests = start(EQUALS_EIGHT, json!([]), false);
+        requests.push(request("setInstructionBreakpoints", json!({ "breakpoints": [{ "instructionReference": "2" }] })));
+        requests.push(request("configurationDone", json!({})));
+        requests.push(request("evaluate", json!({ "expression": "input 8", "context": "repl" })));
+        requests.push(request("continue", json!({ "threadId": 1 })));
+        requests.push(request("evaluate", json!({ "expression": "[9] + ip", "context": "repl" })));
+        requests.push(request("continue", json!({ "threadId": 1 })));
+
+        let transcript = session(&requests);
+
+        let stops = transcript.events("stopped");
+        assert_eq!(stops[0]["body"]["reason"], "exception");
+        assert!(stops[0]["body"]["description"].as_str().unwrap().contains("input"));
+        assert_eq!(stops[1]["body"]["reason"], "breakpoint");
+
+        // [9] holds the input, 8, and the machine is at address 2
+        assert_eq!(transcript.responses("evaluate")[1]["body"]["result"], "10");
+        assert_eq!(transcript.outputs(), vec!("1\n"));
+    }
+
+    #[test]
+    fn memory_and_registers() {
+        let mut requests = start(EQUALS_EIGHT, json!([]), true);
+        requests.push(request("configurationDone", json!({})));
+        requests.push(request("scopes", json!({ "frameId": 0 })));
+        requests.push(request("variables", json!({ "variablesReference": MEMORY, "start": 9, "count": 5 })));
+        requests.push(request("variables", json!({ "variablesReference": MEMORY, "start": 9, "count": u64::MAX })));
+        requests.push(request("variables", json!({ "variablesReference": REGISTERS })));
+        requests.push(request("evaluate", json!({ "expression": "[rb + 1" })));
+
+        let transcript = session(&requests);
+
+        assert_eq!(transcript.events("stopped")[0]["body"]["reason"], "entry");
+        assert_eq!(transcript.response("scopes")["body"]["scopes"][2]["indexedVariables"], 11);
+
+        let memory = &transcript.responses("variables")[0]["body"]["variables"];
+        assert_eq!(memory.as_array().unwrap().len(), 2);
+        assert_eq!(memory[0]["name"], "[9]");
+        assert_eq!(memory[0]["value"], "-1");
+
+        let rest = &transcript.responses("variables")[1]["body"]["variables"];
+        assert_eq!(rest, memory);
+
+        let registers = &transcript.responses("variables")[2]["body"]["variables"];
+        assert_eq!(registers[0]["name"], "ip");
+        assert_eq!(registers[0]["value"], "0");
+
+        let failed = transcript.response("evaluate");
+        assert_eq!(failed["success"], false);
+        assert!(failed["message"].as_str().unwrap().contains("expected `]`"));
+    }
+
+    #[test]
+    fn overflowing_expressions() {
+        let mut requests = start(EQUALS_EIGHT, json!([]), true);
+        requests.push(request("configurationDone", json!({})));
+        requests.push(request("evaluate", json!({ "expression": "-(-9223372036854775807 - 1)" })));
+        requests.push(request("evaluate", json!({ "expression": "9223372036854775807 + 1" })));
+        requests.push(request("evaluate", json!({ "expression": "-9223372036854775807 - 1" })));
+
+        let transcript = session(&requests);
+        let evaluated = transcript.responses("evaluate");
+
+        assert_eq!(evaluated[0]["success"], false);
+        assert_eq!(evaluated[0]["message"], "overflow at column 1");
+        assert_eq!(evaluated[1]["success"], false);
+        assert_eq!(evaluated[1]["message"], "overflow at column 21");
+        assert_eq!(evaluated[2]["body"]["result"], i64::MIN.to_string());
+    }
+
+    #[test]
+    fn call_stack_and_step_over() {
+        let program = compiler::compile("
+            fn inner(n) { return n + 1; }
+            fn outer(n) { return inner(n) * 2; }
+            fn main() { output(outer(input())); }
+        ").unwrap();
+
+        let code: Vec<String> = program.iter().map(|word| word.to_string()).collect();
+        let listing = disasm::disassemble(&program);
+
+        // the first instruction of inner: the line after the entry stub's
+        // halt is where the first function begins
+        let halt_line = listing.iter().position(|(_, text)| text == "hlt").unwrap() + 1;
+
+        let mut requests = start(&code.join(","), json!([20]), false);
+        requests.push(request("setBreakpoints", json!({
+            "source": { "sourceReference": 1 },
+            "breakpoints": [{ "line": halt_line + 1 }]
+        })));
+        requests.push(request("configurationDone", json!({})));
+        requests.push(request("stackTrace", json!({ "threadId": 1 })));
+        requests.push(request("scopes", json!({ "frameId": 0 })));
+        requests.push(request("variables", json!({ "variablesReference": FRAME_SCOPES })));
+        requests.push(request("stepOut", json!({ "threadId": 1 })));
+        requests.push(request("stackTrace", json!({ "threadId": 1 })));
+        requests.push(request("next", json!({ "threadId": 1 })));
+        requests.push(request("stackTrace", json!({ "threadId": 1 })));
+        requests.push(request("continue", json!({ "threadId": 1 })));
+
+        let transcript = session(&requests);
+        let traces = transcript.responses("stackTrace");
+
+        // main, outer and inner, plus the entry stub's frame
+        let frames = traces[0]["body"]["stackFrames"].as_array().unwrap();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0]["line"], halt_line + 1);
+
+        // inner's parameter sits in slot 1 of its frame
+        let slots = &transcript.response("variables")["body"]["variables"];
+        assert_eq!(slots[1]["name"], "rb+1");
+        assert_eq!(slots[1]["value"], "20");
+
+        // the frame is gone once outer drops it with the `arb` at the return
+        // address inner was given, so stepping out stops just past that
+        let return_addr: usize = frames[1]["instructionPointerReference"].as_str().unwrap().parse().unwrap();
+        let after_out = traces[1]["body"]["stackFrames"].as_array().unwrap();
+
+        assert_eq!(after_out.len(), 3);
+        assert_eq!(after_out[0]["instructionPointerReference"], (return_addr + 2).to_string());
+
+        // stepping over stays in outer
+        assert_eq!(traces[2]["body"]["stackFrames"].as_array().unwrap().len(), 3);
+
+        assert_eq!(transcript.outputs(), vec!("42\n"));
+    }
+
+    #[test]
+    fn reports_faults() {
+        let mut requests = start("1,0,0,0,77", json!([]), false);
+        requests.push(request("configurationDone", json!({})));
+        requests.push(request("continue", json!({ "threadId": 1 })));
+        requests.push(request("bogus", json!({})));
+
+        let transcript = session(&requests);
+
+        let stops = transcript.events("stopped");
+        assert_eq!(stops.len(), 1);
+        assert_eq!(stops[0]["body"]["reason"], "exception");
+        assert_eq!(transcript.response("bogus")["success"], false);
+    }
+}
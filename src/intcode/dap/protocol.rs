@@ -0,0 +1,72 @@
+use std::io::{ self, BufRead, Write };
+
+use serde_json::Value;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// reads one `Content-Length` framed message, None at a clean end of stream
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Value>> {
+    let mut length = None;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+
+        if reader.read_line(&mut line)? == 0 {
+            return match length {
+                None => Ok(None),
+                Some(_) => Err(invalid("stream ended inside a header"))
+            };
+        }
+
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = Some(value.trim().parse::<usize>().map_err(|_| invalid("bad Content-Length"))?);
+        }
+    }
+
+    let mut body = vec!(0; length.ok_or_else(|| invalid("missing Content-Length"))?);
+    reader.read_exact(&mut body)?;
+
+    serde_json::from_slice(&body).map(Some).map_err(|err| invalid(&err.to_string()))
+}
+
+pub fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Cursor;
+
+    #[test]
+    fn round_trip() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &json!({ "seq": 1, "command": "threads" })).unwrap();
+        write_message(&mut buffer, &json!({ "seq": 2 })).unwrap();
+
+        let mut reader = Cursor::new(buffer);
+
+        assert_eq!(read_message(&mut reader).unwrap(), Some(json!({ "seq": 1, "command": "threads" })));
+        assert_eq!(read_message(&mut reader).unwrap(), Some(json!({ "seq": 2 })));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_missing_length() {
+        let mut reader = Cursor::new(b"Content-Type: json\r\n\r\n{}".to_vec());
+
+        assert!(read_message(&mut reader).is_err());
+    }
+}
@@ -0,0 +1,524 @@
+use std::collections::{ BTreeSet, HashMap };
+use std::fmt;
+use std::vec::Vec;
+
+use super::Word;
+use super::cfg::{ self, Block, Cfg, Exit };
+use super::disasm::Decoded;
+use super::instruction::{ Mode, Opcode };
+
+#[derive(Clone, PartialEq, Debug)]
+enum Expr {
+    Const(Word),
+    Cell(usize),
+    Rel(Word),
+    // a position-mode access to a negative address, which faults at runtime
+    Invalid(Word),
+    Bin(&'static str, Box<Expr>, Box<Expr>),
+    Not(Box<Expr>)
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct Options {
+    // cells patched from outside before the run, e.g. day 2's noun and verb
+    pub names: HashMap<usize, String>
+}
+
+enum Line {
+    Text(usize, String),
+    Label(usize)
+}
+
+struct Decompiler<'a> {
+    program: &'a [Word],
+    cfg: Cfg,
+    options: &'a Options,
+    // cells that keep their initial value for the whole run
+    constant: Box<dyn Fn(usize) -> bool + 'a>,
+    lines: Vec<Line>,
+    gotos: BTreeSet<usize>
+}
+
+// pending writes within a basic block, kept symbolic so chains fold
+struct Env {
+    pending: Vec<(usize, Expr)>
+}
+
+impl Expr {
+    fn reads(&self, cell: usize) -> bool {
+        match self {
+            Expr::Cell(c) => *c == cell,
+            Expr::Bin(_, l, r) => l.reads(cell) || r.reads(cell),
+            Expr::Not(e) => e.reads(cell),
+            _ => false
+        }
+    }
+
+    fn binary(op: &'static str, left: Expr, right: Expr) -> Expr {
+        match (op, &left, &right) {
+            ("+", Expr::Const(a), Expr::Const(b)) => a.checked_add(*b).map(Expr::Const),
+            ("*", Expr::Const(a), Expr::Const(b)) => a.checked_mul(*b).map(Expr::Const),
+            ("<", Expr::Const(a), Expr::Const(b)) => Some(Expr::Const((a < b) as Word)),
+            ("==", Expr::Const(a), Expr::Const(b)) => Some(Expr::Const((a == b) as Word)),
+            ("+", _, Expr::Const(0)) | ("*", _, Expr::Const(1)) => Some(left.clone()),
+            ("+", Expr::Const(0), _) | ("*", Expr::Const(1), _) => Some(right.clone()),
+            _ => None
+        }.unwrap_or(Expr::Bin(op, Box::new(left), Box::new(right)))
+    }
+
+    fn negate(self) -> Expr {
+        match self {
+            Expr::Bin("==", l, r) => Expr::Bin("!=", l, r),
+            Expr::Bin("!=", l, r) => Expr::Bin("==", l, r),
+            Expr::Bin("<", l, r) => Expr::Bin(">=", l, r),
+            Expr::Bin(">=", l, r) => Expr::Bin("<", l, r),
+            Expr::Not(e) => *e,
+            Expr::Const(n) => Expr::Const((n == 0) as Word),
+            e => Expr::Not(Box::new(e))
+        }
+    }
+}
+
+struct Named<'a>(&'a Expr, &'a Options, bool);
+
+impl<'a> fmt::Display for Named<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Named(expr, options, nested) = *self;
+
+        match expr {
+            Expr::Const(n) => write!(f, "{}", n),
+            Expr::Cell(c) => match options.names.get(c) {
+                Some(name) => write!(f, "{}", name),
+                None => write!(f, "v{}", c)
+            },
+            Expr::Rel(offset) if *offset < 0 => write!(f, "rel[{}]", offset),
+            Expr::Rel(offset) => write!(f, "rel[+{}]", offset),
+            Expr::Invalid(addr) => write!(f, "invalid[{}]", addr),
+            Expr::Not(e) => write!(f, "!{}", Named(e, options, true)),
+            Expr::Bin(op, l, r) => {
+                let inner = format!("{} {} {}", Named(l, options, true), op, Named(r, options, true));
+                if nested { write!(f, "({})", inner) } else { write!(f, "{}", inner) }
+            }
+        }
+    }
+}
+
+impl Env {
+    fn lookup(&self, cell: usize) -> Option<&Expr> {
+        self.pending.iter().rev().find(|(c, _)| *c == cell).map(|(_, e)| e)
+    }
+}
+
+pub fn decompile(program: &[Word], options: &Options) -> String {
+    let cfg = cfg::build(program);
+
+    // cells written anywhere reachable; a relative write could hit anything
+    let mut written = BTreeSet::new();
+    let mut relative_writes = false;
+
+    for decoded in cfg.blocks.values().flat_map(|block| block.instrs.iter()) {
+        if let Some(dest) = decoded.instr.opcode.dest_param() {
+            match decoded.instr.modes[dest] {
+                Mode::Position => { written.insert(decoded.params[dest]); },
+                _ => relative_writes = true
+            }
+        }
+    }
+
+    let names = options.names.clone();
+    let constant = move |cell: usize| {
+        !relative_writes && !written.contains(&(cell as Word)) && !names.contains_key(&cell)
+    };
+
+    let mut decompiler = Decompiler {
+        program: program,
+        cfg: cfg,
+        options: options,
+        constant: Box::new(constant),
+        lines: Vec::new(),
+        gotos: BTreeSet::new()
+    };
+
+    // a block that doesn't decode at all is empty, but still takes its place
+    let end = decompiler.cfg.blocks.values().map(|block| block.end.max(block.start + 1)).max().unwrap_or(0);
+    decompiler.region(0, end, 0);
+
+    let mut text = String::new();
+    for line in decompiler.lines.iter() {
+        match line {
+            Line::Text(indent, content) => {
+                text.push_str(&"    ".repeat(*indent));
+                text.push_str(content);
+                text.push('\n');
+            },
+            Line::Label(addr) if decompiler.gotos.contains(addr) => {
+                text.push_str(&format!("L{}:\n", addr));
+            },
+            Line::Label(_) => ()
+        }
+    }
+
+    text
+}
+
+impl<'a> Decompiler<'a> {
+    fn emit(&mut self, indent: usize, text: String) {
+        self.lines.push(Line::Text(indent, text));
+    }
+
+    fn show(&self, expr: &Expr) -> String {
+        Named(expr, self.options, false).to_string()
+    }
+
+    fn goto(&mut self, indent: usize, target: usize) {
+        self.gotos.insert(target);
+        self.emit(indent, format!("goto L{}", target));
+    }
+
+    fn load(&self, env: &Env, decoded: &Decoded, param_i: usize) -> Expr {
+        let value = decoded.params[param_i];
+
+        match decoded.instr.modes[param_i] {
+            Mode::Immediate => Expr::Const(value),
+            Mode::Relative => Expr::Rel(value),
+            Mode::Position if value < 0 => Expr::Invalid(value),
+            Mode::Position => {
+                let cell = value as usize;
+
+                if let Some(expr) = env.lookup(cell) {
+                    return expr.clone();
+                }
+
+                if (self.constant)(cell) {
+                    return Expr::Const(self.program.get(cell).cloned().unwrap_or(0));
+                }
+
+                Expr::Cell(cell)
+            }
+        }
+    }
+
+    // writes out pending assignments still read by others before cell changes
+    fn materialize(&mut self, env: &mut Env, cell: usize, indent: usize) {
+        let dependants: Vec<usize> = env.pending.iter()
+            .filter(|(c, e)| *c != cell && e.reads(cell))
+            .map(|(c, _)| *c)
+            .collect();
+
+        for dependant in dependants {
+            let index = env.pending.iter().position(|(c, _)| *c == dependant).unwrap();
+            let (c, expr) = env.pending.remove(index);
+
+            self.emit(indent, format!("{} {}", self.cell_name(c), self.show(&expr)));
+        }
+
+        // the old value of cell was never read, so its assignment is dead
+        env.pending.retain(|(c, _)| *c != cell);
+    }
+
+    fn cell_name(&self, cell: usize) -> String {
+        format!("{} =", self.show(&Expr::Cell(cell)))
+    }
+
+    fn flush(&mut self, env: &mut Env, indent: usize) {
+        for (cell, expr) in env.pending.drain(..).collect::<Vec<_>>() {
+            self.emit(indent, format!("{} {}", self.cell_name(cell), self.show(&expr)));
+        }
+    }
+
+    fn store(&mut self, env: &mut Env, decoded: &Decoded, param_i: usize, expr: Expr, indent: usize) {
+        let value = decoded.params[param_i];
+
+        match decoded.instr.modes[param_i] {
+            Mode::Position if value >= 0 => {
+                let cell = value as usize;
+                self.materialize(env, cell, indent);
+                env.pending.push((cell, expr));
+            },
+            mode => {
+                self.flush(env, indent);
+                let target = if mode == Mode::Relative { Expr::Rel(value) } else { Expr::Invalid(value) };
+                let target = self.show(&target);
+                self.emit(indent, format!("{} = {}", target, self.show(&expr)));
+            }
+        }
+    }
+
+    // straight-line statements of a block; returns the branch condition
+    fn statements(&mut self, block: &Block, indent: usize) -> Option<Expr> {
+        let mut env = Env { pending: Vec::new() };
+
+        for decoded in block.instrs.iter() {
+            let touches_relative = decoded.instr.modes.iter().take(decoded.params.len())
+                .any(|&mode| mode == Mode::Relative);
+
+            if touches_relative {
+                self.flush(&mut env, indent);
+            }
+
+            match decoded.instr.opcode {
+                Opcode::Add | Opcode::Mul | Opcode::LessThan | Opcode::Equals => {
+                    let op = match decoded.instr.opcode {
+                        Opcode::Add => "+",
+                        Opcode::Mul => "*",
+                        Opcode::LessThan => "<",
+                        _ => "=="
+                    };
+
+                    let expr = Expr::binary(op, self.load(&env, decoded, 0), self.load(&env, decoded, 1));
+                    self.store(&mut env, decoded, 2, expr, indent);
+                },
+                Opcode::Input => {
+                    self.flush(&mut env, indent);
+                    let target = self.load(&Env { pending: Vec::new() }, decoded, 0);
+                    let target = match target {
+                        Expr::Const(_) => Expr::Cell(decoded.params[0] as usize),
+                        other => other
+                    };
+                    self.emit(indent, format!("{} = input()", self.show(&target)));
+                },
+                Opcode::Output => {
+                    let value = self.load(&env, decoded, 0);
+                    self.emit(indent, format!("output({})", self.show(&value)));
+                },
+                Opcode::AdjustBase => {
+                    let value = self.load(&env, decoded, 0);
+                    self.emit(indent, format!("rb += {}", self.show(&value)));
+                },
+                Opcode::JumpIfTrue | Opcode::JumpIfFalse | Opcode::Halt => ()
+            }
+        }
+
+        let cond = block.terminator().map(|jump| {
+            let cond = self.load(&env, jump, 0);
+            // only keep the folded condition if flushing can't change what it reads
+            if env.pending.iter().any(|(cell, _)| cond.reads(*cell)) {
+                self.load(&Env { pending: Vec::new() }, jump, 0)
+            } else {
+                cond
+            }
+        });
+
+        self.flush(&mut env, indent);
+
+        cond
+    }
+
+    // emits the blocks in [start, end), recovering loops and ifs from the
+    // shape of the jumps between them
+    fn region(&mut self, start: usize, end: usize, indent: usize) {
+        let mut addr = start;
+
+        while addr < end {
+            let block = match self.cfg.blocks.get(&addr) {
+                Some(block) => block.clone(),
+                None => match self.cfg.blocks.range(addr..end).next() {
+                    Some((&next, _)) => { addr = next; continue; },
+                    None => break
+                }
+            };
+
+            self.lines.push(Line::Label(block.start));
+            let cond = self.statements(&block, indent);
+
+            match block.exit {
+                Exit::Fall(next) => addr = next,
+                Exit::Halt => {
+                    self.emit(indent, String::from("halt"));
+                    addr = block.end;
+                },
+                Exit::Invalid => {
+                    self.emit(indent, format!("invalid instruction at {}", block.end));
+                    addr = block.end.max(block.start + 1);
+                },
+                Exit::Dynamic => {
+                    let jump = block.terminator().unwrap();
+                    let target = self.load(&Env { pending: Vec::new() }, jump, 1);
+                    self.emit(indent, format!("jump {}", self.show(&target)));
+                    addr = block.end;
+                },
+                Exit::Jump(target) => {
+                    if target != block.end {
+                        self.goto(indent, target);
+                    }
+                    addr = block.end;
+                },
+                Exit::Branch { target, next, jump_if_true } => {
+                    let cond = cond.unwrap();
+                    // the fall-through side runs when the jump isn't taken
+                    let stay = if jump_if_true { cond.clone().negate() } else { cond.clone() };
+
+                    addr = self.branch(&block, stay, target, next, end, indent);
+                }
+            }
+        }
+    }
+
+    fn branch(&mut self, block: &Block, stay: Expr, target: usize, next: usize, end: usize, indent: usize) -> usize {
+        if target <= block.start || target > end {
+            let jump = stay.negate();
+            self.gotos.insert(target);
+            self.emit(indent, format!("if {} {{ goto L{} }}", self.show(&jump), target));
+            return next;
+        }
+
+        let last = self.cfg.block_ending_at(target).cloned();
+
+        match last.as_ref().map(|last| (last.start, &last.exit)) {
+            // header falls into the body, which jumps back to the header
+            Some((last_start, Exit::Jump(back))) if *back == block.start && last_start >= next => {
+                let has_body = block.instrs.len() > 1;
+
+                if has_body {
+                    // the condition needs the header's statements run first
+                    self.lines.truncate(self.header_line(block.start));
+                    self.lines.push(Line::Label(block.start));
+                    self.emit(indent, String::from("loop {"));
+                    self.statements(block, indent + 1);
+                    self.emit(indent + 1, format!("if {} {{ break }}", self.show(&stay.negate())));
+                } else {
+                    self.emit(indent, format!("while {} {{", self.show(&stay)));
+                }
+
+                self.region(next, last_start, indent + 1);
+                self.statements(last.as_ref().unwrap(), indent + 1);
+                self.emit(indent, String::from("}"));
+            },
+            // then-part ends by jumping past an else-part
+            Some((last_start, Exit::Jump(after))) if *after > target && *after <= end && last_start >= next => {
+                self.emit(indent, format!("if {} {{", self.show(&stay)));
+                self.region(next, last_start, indent + 1);
+                self.statements(last.as_ref().unwrap(), indent + 1);
+                self.emit(indent, String::from("} else {"));
+                self.region(target, *after, indent + 1);
+                self.emit(indent, String::from("}"));
+
+                return *after;
+            },
+            _ => {
+                self.emit(indent, format!("if {} {{", self.show(&stay)));
+                self.region(next, target, indent + 1);
+                self.emit(indent, String::from("}"));
+            }
+        }
+
+        target
+    }
+
+    fn header_line(&self, start: usize) -> usize {
+        self.lines.iter()
+            .rposition(|line| match line { Line::Label(addr) => *addr == start, _ => false })
+            .unwrap_or(self.lines.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_day2_chain() {
+        let program = [1, 1, 2, 9, 2, 9, 10, 0, 99, 0, 3];
+
+        let mut options = Options::default();
+        options.names.insert(1, String::from("noun"));
+        options.names.insert(2, String::from("verb"));
+
+        // v9 is kept since nothing says it isn't read after the run
+        let expected = "\
+v9 = noun + verb
+v0 = (noun + verb) * 3
+halt
+";
+        assert_eq!(decompile(&program, &options), expected);
+    }
+
+    #[test]
+    fn constant_program() {
+        let program = [1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+
+        let expected = "\
+v3 = 70
+v0 = 3500
+halt
+";
+        assert_eq!(decompile(&program, &Options::default()), expected);
+    }
+
+    #[test]
+    fn while_loop() {
+        let program = [3, 15, 1006, 15, 14, 4, 15, 1001, 15, -1, 15, 1105, 1, 2, 99, 0];
+
+        let expected = "\
+v15 = input()
+while v15 {
+    output(v15)
+    v15 = v15 + -1
+}
+halt
+";
+        assert_eq!(decompile(&program, &Options::default()), expected);
+    }
+
+    #[test]
+    fn loop_with_header_statements() {
+        // counts v20 up from 0 while it's below the input
+        let program = [3, 19, 7, 20, 19, 21, 1006, 21, 18, 4, 20, 1001, 20, 1, 20, 1105, 1, 2, 99, 0, 0, 0];
+
+        let expected = "\
+v19 = input()
+loop {
+    v21 = v20 < v19
+    if v20 >= v19 { break }
+    output(v20)
+    v20 = v20 + 1
+}
+halt
+";
+        assert_eq!(decompile(&program, &Options::default()), expected);
+    }
+
+    #[test]
+    fn if_else() {
+        let program = [3, 17, 1008, 17, 5, 18, 1005, 18, 14, 104, 0, 1105, 1, 16, 104, 1, 99, 0, 0];
+
+        let expected = "\
+v17 = input()
+v18 = v17 == 5
+if v17 != 5 {
+    output(0)
+} else {
+    output(1)
+}
+halt
+";
+        assert_eq!(decompile(&program, &Options::default()), expected);
+    }
+
+    #[test]
+    fn backward_branch_becomes_goto() {
+        // do { output(v9); v9 = v9 + -1 } while v9
+        let program = [4, 9, 1001, 9, -1, 9, 1005, 9, 0, 3];
+
+        let expected = "\
+L0:
+output(v9)
+v9 = v9 + -1
+if v9 { goto L0 }
+invalid instruction at 9
+";
+        assert_eq!(decompile(&program, &Options::default()), expected);
+    }
+
+    #[test]
+    fn negative_addresses() {
+        let program = [4, -3, 1101, 1, 2, -1, 99];
+
+        let expected = "\
+output(invalid[-3])
+invalid[-1] = 3
+halt
+";
+        assert_eq!(decompile(&program, &Options::default()), expected);
+    }
+}
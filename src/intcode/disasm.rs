@@ -0,0 +1,103 @@
+use std::vec::Vec;
+
+use super::Word;
+use super::instruction::{ Instruction, Mode };
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Decoded {
+    pub address: usize,
+    pub instr: Instruction,
+    pub params: Vec<Word>
+}
+
+impl Decoded {
+    pub fn next(&self) -> usize {
+        self.address + self.instr.len()
+    }
+}
+
+// decodes the instruction starting at addr, if there is a whole one there
+pub fn decode_at(program: &[Word], addr: usize) -> Option<Decoded> {
+    let instr = Instruction::decode(*program.get(addr)?).ok()?;
+
+    if addr + instr.len() > program.len() {
+        return None;
+    }
+
+    Some(Decoded {
+        address: addr,
+        instr: instr,
+        params: program[addr + 1..addr + instr.len()].to_vec()
+    })
+}
+
+pub fn format_param(mode: Mode, value: Word) -> String {
+    match mode {
+        Mode::Position => format!("[{}]", value),
+        Mode::Immediate => format!("{}", value),
+        Mode::Relative if value < 0 => format!("[rb-{}]", -value),
+        Mode::Relative => format!("[rb+{}]", value)
+    }
+}
+
+pub fn format(decoded: &Decoded) -> String {
+    let params: Vec<String> = decoded.params.iter()
+        .zip(decoded.instr.modes.iter())
+        .map(|(&value, &mode)| format_param(mode, value))
+        .collect();
+
+    if params.is_empty() {
+        return String::from(decoded.instr.opcode.mnemonic());
+    }
+
+    format!("{} {}", decoded.instr.opcode.mnemonic(), params.join(", "))
+}
+
+// linear sweep from address 0; words that don't decode are listed as data
+pub fn disassemble(program: &[Word]) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut addr = 0;
+
+    while addr < program.len() {
+        match decode_at(program, addr) {
+            Some(decoded) => {
+                lines.push((addr, format(&decoded)));
+                addr = decoded.next();
+            },
+            None => {
+                lines.push((addr, format!("data {}", program[addr])));
+                addr += 1;
+            }
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_instructions() {
+        let program = [1002, 4, 3, 4, 33, 204, -1, 21101, 2, 3, 7];
+
+        assert_eq!(format(&decode_at(&program, 0).unwrap()), "mul [4], 3, [4]");
+        assert_eq!(format(&decode_at(&program, 5).unwrap()), "out [rb-1]");
+        assert_eq!(format(&decode_at(&program, 7).unwrap()), "add 2, 3, [rb+7]");
+    }
+
+    #[test]
+    fn sweep() {
+        let lines = disassemble(&[1101, 1, 2, 5, 99, 42, 1]);
+
+        let expected = vec!(
+            (0, String::from("add 1, 2, [5]")),
+            (4, String::from("hlt")),
+            (5, String::from("data 42")),
+            (6, String::from("data 1"))
+        );
+
+        assert_eq!(lines, expected);
+    }
+}
@@ -0,0 +1,458 @@
+use std::collections::VecDeque;
+use std::ptr;
+use std::vec::Vec;
+
+use super::Word;
+use super::async_machine::{ self, RunError };
+use super::ffi;
+use super::local_pool::LocalPool;
+use super::machine::{ ErrorKind, Event, Machine };
+use super::optimizer;
+use super::session;
+use crate::day2;
+use crate::day5;
+use crate::day7;
+
+// instruction sets in the order the puzzles introduced them, each a superset
+// of the one before
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Dialect {
+    Day2,
+    Day5,
+    Day9
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Termination {
+    Halted,
+    Error(ErrorKind),
+    InputExhausted,
+    StepLimit
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Outcome {
+    pub outputs: Vec<Word>,
+    pub memory: Vec<Word>,
+    pub termination: Termination
+}
+
+pub trait Engine {
+    fn name(&self) -> &'static str;
+
+    // largest instruction set the engine implements
+    fn dialect(&self) -> Dialect;
+
+    // engines that can't report errors or honour step limits panic or spin on
+    // bad programs, so they only ever get valid, terminating ones
+    fn checked(&self) -> bool;
+
+    // engines that rewrite the program before running it end up with other
+    // words in memory and may take fewer steps, so see agrees_after_rewrite
+    fn rewrites(&self) -> bool {
+        false
+    }
+
+    fn execute(&self, program: &[Word], inputs: &[Word], step_limit: usize) -> Outcome;
+}
+
+pub struct Reference;
+
+// the reference machine driven through session::record, which feeds input
+// one value at a time as the machine asks for it
+pub struct Traced;
+
+// the reference machine run as a task, fed from a queue it awaits on
+pub struct Async;
+
+// the reference machine behind the C interface, called as a C harness would
+pub struct Ffi;
+
+// the reference machine running what the optimizer makes of the program
+pub struct Optimized;
+
+pub struct LegacyDay2;
+
+pub struct LegacyDay5;
+
+pub struct LegacyDay7;
+
+impl Outcome {
+    // memory with trailing zeros dropped, since engines differ in how much
+    // untouched memory they keep around
+    pub fn trimmed_memory(&self) -> &[Word] {
+        let len = self.memory.iter().rposition(|&w| w != 0).map_or(0, |i| i + 1);
+
+        &self.memory[..len]
+    }
+
+    pub fn agrees_with(&self, other: &Outcome) -> bool {
+        self.outputs == other.outputs &&
+            self.termination == other.termination &&
+            self.trimmed_memory() == other.trimmed_memory()
+    }
+
+    // memory is left out, and a run of the rewritten program gets further
+    // in the same number of steps, so it only has to carry on from where an
+    // `expected` that hit the step limit stopped
+    pub fn agrees_after_rewrite(&self, expected: &Outcome) -> bool {
+        if expected.termination == Termination::StepLimit {
+            self.outputs.starts_with(&expected.outputs)
+        } else {
+            self.outputs == expected.outputs && self.termination == expected.termination
+        }
+    }
+}
+
+pub fn all() -> Vec<Box<dyn Engine>> {
+    vec!(
+        Box::new(Reference),
+        Box::new(Traced),
+        Box::new(Async),
+        Box::new(Ffi),
+        Box::new(Optimized),
+        Box::new(LegacyDay2),
+        Box::new(LegacyDay5),
+        Box::new(LegacyDay7)
+    )
+}
+
+pub fn run_machine(machine: &mut Machine, step_limit: usize) -> (Vec<Word>, Termination) {
+    let mut outputs = Vec::new();
+
+    loop {
+        if machine.steps() >= step_limit {
+            return (outputs, Termination::StepLimit);
+        }
+
+        match machine.step() {
+            Ok(None) => continue,
+            Ok(Some(Event::Output(value))) => outputs.push(value),
+            Ok(Some(Event::NeedInput)) => return (outputs, Termination::InputExhausted),
+            Ok(Some(Event::Halted)) => return (outputs, Termination::Halted),
+            Err(err) => return (outputs, Termination::Error(err.kind))
+        }
+    }
+}
+
+fn to_legacy(program: &[Word]) -> Vec<i32> {
+    program.iter().map(|&w| w as i32).collect()
+}
+
+fn from_legacy(memory: &[i32]) -> Vec<Word> {
+    memory.iter().map(|&w| w as Word).collect()
+}
+
+impl Engine for Reference {
+    fn name(&self) -> &'static str {
+        "reference"
+    }
+
+    fn dialect(&self) -> Dialect {
+        Dialect::Day9
+    }
+
+    fn checked(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, program: &[Word], inputs: &[Word], step_limit: usize) -> Outcome {
+        let mut machine = Machine::new(program.to_vec());
+        machine.extend_input(inputs);
+
+        let (outputs, termination) = run_machine(&mut machine, step_limit);
+
+        Outcome {
+            outputs: outputs,
+            memory: machine.memory().to_vec(),
+            termination: termination
+        }
+    }
+}
+
+impl Engine for Traced {
+    fn name(&self) -> &'static str {
+        "traced"
+    }
+
+    fn dialect(&self) -> Dialect {
+        Dialect::Day9
+    }
+
+    fn checked(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, program: &[Word], inputs: &[Word], step_limit: usize) -> Outcome {
+        let mut machine = Machine::new(program.to_vec());
+        let mut inputs = inputs.iter().cloned();
+        let mut outputs = Vec::new();
+
+        let (_, termination) = session::record(&mut machine, || inputs.next(), |value| outputs.push(value), step_limit);
+
+        Outcome {
+            outputs: outputs,
+            memory: machine.memory().to_vec(),
+            termination: termination
+        }
+    }
+}
+
+impl Engine for Async {
+    fn name(&self) -> &'static str {
+        "async"
+    }
+
+    fn dialect(&self) -> Dialect {
+        Dialect::Day9
+    }
+
+    fn checked(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, program: &[Word], inputs: &[Word], step_limit: usize) -> Outcome {
+        let mut machine = Machine::new(program.to_vec());
+        let mut inputs: VecDeque<Word> = inputs.iter().cloned().collect();
+        let mut outputs: Vec<Word> = Vec::new();
+
+        let result = LocalPool::new().run_until(async_machine::run_limited(&mut machine, &mut inputs, &mut outputs, step_limit));
+
+        Outcome {
+            outputs: outputs,
+            memory: machine.memory().to_vec(),
+            termination: match result {
+                Ok(()) => Termination::Halted,
+                Err(RunError::Vm(err)) => Termination::Error(err.kind),
+                Err(RunError::InputClosed) => Termination::InputExhausted,
+                Err(RunError::StepLimit) => Termination::StepLimit
+            }
+        }
+    }
+}
+
+// rebuilds the fault from the C interface's code and word
+fn ffi_error(code: i32, word: Word) -> ErrorKind {
+    match code {
+        ffi::ERROR_INVALID_OPCODE => ErrorKind::InvalidOpcode(word),
+        ffi::ERROR_INVALID_MODE => ErrorKind::InvalidMode(word),
+        ffi::ERROR_IMMEDIATE_WRITE => ErrorKind::ImmediateWrite,
+        ffi::ERROR_INVALID_ADDRESS => ErrorKind::InvalidAddress(word),
+        ffi::ERROR_ARITHMETIC_OVERFLOW => ErrorKind::ArithmeticOverflow,
+        code => panic!("unknown error code {}", code)
+    }
+}
+
+impl Engine for Ffi {
+    fn name(&self) -> &'static str {
+        "ffi"
+    }
+
+    fn dialect(&self) -> Dialect {
+        Dialect::Day9
+    }
+
+    fn checked(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, program: &[Word], inputs: &[Word], step_limit: usize) -> Outcome {
+        let mut outputs = Vec::new();
+
+        unsafe {
+            let machine = ffi::intcode_create(program.as_ptr(), program.len());
+
+            for &input in inputs.iter() {
+                ffi::intcode_push_input(machine, input);
+            }
+
+            let termination = loop {
+                match ffi::intcode_run_limited(machine, step_limit) {
+                    ffi::STATUS_OUTPUT => {
+                        let mut value = 0;
+                        while ffi::intcode_read_output(machine, &mut value) == 1 {
+                            outputs.push(value);
+                        }
+                    },
+                    ffi::STATUS_NEED_INPUT => break Termination::InputExhausted,
+                    ffi::STATUS_HALTED => break Termination::Halted,
+                    ffi::STATUS_STEP_LIMIT => break Termination::StepLimit,
+                    ffi::STATUS_ERROR => {
+                        let code = ffi::intcode_last_error(machine, ptr::null_mut());
+                        break Termination::Error(ffi_error(code, ffi::intcode_error_word(machine)));
+                    },
+                    status => panic!("unexpected status {}", status)
+                }
+            };
+
+            let mut memory = vec!(0; ffi::intcode_read_memory(machine, ptr::null_mut(), 0));
+            ffi::intcode_read_memory(machine, memory.as_mut_ptr(), memory.len());
+
+            ffi::intcode_destroy(machine);
+
+            Outcome {
+                outputs: outputs,
+                memory: memory,
+                termination: termination
+            }
+        }
+    }
+}
+
+impl Engine for Optimized {
+    fn name(&self) -> &'static str {
+        "optimized"
+    }
+
+    fn dialect(&self) -> Dialect {
+        Dialect::Day9
+    }
+
+    fn checked(&self) -> bool {
+        true
+    }
+
+    fn rewrites(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, program: &[Word], inputs: &[Word], step_limit: usize) -> Outcome {
+        let optimization = optimizer::optimize(program, &[]);
+
+        Reference.execute(&optimization.program, inputs, step_limit)
+    }
+}
+
+impl Engine for LegacyDay2 {
+    fn name(&self) -> &'static str {
+        "day2"
+    }
+
+    fn dialect(&self) -> Dialect {
+        Dialect::Day2
+    }
+
+    fn checked(&self) -> bool {
+        false
+    }
+
+    fn execute(&self, program: &[Word], _inputs: &[Word], _step_limit: usize) -> Outcome {
+        let mut machine = day2::int_code::IntCode::from_program(to_legacy(program));
+
+        // patch noun and verb with what is already there
+        machine.process_inputs(program[1] as i32, program[2] as i32);
+
+        Outcome {
+            outputs: Vec::new(),
+            memory: from_legacy(machine.current_state()),
+            termination: Termination::Halted
+        }
+    }
+}
+
+impl Engine for LegacyDay5 {
+    fn name(&self) -> &'static str {
+        "day5"
+    }
+
+    fn dialect(&self) -> Dialect {
+        Dialect::Day5
+    }
+
+    fn checked(&self) -> bool {
+        false
+    }
+
+    fn execute(&self, program: &[Word], inputs: &[Word], _step_limit: usize) -> Outcome {
+        let mut machine = day5::int_code::IntCode::from_program(to_legacy(program));
+
+        let outputs = machine.run(to_legacy(inputs));
+
+        Outcome {
+            outputs: from_legacy(&outputs),
+            memory: from_legacy(machine.current_state()),
+            termination: Termination::Halted
+        }
+    }
+}
+
+impl Engine for LegacyDay7 {
+    fn name(&self) -> &'static str {
+        "day7"
+    }
+
+    fn dialect(&self) -> Dialect {
+        Dialect::Day5
+    }
+
+    fn checked(&self) -> bool {
+        false
+    }
+
+    fn execute(&self, program: &[Word], inputs: &[Word], _step_limit: usize) -> Outcome {
+        let mut machine = day7::int_code::IntCode::from_program(to_legacy(program));
+        machine.reset(to_legacy(inputs));
+
+        let mut outputs = Vec::new();
+        while let Some(output) = machine.run_until_output() {
+            outputs.push(output as Word);
+        }
+
+        Outcome {
+            outputs: outputs,
+            memory: from_legacy(machine.current_state()),
+            termination: Termination::Halted
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn engines_agree_on_example() {
+        // outputs 999, 1000 or 1001 depending on how the input compares to 8
+        let program = vec!(3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31,
+            1106, 0, 36, 98, 0, 0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104,
+            999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20, 1105, 1, 46, 98, 99);
+
+        for input in 6..11 {
+            let expected = Reference.execute(&program, &[input], 1000);
+
+            for engine in all().iter().filter(|e| e.dialect() >= Dialect::Day5) {
+                let outcome = engine.execute(&program, &[input], 1000);
+                let agrees = if engine.rewrites() {
+                    outcome.agrees_after_rewrite(&expected)
+                } else {
+                    outcome.agrees_with(&expected)
+                };
+
+                assert!(agrees, "{} disagrees", engine.name());
+            }
+        }
+    }
+
+    #[test]
+    fn reference_step_limit() {
+        let outcome = Reference.execute(&[1105, 1, 0], &[], 50);
+
+        assert_eq!(outcome.termination, Termination::StepLimit);
+        assert!(Traced.execute(&[1105, 1, 0], &[], 50).agrees_with(&outcome));
+        assert!(Async.execute(&[1105, 1, 0], &[], 50).agrees_with(&outcome));
+        assert!(Ffi.execute(&[1105, 1, 0], &[], 50).agrees_with(&outcome));
+    }
+
+    #[test]
+    fn traced_reports_faults() {
+        // reads from a negative address after taking one input
+        let program = [3, 9, 4, 9, 1, -1, 0, 9, 99, 0];
+
+        let expected = Reference.execute(&program, &[7, 8], 100);
+        let outcome = Traced.execute(&program, &[7, 8], 100);
+
+        assert!(matches!(outcome.termination, Termination::Error(_)));
+        assert!(outcome.agrees_with(&expected));
+        assert_eq!(outcome.outputs, vec!(7));
+    }
+}
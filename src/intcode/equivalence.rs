@@ -0,0 +1,315 @@
+use std::fmt;
+use std::vec::Vec;
+
+use super::Word;
+use super::engine::{ self, Termination };
+use super::machine::Machine;
+use super::optimizer::Sample;
+use super::rng::Rng;
+
+// domains at most this large are enumerated rather than sampled
+pub const EXHAUSTIVE_LIMIT: u128 = 100_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Range {
+    pub low: Word,
+    pub high: Word
+}
+
+// the values a run may start from: cells patched before running and the
+// inputs supplied, each drawn from its own inclusive range
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Domain {
+    pub patches: Vec<(usize, Range)>,
+    pub inputs: Vec<Range>
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Options {
+    pub seed: u64,
+    // runs to try when the domain is too large to enumerate
+    pub samples: usize,
+    pub step_limit: usize
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Counterexample {
+    pub sample: Sample,
+    pub left: (Vec<Word>, Termination),
+    pub right: (Vec<Word>, Termination)
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Report {
+    pub cases: u128,
+    pub exhaustive: bool,
+    pub counterexample: Option<Counterexample>
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseError {
+    pub item: String,
+    pub message: String
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            seed: 0,
+            samples: 10_000,
+            step_limit: 1_000_000
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "in `{}`: {}", self.item, self.message)
+    }
+}
+
+impl Range {
+    pub fn size(&self) -> u128 {
+        (self.high as i128 - self.low as i128 + 1).max(0) as u128
+    }
+
+    fn parse(text: &str) -> Option<Range> {
+        let (low, high) = match text.find("..") {
+            Some(at) => (&text[..at], text[at + 2..].trim_start_matches('=')),
+            None => (text, text)
+        };
+
+        let range = Range {
+            low: low.trim().parse().ok()?,
+            high: high.trim().parse().ok()?
+        };
+
+        if range.low > range.high {
+            return None;
+        }
+
+        Some(range)
+    }
+}
+
+impl Domain {
+    // comma separated: `0..9` is the next input, `@1=0..99` patches cell 1,
+    // and a lone number stands for a single value
+    pub fn parse(spec: &str) -> Result<Domain, ParseError> {
+        let mut domain = Domain::default();
+
+        for item in spec.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let error = |message: &str| ParseError {
+                item: String::from(item),
+                message: String::from(message)
+            };
+
+            if item.starts_with('@') {
+                let eq = item.find('=').ok_or_else(|| error("expected `@cell=range`"))?;
+                let cell = item[1..eq].trim().parse().map_err(|_| error("bad cell address"))?;
+                let range = Range::parse(&item[eq + 1..]).ok_or_else(|| error("bad range"))?;
+
+                domain.patches.push((cell, range));
+            } else {
+                domain.inputs.push(Range::parse(item).ok_or_else(|| error("bad range"))?);
+            }
+        }
+
+        Ok(domain)
+    }
+
+    fn ranges(&self) -> Vec<Range> {
+        self.patches.iter().map(|&(_, range)| range).chain(self.inputs.iter().cloned()).collect()
+    }
+
+    // saturates rather than overflowing, which is only ever compared against
+    // the exhaustive limit
+    pub fn size(&self) -> u128 {
+        self.ranges().iter().fold(1u128, |size, range| size.saturating_mul(range.size()))
+    }
+
+    fn sample(&self, values: &[Word]) -> Sample {
+        let (patched, inputs) = values.split_at(self.patches.len());
+
+        Sample {
+            patches: self.patches.iter().map(|&(cell, _)| cell).zip(patched.iter().cloned()).collect(),
+            inputs: inputs.to_vec()
+        }
+    }
+}
+
+fn run(program: &[Word], sample: &Sample, step_limit: usize) -> (Vec<Word>, Termination) {
+    let mut program = program.to_vec();
+
+    for &(cell, value) in sample.patches.iter() {
+        if cell >= program.len() {
+            program.resize(cell + 1, 0);
+        }
+        program[cell] = value;
+    }
+
+    let mut machine = Machine::new(program);
+    machine.extend_input(&sample.inputs);
+
+    engine::run_machine(&mut machine, step_limit)
+}
+
+fn compare(left: &[Word], right: &[Word], sample: Sample, step_limit: usize) -> Option<Counterexample> {
+    let left = run(left, &sample, step_limit);
+    let right = run(right, &sample, step_limit);
+
+    if left == right {
+        return None;
+    }
+
+    Some(Counterexample { sample: sample, left: left, right: right })
+}
+
+// steps a mixed-radix counter over the ranges, false once it wraps around
+fn advance(values: &mut [Word], ranges: &[Range]) -> bool {
+    for (value, range) in values.iter_mut().zip(ranges.iter()).rev() {
+        if *value < range.high {
+            *value += 1;
+            return true;
+        }
+
+        *value = range.low;
+    }
+
+    false
+}
+
+// checks the programs produce the same outputs and stop the same way on every
+// start in the domain, or on a seeded random selection when it's too large
+pub fn check(left: &[Word], right: &[Word], domain: &Domain, options: &Options) -> Report {
+    let ranges = domain.ranges();
+    let size = domain.size();
+
+    let mut report = Report {
+        cases: 0,
+        exhaustive: size <= EXHAUSTIVE_LIMIT,
+        counterexample: None
+    };
+
+    if report.exhaustive {
+        if size == 0 {
+            return report;
+        }
+
+        let mut values: Vec<Word> = ranges.iter().map(|range| range.low).collect();
+
+        loop {
+            report.cases += 1;
+            report.counterexample = compare(left, right, domain.sample(&values), options.step_limit);
+
+            if report.counterexample.is_some() || !advance(&mut values, &ranges) {
+                return report;
+            }
+        }
+    }
+
+    let mut rng = Rng::new(options.seed);
+
+    for _ in 0..options.samples {
+        let values: Vec<Word> = ranges.iter().map(|range| rng.range(range.low, range.high)).collect();
+
+        report.cases += 1;
+        report.counterexample = compare(left, right, domain.sample(&values), options.step_limit);
+
+        if report.counterexample.is_some() {
+            break;
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // outputs 1 if the input is 8, 0 otherwise, in position and immediate mode
+    const EQ_POSITION: [Word; 11] = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+    const EQ_IMMEDIATE: [Word; 9] = [3, 3, 1108, -1, 8, 3, 4, 3, 99];
+
+    // outputs 1 if the input is less than 8
+    const LT_IMMEDIATE: [Word; 9] = [3, 3, 1107, -1, 8, 3, 4, 3, 99];
+
+    #[test]
+    fn parses_domains() {
+        let domain = Domain::parse("@1=0..99, @2=0..=99, -5..5, 7").unwrap();
+
+        assert_eq!(domain.patches, vec!((1, Range { low: 0, high: 99 }), (2, Range { low: 0, high: 99 })));
+        assert_eq!(domain.inputs, vec!(Range { low: -5, high: 5 }, Range { low: 7, high: 7 }));
+        assert_eq!(domain.size(), 100 * 100 * 11);
+
+        assert!(Domain::parse("@x=1").is_err());
+        assert!(Domain::parse("5..1").is_err());
+    }
+
+    #[test]
+    fn equivalent_programs() {
+        let domain = Domain::parse("-100..100").unwrap();
+        let report = check(&EQ_POSITION, &EQ_IMMEDIATE, &domain, &Options::default());
+
+        assert!(report.exhaustive);
+        assert_eq!(report.cases, 201);
+        assert_eq!(report.counterexample, None);
+    }
+
+    #[test]
+    fn first_counterexample() {
+        let domain = Domain::parse("0..20").unwrap();
+        let report = check(&EQ_IMMEDIATE, &LT_IMMEDIATE, &domain, &Options::default());
+
+        let counterexample = report.counterexample.unwrap();
+
+        assert_eq!(counterexample.sample.inputs, vec!(0));
+        assert_eq!(counterexample.left, (vec!(0), Termination::Halted));
+        assert_eq!(counterexample.right, (vec!(1), Termination::Halted));
+    }
+
+    #[test]
+    fn samples_large_domains() {
+        // day 2 style: the noun and verb are patched in and the sum output,
+        // against a version that adds one more whenever the noun is below
+        // 100000, a tenth of the noun's range
+        let sum = [1101, 0, 0, 0, 4, 0, 99];
+        let seeded = [1101, 0, 0, 20, 1007, 1, 100000, 21, 1006, 21, 15, 1001, 20, 1, 20,
+            4, 20, 99, 0, 0, 0, 0];
+
+        let domain = Domain {
+            patches: vec!((1, Range { low: 0, high: 999_999 }), (2, Range { low: 0, high: 999_999 })),
+            inputs: Vec::new()
+        };
+        let options = Options { seed: 7, samples: 500, ..Options::default() };
+
+        let report = check(&sum, &sum, &domain, &options);
+        assert!(!report.exhaustive);
+        assert_eq!(report.cases, 500);
+        assert_eq!(report.counterexample, None);
+
+        let report = check(&sum, &seeded, &domain, &options);
+        let counterexample = report.counterexample.unwrap();
+
+        let (noun, verb) = (counterexample.sample.patches[0].1, counterexample.sample.patches[1].1);
+        assert!(noun < 100000);
+        assert_eq!(counterexample.left, (vec!(noun + verb), Termination::Halted));
+        assert_eq!(counterexample.right, (vec!(noun + verb + 1), Termination::Halted));
+    }
+
+    #[test]
+    fn sampling_is_reproducible() {
+        // the patched cell is never read, it only makes the domain too large
+        // to enumerate
+        let domain = Domain::parse("@20=0..1000000, 0..15").unwrap();
+        let options = Options { seed: 42, samples: 100, ..Options::default() };
+
+        let first = check(&EQ_POSITION, &LT_IMMEDIATE, &domain, &options);
+        let second = check(&EQ_POSITION, &LT_IMMEDIATE, &domain, &options);
+
+        assert_eq!(first, second);
+        assert!(!first.exhaustive);
+        assert!(first.counterexample.is_some());
+    }
+}
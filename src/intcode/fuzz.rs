@@ -0,0 +1,366 @@
+use std::fmt;
+use std::vec::Vec;
+
+use super::Word;
+use super::engine::{ Dialect, Engine, Outcome };
+use super::instruction::{ Instruction, Mode, Opcode };
+use super::minimizer::{ self, Reproduction };
+use super::rng::Rng;
+
+const MAX_INSTRS: usize = 12;
+const DATA_SIZE: usize = 8;
+const STEP_LIMIT: usize = 10_000;
+
+// generated values stay below this so the i32 legacy engines never overflow
+const VALUE_LIMIT: Word = 1 << 30;
+
+const INTERESTING: [Word; 10] = [0, 1, -1, 3, 4, 99, 203, 1105, 21101, 1 << 40];
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Case {
+    pub dialect: Dialect,
+    pub program: Vec<Word>,
+    pub inputs: Vec<Word>,
+    // valid cases terminate without faulting on every engine of their
+    // dialect, so unchecked engines can run them too. the rest, which may
+    // fault, loop or starve, only go to checked engines
+    pub valid: bool
+}
+
+#[derive(Debug)]
+pub struct Mismatch {
+    pub case: Case,
+    pub expected_engine: &'static str,
+    pub actual_engine: &'static str,
+    pub expected: Outcome,
+    pub actual: Outcome,
+    pub reduced: Option<Reproduction<Word>>
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} and {} disagree", self.expected_engine, self.actual_engine)?;
+        writeln!(f, "  program: {:?}", self.case.program)?;
+        writeln!(f, "  inputs: {:?}", self.case.inputs)?;
+        writeln!(f, "  {}: {:?}", self.expected_engine, self.expected)?;
+        write!(f, "  {}: {:?}", self.actual_engine, self.actual)?;
+
+        if let Some(reduced) = &self.reduced {
+            write!(f, "\n  reduced: program {:?} inputs {:?}", reduced.program, reduced.inputs)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn read_modes(dialect: Dialect) -> &'static [Mode] {
+    match dialect {
+        Dialect::Day2 => &[Mode::Position],
+        Dialect::Day5 => &[Mode::Position, Mode::Immediate],
+        Dialect::Day9 => &[Mode::Position, Mode::Immediate, Mode::Relative]
+    }
+}
+
+fn opcodes(dialect: Dialect) -> Vec<Opcode> {
+    let mut ops = vec!(Opcode::Add, Opcode::Mul);
+
+    if dialect >= Dialect::Day5 {
+        ops.extend_from_slice(&[Opcode::Input, Opcode::Output, Opcode::JumpIfTrue,
+            Opcode::JumpIfFalse, Opcode::LessThan, Opcode::Equals]);
+    }
+
+    if dialect >= Dialect::Day9 {
+        ops.push(Opcode::AdjustBase);
+    }
+
+    ops
+}
+
+// builds a program that only jumps forward and only writes to a data region
+// after the code, so it terminates for every engine
+pub fn generate_valid(rng: &mut Rng, dialect: Dialect) -> Case {
+    let available = opcodes(dialect);
+    let mut bound: Word = 9;
+    let mut ops: Vec<Opcode> = Vec::new();
+
+    for _ in 0..1 + rng.below(MAX_INSTRS) {
+        let op = *rng.choose(&available);
+
+        // keep a global bound on every value so legacy i32 arithmetic is safe
+        bound = match op {
+            Opcode::Add if bound * 2 < VALUE_LIMIT => bound * 2,
+            Opcode::Mul if bound * bound < VALUE_LIMIT => bound * bound,
+            Opcode::Add | Opcode::Mul => continue,
+            _ => bound
+        };
+
+        ops.push(op);
+    }
+
+    if ops.is_empty() {
+        ops.push(Opcode::Add);
+    }
+
+    let mut addresses = Vec::new();
+    let mut addr = 0;
+    for op in ops.iter() {
+        addresses.push(addr as Word);
+        addr += op.param_count() + 1;
+    }
+
+    let halt_addr = addr;
+    addresses.push(halt_addr as Word);
+
+    let data_start = (halt_addr + 1) as Word;
+    let modes = read_modes(dialect);
+
+    let mut program: Vec<Word> = Vec::new();
+    let mut inputs: Vec<Word> = Vec::new();
+
+    for (instr_i, &op) in ops.iter().enumerate() {
+        let mut instr = Instruction::new(op, [Mode::Position; 3]);
+        let mut params = Vec::new();
+
+        for param_i in 0..op.param_count() {
+            let is_dest = op.dest_param() == Some(param_i);
+            let is_target = op.is_jump() && param_i == 1;
+
+            let mode = if is_target {
+                Mode::Immediate
+            } else if is_dest {
+                if dialect >= Dialect::Day9 && rng.chance(30) { Mode::Relative } else { Mode::Position }
+            } else {
+                *rng.choose(modes)
+            };
+
+            let value = match mode {
+                _ if is_target => addresses[instr_i + 1 + rng.below(ops.len() - instr_i)],
+                _ if op == Opcode::AdjustBase && mode == Mode::Immediate => rng.range(-4, 4),
+                Mode::Immediate => rng.range(0, 9),
+                Mode::Position => data_start + rng.below(DATA_SIZE) as Word,
+                Mode::Relative => rng.range(-2, data_start + DATA_SIZE as Word)
+            };
+
+            instr.modes[param_i] = mode;
+            params.push(value);
+        }
+
+        if op == Opcode::Input {
+            inputs.push(rng.range(0, 9));
+        }
+
+        program.push(instr.encode());
+        program.extend(params);
+    }
+
+    program.push(Opcode::Halt.code());
+
+    for _ in 0..DATA_SIZE {
+        program.push(rng.range(0, 9));
+    }
+
+    Case {
+        dialect: dialect,
+        program: program,
+        inputs: inputs,
+        valid: dialect < Dialect::Day9
+    }
+}
+
+// corrupts a generated program so it may fault, loop or starve for input
+pub fn generate_invalid(rng: &mut Rng) -> Case {
+    let mut case = generate_valid(rng, Dialect::Day9);
+
+    for _ in 0..1 + rng.below(3) {
+        let len = case.program.len();
+
+        match rng.below(4) {
+            0 => case.program[rng.below(len)] = *rng.choose(&INTERESTING),
+            1 => case.program[rng.below(len)] = rng.range(-10, 2300),
+            2 => { case.program.remove(rng.below(len)); },
+            _ => case.program.insert(rng.below(len + 1), rng.range(-10, 2300))
+        }
+
+        if case.program.is_empty() {
+            case.program.push(rng.range(-10, 2300));
+        }
+    }
+
+    if !case.inputs.is_empty() && rng.chance(20) {
+        case.inputs.pop();
+    }
+
+    case.valid = false;
+    case
+}
+
+fn participants<'a>(case: &Case, engines: &'a [Box<dyn Engine>]) -> Vec<&'a dyn Engine> {
+    engines.iter()
+        .filter(|e| e.dialect() >= case.dialect && (case.valid || e.checked()))
+        .map(|e| e.as_ref())
+        .collect()
+}
+
+// runs a case through every engine able to take it and compares each
+// against the first one
+pub fn check(case: &Case, engines: &[Box<dyn Engine>]) -> Result<(), Box<Mismatch>> {
+    let engines = participants(case, engines);
+    let (reference, others) = match engines.split_first() {
+        Some(split) => split,
+        None => return Ok(())
+    };
+
+    let expected = reference.execute(&case.program, &case.inputs, STEP_LIMIT);
+
+    for engine in others.iter() {
+        // the first engine, which the rest are held to, runs programs as given
+        let agree = |actual: &Outcome, expected: &Outcome| if engine.rewrites() {
+            actual.agrees_after_rewrite(expected)
+        } else {
+            actual.agrees_with(expected)
+        };
+
+        let actual = engine.execute(&case.program, &case.inputs, STEP_LIMIT);
+
+        if agree(&actual, &expected) {
+            continue;
+        }
+
+        // only engines that survive arbitrary programs can be minimized against
+        let reduced = if reference.checked() && engine.checked() {
+            let disagree = |program: &[Word], inputs: &[Word]| {
+                let expected = reference.execute(program, inputs, STEP_LIMIT);
+                !agree(&engine.execute(program, inputs, STEP_LIMIT), &expected)
+            };
+
+            Some(minimizer::minimize(case.program.to_vec(), case.inputs.to_vec(), disagree))
+        } else {
+            None
+        };
+
+        return Err(Box::new(Mismatch {
+            case: case.clone(),
+            expected_engine: reference.name(),
+            actual_engine: engine.name(),
+            expected: expected,
+            actual: actual,
+            reduced: reduced
+        }));
+    }
+
+    Ok(())
+}
+
+pub fn fuzz(seed: u64, iterations: usize, engines: &[Box<dyn Engine>]) -> Result<(), Box<Mismatch>> {
+    let mut rng = Rng::new(seed);
+    let dialects = [Dialect::Day2, Dialect::Day5, Dialect::Day9];
+
+    for _ in 0..iterations {
+        let dialect = *rng.choose(&dialects);
+
+        check(&generate_valid(&mut rng, dialect), engines)?;
+        check(&generate_invalid(&mut rng), engines)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::engine::{ self, Reference, Termination };
+    use std::env;
+
+    fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+        env::var(name).ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
+    }
+
+    // drops the last output, standing in for a broken engine
+    struct Lossy;
+
+    impl Engine for Lossy {
+        fn name(&self) -> &'static str {
+            "lossy"
+        }
+
+        fn dialect(&self) -> Dialect {
+            Dialect::Day9
+        }
+
+        fn checked(&self) -> bool {
+            true
+        }
+
+        fn execute(&self, program: &[Word], inputs: &[Word], step_limit: usize) -> Outcome {
+            let mut outcome = Reference.execute(program, inputs, step_limit);
+            outcome.outputs.pop();
+            outcome
+        }
+    }
+
+    #[test]
+    fn valid_programs_halt() {
+        let mut rng = Rng::new(5);
+
+        for _ in 0..200 {
+            let case = generate_valid(&mut rng, Dialect::Day5);
+            let outcome = Reference.execute(&case.program, &case.inputs, STEP_LIMIT);
+
+            assert_eq!(outcome.termination, Termination::Halted, "{:?}", case);
+        }
+    }
+
+    #[test]
+    fn detects_and_reduces_mismatch() {
+        let engines: Vec<Box<dyn Engine>> = vec!(Box::new(Reference), Box::new(Lossy));
+        let case = Case {
+            dialect: Dialect::Day5,
+            program: vec!(1101, 2, 3, 9, 4, 9, 99, 0, 0, 0),
+            inputs: vec!(1, 2),
+            valid: true
+        };
+
+        let mismatch = check(&case, &engines).unwrap_err();
+        let reduced = mismatch.reduced.unwrap();
+
+        assert_eq!(mismatch.actual_engine, "lossy");
+        assert_eq!(reduced.program, vec!(4));
+        assert!(reduced.inputs.is_empty());
+    }
+
+    #[test]
+    fn invalid_cases_have_a_second_opinion() {
+        let engines = engine::all();
+        let mut rng = Rng::new(11);
+
+        for _ in 0..50 {
+            let case = generate_invalid(&mut rng);
+            assert!(participants(&case, &engines).len() >= 2, "{:?}", case);
+
+            let case = generate_valid(&mut rng, Dialect::Day9);
+            assert!(participants(&case, &engines).len() >= 2, "{:?}", case);
+        }
+    }
+
+    #[test]
+    fn differential_fixed_seed() {
+        if let Err(mismatch) = fuzz(2019, 500, &engine::all()) {
+            panic!("{}", mismatch);
+        }
+    }
+
+    // long-running local fuzz target:
+    // INTCODE_FUZZ_SEED=1 INTCODE_FUZZ_ITERATIONS=1000000 cargo test differential_long -- --ignored
+    #[test]
+    #[ignore]
+    fn differential_long() {
+        let seed = env_or("INTCODE_FUZZ_SEED", 1);
+        let iterations = env_or("INTCODE_FUZZ_ITERATIONS", 200_000);
+
+        if let Err(mismatch) = fuzz(seed, iterations, &engine::all()) {
+            panic!("seed {}: {}", seed, mismatch);
+        }
+    }
+}
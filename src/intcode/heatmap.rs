@@ -0,0 +1,401 @@
+use std::fs;
+use std::io;
+use std::path::{ Path, PathBuf };
+use std::vec::Vec;
+
+use super::Word;
+use super::engine::Termination;
+use super::instruction::Mode;
+use super::machine::{ Event, Machine };
+
+const HIGHLIGHT: [u8; 3] = [255, 255, 255];
+
+// cells past this many aren't counted, so one far off access can't make the
+// counts as large as the machine's whole address space
+const MAX_CELLS: usize = 1 << 16;
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Heatmap {
+    pub reads: Vec<u64>,
+    pub writes: Vec<u64>,
+    pub executes: Vec<u64>,
+    pub instr_ptr: usize
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Layout {
+    // cells per row
+    width: usize,
+    // pixels per cell side, ignored by the text rendering
+    scale: usize
+}
+
+// writes a numbered image every `every` steps into `dir`
+#[derive(Clone, PartialEq, Debug)]
+pub struct Animation {
+    dir: PathBuf,
+    every: usize,
+    layout: Layout
+}
+
+// the cells one instruction touches, taken before it runs so the machine
+// doesn't have to be kept around as it was
+#[derive(Clone, PartialEq, Debug)]
+pub struct Access {
+    instr_ptr: usize,
+    len: usize,
+    reads: Vec<Word>,
+    write: Option<Word>
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+impl Layout {
+    pub fn new(width: usize, scale: usize) -> io::Result<Layout> {
+        if width == 0 {
+            return Err(invalid("layout needs at least one cell per row"));
+        }
+        if scale == 0 {
+            return Err(invalid("layout needs at least one pixel per cell"));
+        }
+
+        Ok(Layout { width: width, scale: scale })
+    }
+}
+
+impl Animation {
+    pub fn new(dir: PathBuf, every: usize, layout: Layout) -> io::Result<Animation> {
+        if every == 0 {
+            return Err(invalid("animation needs a frame every one or more steps"));
+        }
+
+        Ok(Animation { dir: dir, every: every, layout: layout })
+    }
+}
+
+impl Access {
+    // what the instruction at the machine's pointer is about to touch, or
+    // None if there's no valid instruction there
+    pub fn of(machine: &Machine) -> Option<Access> {
+        let instr = machine.current_instruction().ok()?;
+        let i_ptr = machine.instr_ptr();
+
+        let mut access = Access {
+            instr_ptr: i_ptr,
+            len: instr.len(),
+            reads: Vec::new(),
+            write: None
+        };
+
+        for param_i in 0..instr.opcode.param_count() {
+            let raw = machine.read((i_ptr + 1 + param_i) as Word).unwrap_or(0);
+
+            let addr = match instr.modes[param_i] {
+                Mode::Immediate => continue,
+                Mode::Position => raw,
+                // the machine faults on an address it can't work out
+                Mode::Relative => match machine.relative_base().checked_add(raw) {
+                    Some(addr) => addr,
+                    None => continue
+                }
+            };
+
+            if instr.opcode.dest_param() == Some(param_i) {
+                access.write = Some(addr);
+            } else {
+                access.reads.push(addr);
+            }
+        }
+
+        Some(access)
+    }
+}
+
+fn bump(counts: &mut Vec<u64>, addr: Word) {
+    if addr < 0 || addr as usize >= MAX_CELLS {
+        return;
+    }
+
+    let addr = addr as usize;
+    if addr >= counts.len() {
+        counts.resize(addr + 1, 0);
+    }
+
+    counts[addr] += 1;
+}
+
+// 0..=255, on a log scale so a few hot cells don't wash everything else out
+fn intensity(count: u64, max: u64) -> u8 {
+    if count == 0 || max == 0 {
+        return 0;
+    }
+
+    let scaled = ((count as f64).ln_1p() / (max as f64).ln_1p()) * 195.0 + 60.0;
+    scaled.min(255.0) as u8
+}
+
+impl Heatmap {
+    pub fn new() -> Heatmap {
+        Heatmap::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.reads.len().max(self.writes.len()).max(self.executes.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // records the accesses the instruction at the machine's pointer is about
+    // to make; call right before a step that executes it
+    pub fn observe(&mut self, machine: &Machine) {
+        if let Some(access) = Access::of(machine) {
+            self.record(&access);
+        }
+    }
+
+    pub fn record(&mut self, access: &Access) {
+        self.instr_ptr = access.instr_ptr;
+
+        for offset in 0..access.len {
+            bump(&mut self.executes, (access.instr_ptr + offset) as Word);
+        }
+
+        for &addr in access.reads.iter() {
+            bump(&mut self.reads, addr);
+        }
+
+        if let Some(addr) = access.write {
+            bump(&mut self.writes, addr);
+        }
+    }
+
+    // the highest write, read and execute counts, which colours are scaled by
+    pub fn peaks(&self) -> [u64; 3] {
+        let max = |counts: &Vec<u64>| counts.iter().cloned().max().unwrap_or(0);
+
+        [max(&self.writes), max(&self.reads), max(&self.executes)]
+    }
+
+    pub fn colour(&self, addr: usize, peaks: &[u64; 3]) -> [u8; 3] {
+        let count = |counts: &Vec<u64>| counts.get(addr).cloned().unwrap_or(0);
+
+        [
+            intensity(count(&self.writes), peaks[0]),
+            intensity(count(&self.reads), peaks[1]),
+            intensity(count(&self.executes), peaks[2])
+        ]
+    }
+
+    // binary ppm: writes in red, reads in green, execution in blue, and the
+    // instruction pointer's cell outlined in white
+    pub fn to_ppm(&self, layout: &Layout) -> Vec<u8> {
+        let cells = self.len().max(1);
+        let rows = cells.div_ceil(layout.width);
+        let peaks = self.peaks();
+        let (width, height) = (layout.width * layout.scale, rows * layout.scale);
+
+        let mut image = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+
+        for y in 0..height {
+            for x in 0..width {
+                let (col, row) = (x / layout.scale, y / layout.scale);
+                let addr = row * layout.width + col;
+
+                let (cx, cy) = (x % layout.scale, y % layout.scale);
+                let on_border = cx == 0 || cy == 0 || cx == layout.scale - 1 || cy == layout.scale - 1;
+
+                let pixel = if addr == self.instr_ptr && on_border {
+                    HIGHLIGHT
+                } else if addr < cells {
+                    self.colour(addr, &peaks)
+                } else {
+                    [0, 0, 0]
+                };
+
+                image.extend_from_slice(&pixel);
+            }
+        }
+
+        image
+    }
+
+    // 24-bit ansi colour blocks, two characters per cell, `>` marking the
+    // instruction pointer
+    pub fn to_ansi(&self, layout: &Layout) -> String {
+        let width = layout.width;
+        let peaks = self.peaks();
+        let mut text = String::new();
+
+        for addr in 0..self.len() {
+            let [r, g, b] = self.colour(addr, &peaks);
+            let marker = if addr == self.instr_ptr { "> " } else { "  " };
+
+            text.push_str(&format!("\x1b[48;2;{};{};{}m{}", r, g, b, marker));
+
+            if (addr + 1) % width == 0 || addr + 1 == self.len() {
+                text.push_str("\x1b[0m\n");
+            }
+        }
+
+        text
+    }
+}
+
+// runs a program while counting memory accesses, optionally writing frames
+pub fn profile(program: &[Word], inputs: &[Word], step_limit: usize, animation: Option<&Animation>) -> io::Result<(Heatmap, Termination)> {
+    let mut machine = Machine::new(program.to_vec());
+    machine.extend_input(inputs);
+
+    let mut heatmap = Heatmap::new();
+    let mut frame = 0;
+
+    if let Some(animation) = animation {
+        fs::create_dir_all(&animation.dir)?;
+    }
+
+    let termination = loop {
+        if machine.steps() >= step_limit {
+            break Termination::StepLimit;
+        }
+
+        let access = Access::of(&machine);
+        let steps = machine.steps();
+
+        let event = machine.step();
+
+        if machine.steps() > steps {
+            if let Some(access) = &access {
+                heatmap.record(access);
+            }
+        }
+
+        if let Some(animation) = animation {
+            if machine.steps() > steps && steps.is_multiple_of(animation.every) {
+                heatmap.instr_ptr = machine.instr_ptr();
+                write_frame(&animation.dir, frame, &heatmap.to_ppm(&animation.layout))?;
+                frame += 1;
+            }
+        }
+
+        match event {
+            Ok(None) | Ok(Some(Event::Output(_))) => (),
+            Ok(Some(Event::NeedInput)) => break Termination::InputExhausted,
+            Ok(Some(Event::Halted)) => break Termination::Halted,
+            Err(err) => break Termination::Error(err.kind)
+        }
+    };
+
+    heatmap.instr_ptr = machine.instr_ptr();
+
+    Ok((heatmap, termination))
+}
+
+fn write_frame(dir: &Path, frame: usize, image: &[u8]) -> io::Result<()> {
+    fs::write(dir.join(format!("frame_{:05}.ppm", frame)), image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    // doubles [12] five times, counting down [13]
+    const LOOP: [Word; 14] = [1002, 12, 2, 12, 1001, 13, -1, 13, 1005, 13, 0, 99, 1, 5];
+
+    #[test]
+    fn counts_accesses() {
+        let (heatmap, termination) = profile(&[1, 5, 6, 7, 99, 3, 4, 0], &[], 100, None).unwrap();
+
+        assert_eq!(termination, Termination::Halted);
+        assert_eq!(heatmap.executes[..5], [1, 1, 1, 1, 1]);
+        assert_eq!(heatmap.reads[5..7], [1, 1]);
+        assert_eq!(heatmap.writes[7], 1);
+        assert_eq!(heatmap.instr_ptr, 4);
+    }
+
+    #[test]
+    fn counts_relative_accesses() {
+        let (heatmap, _) = profile(&[109, 6, 204, 0, 99, 0, 42], &[], 100, None).unwrap();
+
+        assert_eq!(heatmap.reads[6], 1);
+    }
+
+    #[test]
+    fn skips_relative_addresses_past_a_word() {
+        let (heatmap, termination) = profile(&[109, Word::MAX, 204, 1, 99], &[], 100, None).unwrap();
+
+        assert!(matches!(termination, Termination::Error(_)));
+        assert!(heatmap.reads.is_empty());
+    }
+
+    #[test]
+    fn caps_far_off_cells() {
+        let mut heatmap = Heatmap::new();
+        heatmap.record(&Access { instr_ptr: 0, len: 1, reads: vec!(Word::MAX), write: Some(MAX_CELLS as Word) });
+
+        assert_eq!(heatmap.len(), 1);
+    }
+
+    #[test]
+    fn runs_the_loop() {
+        let (heatmap, termination) = profile(&LOOP, &[], 100, None).unwrap();
+
+        assert_eq!(termination, Termination::Halted);
+        assert_eq!(heatmap.executes[0], 5);
+        assert_eq!(heatmap.writes[12..14], [5, 5]);
+        assert_eq!(heatmap.executes[11], 1);
+    }
+
+    #[test]
+    fn ppm_image() {
+        let (heatmap, _) = profile(&LOOP, &[], 100, None).unwrap();
+        let layout = Layout::new(4, 3).unwrap();
+
+        let image = heatmap.to_ppm(&layout);
+        let header = b"P6\n12 12\n255\n";
+
+        assert_eq!(&image[..header.len()], &header[..]);
+        assert_eq!(image.len(), header.len() + 12 * 12 * 3);
+
+        // top-left pixel of the instruction pointer's cell (address 11, row 2,
+        // column 3) is highlighted
+        let offset = header.len() + (2 * 3 * 12 + 3 * 3) * 3;
+        assert_eq!(&image[offset..offset + 3], &HIGHLIGHT);
+    }
+
+    #[test]
+    fn ansi_rendering() {
+        let (heatmap, _) = profile(&LOOP, &[], 100, None).unwrap();
+
+        let text = heatmap.to_ansi(&Layout::new(6, 1).unwrap());
+
+        assert_eq!(text.lines().count(), 3);
+        assert!(text.contains("\x1b[48;2;"));
+        assert_eq!(text.matches('>').count(), 1);
+    }
+
+    #[test]
+    fn writes_frames() {
+        let dir = env::temp_dir().join(format!("intcode_heatmap_{}", std::process::id()));
+        let animation = Animation::new(dir.clone(), 4, Layout::new(4, 1).unwrap()).unwrap();
+
+        profile(&LOOP, &[], 100, Some(&animation)).unwrap();
+
+        let frames = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // sixteen steps, one frame before each fourth
+        assert_eq!(frames, 4);
+    }
+
+    #[test]
+    fn rejects_zero_sizes() {
+        assert!(Layout::new(0, 3).is_err());
+        assert!(Layout::new(4, 0).is_err());
+        assert!(Animation::new(env::temp_dir(), 0, Layout::new(4, 3).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::vec::Vec;
+
+use super::Word;
+
+// the std side of the machine: getting programs off disk. instruction and
+// machine live in the no_std intcode-core crate; everything that needs files
+// or the csv crate goes through here.
+
+// reads comma separated words; records may span several lines
+pub fn read_program<R: Read>(reader: R) -> Result<Vec<Word>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(reader);
+
+    let mut program = Vec::new();
+
+    for (line, record) in reader.records().enumerate() {
+        let record = record.map_err(|err| err.to_string())?;
+
+        for word in record.iter().map(str::trim).filter(|word| !word.is_empty()) {
+            let word = word.parse().map_err(|_| format!("bad word `{}` on line {}", word, line + 1))?;
+            program.push(word);
+        }
+    }
+
+    Ok(program)
+}
+
+pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Vec<Word>, String> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+
+    read_program(file).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+// the earlier interpreters work in 32 bit words
+pub fn read_legacy_program<R: Read>(reader: R) -> Result<Vec<i32>, String> {
+    read_program(reader)?.into_iter()
+        .map(|word| if word as i32 as Word == word { Ok(word as i32) } else { Err(format!("{} doesn't fit in 32 bits", word)) })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_programs() {
+        assert_eq!(read_program("1,0,0,3,99\n".as_bytes()), Ok(vec!(1, 0, 0, 3, 99)));
+        assert_eq!(read_program("1,0,\n0, 3,99".as_bytes()), Ok(vec!(1, 0, 0, 3, 99)));
+        assert_eq!(read_program("".as_bytes()), Ok(vec!()));
+
+        assert!(read_program("1,x,3".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn legacy_words() {
+        assert_eq!(read_legacy_program("104,-5,99".as_bytes()), Ok(vec!(104, -5, 99)));
+        assert!(read_legacy_program("104,1125899906842624,99".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{ Arc, Mutex };
+use std::task::{ Context, Poll, Wake, Waker };
+use std::vec::Vec;
+
+// minimal single-threaded executor, enough to multiplex machines as tasks
+pub struct LocalPool {
+    tasks: Vec<Option<Pin<Box<dyn Future<Output = ()>>>>>,
+    ready: Arc<Mutex<VecDeque<usize>>>
+}
+
+struct TaskWaker {
+    id: usize,
+    ready: Arc<Mutex<VecDeque<usize>>>
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.ready.lock().unwrap().push_back(self.id);
+    }
+}
+
+impl Default for LocalPool {
+    fn default() -> LocalPool {
+        LocalPool::new()
+    }
+}
+
+impl LocalPool {
+    pub fn new() -> LocalPool {
+        LocalPool {
+            tasks: Vec::new(),
+            ready: Arc::new(Mutex::new(VecDeque::new()))
+        }
+    }
+
+    pub fn spawn<F>(&mut self, task: F) where
+        F: Future<Output = ()> + 'static {
+
+        self.tasks.push(Some(Box::pin(task)));
+        self.ready.lock().unwrap().push_back(self.tasks.len() - 1);
+    }
+
+    // polls tasks until every one has finished or all are waiting on
+    // something that will never happen
+    pub fn run(&mut self) {
+        loop {
+            let id = match self.ready.lock().unwrap().pop_front() {
+                Some(id) => id,
+                None => return
+            };
+
+            let waker = Waker::from(Arc::new(TaskWaker { id: id, ready: self.ready.clone() }));
+            let mut cx = Context::from_waker(&waker);
+
+            let finished = match self.tasks[id].as_mut() {
+                Some(task) => task.as_mut().poll(&mut cx).is_ready(),
+                None => false
+            };
+
+            if finished {
+                self.tasks[id] = None;
+            }
+        }
+    }
+
+    // drives a single future that may borrow from the caller, alongside any
+    // spawned tasks
+    pub fn run_until<F: Future>(&mut self, future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let main_ready = Arc::new(Mutex::new(VecDeque::new()));
+        let waker = Waker::from(Arc::new(TaskWaker { id: 0, ready: main_ready.clone() }));
+        let mut cx = Context::from_waker(&waker);
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+
+            self.run();
+
+            if main_ready.lock().unwrap().drain(..).count() == 0 {
+                panic!("future can never complete: nothing left to wake it");
+            }
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::cmp;
+use std::vec::Vec;
+
+#[derive(PartialEq, Debug)]
+pub struct Reproduction<T> {
+    pub program: Vec<T>,
+    pub inputs: Vec<T>,
+    pub predicate_runs: usize
+}
+
+// shrinks a failing program and its input sequence while `fails` keeps holding.
+// words and inputs are first removed in chunks (ddmin), then surviving words
+// are simplified to their default value one at a time.
+pub fn minimize<T, F>(program: Vec<T>, inputs: Vec<T>, fails: F) -> Reproduction<T> where
+    T: Clone + Default + PartialEq,
+    F: Fn(&[T], &[T]) -> bool {
+
+    let mut runs = 0;
+    let mut fails = |program: &[T], inputs: &[T]| {
+        runs += 1;
+        fails(program, inputs)
+    };
+
+    if !fails(&program, &inputs) {
+        panic!("failure predicate does not hold for the original program");
+    }
+
+    let mut program = program;
+    let mut inputs = inputs;
+
+    loop {
+        let before = (program.len(), inputs.len());
+
+        program = ddmin(program, |candidate| fails(candidate, &inputs));
+        inputs = ddmin(inputs, |candidate| fails(&program, candidate));
+
+        let simplified = simplify(&mut program, |candidate| fails(candidate, &inputs)) +
+            simplify(&mut inputs, |candidate| fails(&program, candidate));
+
+        if simplified == 0 && before == (program.len(), inputs.len()) {
+            break;
+        }
+    }
+
+    Reproduction {
+        program: program,
+        inputs: inputs,
+        predicate_runs: runs
+    }
+}
+
+fn ddmin<T, F>(mut items: Vec<T>, mut test: F) -> Vec<T> where
+    T: Clone,
+    F: FnMut(&[T]) -> bool {
+
+    let mut granularity = 2;
+
+    while items.len() >= 2 {
+        let chunk_size = items.len().div_ceil(granularity);
+        let mut reduced = false;
+
+        // try each chunk on its own first, then everything but the chunk
+        for start in (0..items.len()).step_by(chunk_size) {
+            let end = cmp::min(start + chunk_size, items.len());
+
+            let subset = items[start..end].to_vec();
+            if test(&subset) {
+                items = subset;
+                granularity = 2;
+                reduced = true;
+                break;
+            }
+
+            let mut complement = items[..start].to_vec();
+            complement.extend_from_slice(&items[end..]);
+            if test(&complement) {
+                items = complement;
+                granularity = cmp::max(granularity - 1, 2);
+                reduced = true;
+                break;
+            }
+        }
+
+        if !reduced {
+            if granularity >= items.len() {
+                break;
+            }
+
+            granularity = cmp::min(granularity * 2, items.len());
+        }
+    }
+
+    if items.len() == 1 && test(&[]) {
+        items.clear();
+    }
+
+    items
+}
+
+fn simplify<T, F>(items: &mut Vec<T>, mut test: F) -> usize where
+    T: Clone + Default + PartialEq,
+    F: FnMut(&[T]) -> bool {
+
+    let mut simplified = 0;
+
+    for i in 0..items.len() {
+        if items[i] == T::default() {
+            continue;
+        }
+
+        let mut candidate = items.to_vec();
+        candidate[i] = T::default();
+
+        if test(&candidate) {
+            *items = candidate;
+            simplified += 1;
+        }
+    }
+
+    simplified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ddmin_single_culprit() {
+        let items: Vec<i32> = (0..40).collect();
+
+        let reduced = ddmin(items, |c| c.contains(&17));
+
+        assert_eq!(reduced, vec!(17));
+    }
+
+    #[test]
+    fn ddmin_pair_of_culprits() {
+        let items: Vec<i32> = (0..40).collect();
+
+        let reduced = ddmin(items, |c| c.contains(&3) && c.contains(&31));
+
+        assert_eq!(reduced, vec!(3, 31));
+    }
+
+    #[test]
+    fn minimize_program_and_inputs() {
+        // fails whenever a 99 comes straight after a 4 and some input is negative
+        let program = vec!(1, 0, 0, 3, 4, 99, 2, 5, 6, 0, 99);
+        let inputs = vec!(7, 3, -2, 8);
+
+        let fails = |p: &[i32], i: &[i32]| {
+            p.windows(2).any(|w| w == [4, 99]) && i.iter().any(|&n| n < 0)
+        };
+
+        let repro = minimize(program, inputs, fails);
+
+        assert_eq!(repro.program, vec!(4, 99));
+        assert_eq!(repro.inputs, vec!(-2));
+        assert!(repro.predicate_runs > 0);
+    }
+
+    #[test]
+    fn minimize_zeroes_irrelevant_words() {
+        // length matters here, so words can only be simplified, not removed
+        let program = vec!(5, 6, 7, 8);
+
+        let fails = |p: &[i32], _: &[i32]| p.len() == 4 && p[2] == 7;
+
+        let repro = minimize(program, Vec::new(), fails);
+
+        assert_eq!(repro.program, vec!(0, 0, 7, 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn minimize_requires_failure() {
+        minimize(vec!(99), Vec::new(), |_: &[i32], _: &[i32]| false);
+    }
+}
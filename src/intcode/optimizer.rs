@@ -0,0 +1,632 @@
+use std::collections::{ BTreeMap, BTreeSet };
+use std::vec::Vec;
+
+use super::Word;
+use super::cfg::{ self, Exit };
+use super::disasm::Decoded;
+use super::engine::{ self, Termination };
+use super::instruction::{ Instruction, Mode, Opcode };
+use super::machine::Machine;
+
+const STEP_LIMIT: usize = 1_000_000;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Optimization {
+    pub program: Vec<Word>,
+    pub folded: usize,
+    pub threaded: usize,
+    pub removed: usize,
+    // why nothing, or nothing more than in-place rewrites, was done
+    pub refused: Option<String>
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Sample {
+    // cells set before the run, like day 2's noun and verb
+    pub patches: Vec<(usize, Word)>,
+    pub inputs: Vec<Word>
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Divergence {
+    pub sample: Sample,
+    pub original: (Vec<Word>, Termination),
+    pub optimized: (Vec<Word>, Termination)
+}
+
+fn unchanged(program: &[Word], reason: &str) -> Optimization {
+    Optimization {
+        program: program.to_vec(),
+        folded: 0,
+        threaded: 0,
+        removed: 0,
+        refused: Some(String::from(reason))
+    }
+}
+
+fn words(decoded: &Decoded) -> std::ops::Range<usize> {
+    decoded.address..decoded.next()
+}
+
+fn is_read(decoded: &Decoded, param_i: usize) -> bool {
+    decoded.instr.opcode.dest_param() != Some(param_i) &&
+        !(decoded.instr.opcode.is_jump() && param_i == 1)
+}
+
+fn write_back(program: &mut [Word], decoded: &Decoded) {
+    program[decoded.address] = decoded.instr.encode();
+
+    for (param_i, &value) in decoded.params.iter().enumerate() {
+        program[decoded.address + 1 + param_i] = value;
+    }
+}
+
+fn unconditional(decoded: &Decoded) -> bool {
+    decoded.instr.opcode.is_jump() &&
+        decoded.instr.modes[0] == Mode::Immediate &&
+        (decoded.params[0] != 0) == (decoded.instr.opcode == Opcode::JumpIfTrue)
+}
+
+fn never_taken(decoded: &Decoded) -> bool {
+    decoded.instr.opcode.is_jump() &&
+        decoded.instr.modes[0] == Mode::Immediate &&
+        !unconditional(decoded)
+}
+
+// does nothing but move the instruction pointer on. cells outside the
+// program may not exist, and touching them faults
+fn is_noop(decoded: &Decoded, len: usize) -> bool {
+    let (modes, params) = (&decoded.instr.modes, &decoded.params);
+    let same_cell = |param_i: usize| modes[param_i] == Mode::Position && modes[2] == Mode::Position &&
+        params[param_i] == params[2] && params[2] >= 0 && (params[2] as usize) < len;
+    let constant = |param_i: usize, value: Word| modes[param_i] == Mode::Immediate && params[param_i] == value;
+
+    match decoded.instr.opcode {
+        Opcode::Add => (same_cell(0) && constant(1, 0)) || (same_cell(1) && constant(0, 0)),
+        Opcode::Mul => (same_cell(0) && constant(1, 1)) || (same_cell(1) && constant(0, 1)),
+        op if op.is_jump() => never_taken(decoded) ||
+            (modes[0] == Mode::Immediate && modes[1] == Mode::Immediate && params[1] == decoded.next() as Word),
+        _ => false
+    }
+}
+
+fn fold(decoded: &Decoded) -> Option<Word> {
+    if decoded.instr.modes[0] != Mode::Immediate || decoded.instr.modes[1] != Mode::Immediate {
+        return None;
+    }
+
+    let (left, right) = (decoded.params[0], decoded.params[1]);
+
+    match decoded.instr.opcode {
+        Opcode::Add => left.checked_add(right),
+        Opcode::Mul => left.checked_mul(right),
+        Opcode::LessThan => Some((left < right) as Word),
+        Opcode::Equals => Some((left == right) as Word),
+        _ => None
+    }
+}
+
+// rewrites a program into an equivalent one that runs fewer or cheaper
+// instructions. `pinned` cells are read or patched from outside the run and
+// keep both their address and meaning.
+pub fn optimize(program: &[Word], pinned: &[usize]) -> Optimization {
+    let mut optimization = optimize_once(program, pinned);
+
+    // removing instructions can leave a jump aimed at the very next one, so
+    // go round again until nothing changes
+    loop {
+        let next = optimize_once(&optimization.program, pinned);
+
+        if next.program == optimization.program {
+            return optimization;
+        }
+
+        optimization = Optimization {
+            program: next.program,
+            folded: optimization.folded + next.folded,
+            threaded: optimization.threaded + next.threaded,
+            removed: optimization.removed + next.removed,
+            refused: next.refused
+        };
+    }
+}
+
+fn optimize_once(program: &[Word], pinned: &[usize]) -> Optimization {
+    let cfg = cfg::build(program);
+
+    if cfg.blocks.values().any(|block| block.exit == Exit::Dynamic) {
+        return unchanged(program, "jumps to runtime-computed targets");
+    }
+
+    let mut instrs: BTreeMap<usize, Decoded> = BTreeMap::new();
+    for decoded in cfg.blocks.values().flat_map(|block| block.instrs.iter()) {
+        instrs.insert(decoded.address, decoded.clone());
+    }
+
+    let mut written: BTreeSet<usize> = pinned.iter().cloned().collect();
+    let mut relative = false;
+
+    for decoded in instrs.values() {
+        for param_i in 0..decoded.params.len() {
+            let value = decoded.params[param_i];
+
+            match decoded.instr.modes[param_i] {
+                Mode::Relative if !is_read(decoded, param_i) => {
+                    return unchanged(program, "writes through the relative base");
+                },
+                Mode::Relative => relative = true,
+                Mode::Position if !is_read(decoded, param_i) && value >= 0 => { written.insert(value as usize); },
+                _ => ()
+            }
+        }
+
+        relative |= decoded.instr.opcode == Opcode::AdjustBase;
+    }
+
+    // a write into the word a path runs aground on can turn it into code
+    // nobody has looked at
+    let aground = cfg.blocks.values()
+        .filter(|block| block.exit == Exit::Invalid)
+        .any(|block| (block.end..block.end + 4).any(|addr| written.contains(&addr)));
+
+    if aground {
+        return unchanged(program, "writes into words that don't decode yet");
+    }
+
+    // an instruction cut short by the end of the program still runs, on
+    // operands nobody has looked at
+    let truncated = cfg.blocks.values()
+        .filter(|block| block.exit == Exit::Invalid)
+        .any(|block| program.get(block.end).is_some_and(|&word| Instruction::decode(word).is_ok()));
+
+    if truncated {
+        return unchanged(program, "runs off the end of the program");
+    }
+
+    // a relative read can land on any cell, instruction words included, so
+    // not even the in-place rewrites below are safe
+    if relative {
+        return unchanged(program, "reads through the relative base");
+    }
+
+    // an instruction whose opcode is overwritten can run as something else
+    // entirely afterwards. only the entry block runs just once, so only there
+    // can the write safely come after the instruction it changes
+    let entry_end = cfg.blocks.get(&0).map_or(0, |block| block.end);
+    let runs_once = cfg.predecessors(0).is_empty();
+
+    for writer in instrs.values() {
+        for param_i in 0..writer.params.len() {
+            let cell = writer.params[param_i];
+
+            if writer.instr.modes[param_i] != Mode::Position || is_read(writer, param_i) || cell < 0 ||
+                !instrs.contains_key(&(cell as usize)) {
+                continue;
+            }
+
+            if !(runs_once && (cell as usize) < entry_end && writer.address >= cell as usize) {
+                return unchanged(program, "overwrites its own opcodes");
+            }
+        }
+    }
+
+    // self-modified instructions are left exactly as they are
+    let touched: BTreeSet<usize> = instrs.values()
+        .filter(|decoded| words(decoded).any(|addr| written.contains(&addr)))
+        .map(|decoded| decoded.address)
+        .collect();
+
+    for &addr in touched.iter() {
+        let decoded = &instrs[&addr];
+
+        for param_i in 0..decoded.params.len() {
+            if is_read(decoded, param_i) && written.contains(&(decoded.address + 1 + param_i)) {
+                return unchanged(program, "reads from runtime-computed addresses");
+            }
+        }
+    }
+
+    // words run as part of more than one instruction, like an operand a jump
+    // lands on, stay put since rewriting them for one changes the other
+    let mut covered = BTreeSet::new();
+    let mut shared = BTreeSet::new();
+    for decoded in instrs.values() {
+        shared.extend(words(decoded).filter(|&addr| !covered.insert(addr)));
+    }
+    shared.extend(cfg.blocks.values()
+        .filter(|block| block.exit == Exit::Invalid && covered.contains(&block.end))
+        .map(|block| block.end));
+
+    // as do the cells kept instructions read, which keeps any instruction
+    // those cells are part of, and so on
+    let mut observed = shared.clone();
+    let mut kept = touched.clone();
+    loop {
+        for addr in kept.iter() {
+            let decoded = &instrs[addr];
+
+            observed.extend((0..decoded.params.len())
+                .filter(|&param_i| decoded.instr.modes[param_i] == Mode::Position && decoded.params[param_i] >= 0)
+                .map(|param_i| decoded.params[param_i] as usize));
+        }
+
+        let more: Vec<usize> = instrs.values()
+            .filter(|decoded| !kept.contains(&decoded.address) && words(decoded).any(|addr| observed.contains(&addr)))
+            .map(|decoded| decoded.address)
+            .collect();
+
+        if more.is_empty() {
+            break;
+        }
+        kept.extend(more);
+    }
+
+    let rewritable = |decoded: &Decoded| !kept.contains(&decoded.address);
+
+    let mut out = program.to_vec();
+    let mut folded = 0;
+    let mut threaded = 0;
+
+    // constant propagation and folding
+    for decoded in instrs.values_mut().filter(|decoded| rewritable(decoded)) {
+        for param_i in 0..decoded.params.len() {
+            let cell = decoded.params[param_i];
+
+            // reads outside the program are left to fault, or not, at runtime
+            if decoded.instr.modes[param_i] == Mode::Position && is_read(decoded, param_i) &&
+                cell >= 0 && (cell as usize) < program.len() && !written.contains(&(cell as usize)) {
+
+                decoded.instr.modes[param_i] = Mode::Immediate;
+                decoded.params[param_i] = program[cell as usize];
+            }
+        }
+
+        if let Some(value) = fold(decoded) {
+            let is_already_folded = decoded.instr.opcode == Opcode::Add && decoded.params[1] == 0;
+
+            decoded.instr = Instruction::new(Opcode::Add, [Mode::Immediate, Mode::Immediate, decoded.instr.modes[2]]);
+            decoded.params[0] = value;
+            decoded.params[1] = 0;
+
+            if !is_already_folded {
+                folded += 1;
+            }
+        }
+
+        if unconditional(decoded) {
+            decoded.instr = Instruction::new(Opcode::JumpIfTrue, [Mode::Immediate, decoded.instr.modes[1], Mode::Position]);
+            decoded.params[0] = 1;
+        }
+
+        write_back(&mut out, decoded);
+    }
+
+    // jump threading: skip over chains of unconditional jumps
+    let targets: Vec<usize> = instrs.keys().cloned().collect();
+    for addr in targets {
+        let decoded = instrs[&addr].clone();
+
+        if !decoded.instr.opcode.is_jump() || decoded.instr.modes[1] != Mode::Immediate ||
+            never_taken(&decoded) || !rewritable(&decoded) {
+            continue;
+        }
+
+        let mut target = decoded.params[1];
+        let mut seen = BTreeSet::new();
+
+        while let Some(next) = instrs.get(&(target as usize)) {
+            if !unconditional(next) || next.instr.modes[1] != Mode::Immediate ||
+                !rewritable(next) || !seen.insert(target) {
+                break;
+            }
+
+            target = next.params[1];
+        }
+
+        if target != decoded.params[1] {
+            let mut decoded = decoded;
+            decoded.params[1] = target;
+
+            write_back(&mut out, &decoded);
+            instrs.insert(addr, decoded);
+            threaded += 1;
+        }
+    }
+
+    let mut optimization = Optimization {
+        program: out,
+        folded: folded,
+        threaded: threaded,
+        removed: 0,
+        refused: None
+    };
+
+    // removing instructions moves everything after them, which is only safe
+    // when every address in the program is a literal we can rewrite
+    if !touched.is_empty() {
+        optimization.refused = Some(String::from("self-modifying, so no instructions were removed"));
+    } else if !shared.is_empty() {
+        optimization.refused = Some(String::from("runs words as more than one instruction, so no instructions were removed"));
+    } else {
+        let first_movable = pinned.iter().map(|&cell| cell + 1).max().unwrap_or(0);
+        let removable: BTreeSet<usize> = instrs.values()
+            .filter(|decoded| decoded.address >= first_movable && rewritable(decoded) &&
+                is_noop(decoded, optimization.program.len()))
+            .map(|decoded| decoded.address)
+            .collect();
+
+        compact(&mut optimization, &instrs, &removable);
+    }
+
+    optimization
+}
+
+fn compact(optimization: &mut Optimization, instrs: &BTreeMap<usize, Decoded>, removable: &BTreeSet<usize>) {
+    let program = &optimization.program;
+
+    // no-op instructions whose words are read as data have to stay
+    let referenced: BTreeSet<usize> = instrs.values()
+        .flat_map(|decoded| decoded.params.iter().zip(decoded.instr.modes.iter())
+            .filter(|(&value, &mode)| mode == Mode::Position && value >= 0)
+            .map(|(&value, _)| value as usize)
+            .collect::<Vec<usize>>())
+        .collect();
+
+    let removed: Vec<&Decoded> = removable.iter()
+        .map(|addr| &instrs[addr])
+        .filter(|decoded| words(decoded).all(|addr| !referenced.contains(&addr)))
+        .collect();
+
+    if removed.is_empty() {
+        return;
+    }
+
+    let mut keep = vec!(true; program.len());
+    for decoded in removed.iter() {
+        for addr in words(decoded) {
+            keep[addr] = false;
+        }
+    }
+
+    // old address -> new address; removed words map to whatever follows them
+    let mut new_addr = vec!(0; program.len());
+    let mut shift = 0;
+    for addr in 0..program.len() {
+        new_addr[addr] = addr - shift;
+        if !keep[addr] {
+            shift += 1;
+        }
+    }
+
+    // cells past the end are only ever zero to start with, so they can stay
+    // where they are. moving them would change the address a fault reports
+    let relocate = |addr: Word| -> Word {
+        if addr < 0 || addr as usize >= program.len() {
+            addr
+        } else {
+            new_addr[addr as usize] as Word
+        }
+    };
+
+    let mut relocated = program.to_vec();
+    for decoded in instrs.values() {
+        let mut decoded = decoded.clone();
+
+        for param_i in 0..decoded.params.len() {
+            let is_target = decoded.instr.opcode.is_jump() && param_i == 1;
+
+            if decoded.instr.modes[param_i] == Mode::Position || is_target {
+                decoded.params[param_i] = relocate(decoded.params[param_i]);
+            }
+        }
+
+        write_back(&mut relocated, &decoded);
+    }
+
+    optimization.program = relocated.into_iter()
+        .enumerate()
+        .filter(|&(addr, _)| keep[addr])
+        .map(|(_, word)| word)
+        .collect();
+    optimization.removed = removed.len();
+}
+
+fn run_sample(program: &[Word], sample: &Sample) -> (Vec<Word>, Termination, Vec<Word>) {
+    let mut program = program.to_vec();
+    for &(cell, value) in sample.patches.iter() {
+        if cell >= program.len() {
+            program.resize(cell + 1, 0);
+        }
+        program[cell] = value;
+    }
+
+    let mut machine = Machine::new(program);
+    machine.extend_input(&sample.inputs);
+
+    let (outputs, termination) = engine::run_machine(&mut machine, STEP_LIMIT);
+
+    (outputs, termination, machine.memory().to_vec())
+}
+
+// runs both programs on every sample, comparing outputs, how they stopped and
+// the final value of each pinned cell
+pub fn verify(original: &[Word], optimized: &[Word], pinned: &[usize], samples: &[Sample]) -> Result<(), Box<Divergence>> {
+    for sample in samples.iter() {
+        let (out_a, end_a, mem_a) = run_sample(original, sample);
+        let (out_b, end_b, mem_b) = run_sample(optimized, sample);
+
+        let pinned_eq = pinned.iter().all(|&cell| mem_a.get(cell) == mem_b.get(cell));
+
+        if out_a != out_b || end_a != end_b || !pinned_eq {
+            return Err(Box::new(Divergence {
+                sample: sample.clone(),
+                original: (out_a, end_a),
+                optimized: (out_b, end_b)
+            }));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::engine::Dialect;
+    use crate::intcode::fuzz;
+    use crate::intcode::rng::Rng;
+
+    fn inputs(values: &[Word]) -> Sample {
+        Sample { patches: Vec::new(), inputs: values.to_vec() }
+    }
+
+    #[test]
+    fn folds_constants() {
+        // [15] = [13] * [14], [15] = [15] + [16], output [15]
+        let program = vec!(2, 13, 14, 15, 1, 15, 16, 15, 4, 15, 99, 0, 0, 3, 4, 0, 5);
+
+        let optimization = optimize(&program, &[]);
+
+        assert_eq!(optimization.folded, 1);
+        assert_eq!(&optimization.program[..8], &[1101, 12, 0, 15, 1001, 15, 5, 15]);
+        assert!(verify(&program, &optimization.program, &[], &[inputs(&[])]).is_ok());
+    }
+
+    #[test]
+    fn removes_noops_and_threads_jumps() {
+        // 0: jump to next, 3: add [16], 0 -> [16], 7: jump to 10,
+        // 10: jump to next, 13: output [16], 15: halt
+        let program = vec!(1105, 1, 3, 1001, 16, 0, 16, 1105, 1, 10, 1105, 1, 13, 4, 16, 99, 7);
+
+        let optimization = optimize(&program, &[]);
+
+        assert!(optimization.refused.is_none());
+        assert_eq!(optimization.threaded, 1);
+        // the jump at 7 only lands on the next instruction once 10 is gone,
+        // and goes on the second pass. the third then sees the output reads
+        // a cell nothing writes
+        assert_eq!(optimization.removed, 4);
+        assert_eq!(optimization.program, vec!(104, 7, 99, 7));
+
+        assert!(verify(&program, &optimization.program, &[], &[inputs(&[])]).is_ok());
+    }
+
+    #[test]
+    fn leaves_self_modified_code() {
+        // the day 2 example writes into the operands of its first instruction
+        let program = vec!(1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50);
+
+        let optimization = optimize(&program, &[0]);
+
+        assert_eq!(&optimization.program[..4], &program[..4]);
+        assert_eq!(&optimization.program[4..8], &[1002, 3, 50, 0]);
+        assert_eq!(optimization.refused.as_ref().unwrap(), "self-modifying, so no instructions were removed");
+
+        let samples = [inputs(&[])];
+        assert!(verify(&program, &optimization.program, &[0], &samples).is_ok());
+    }
+
+    #[test]
+    fn refuses_unknown_read_addresses() {
+        // noun and verb pick which cells the first instruction reads
+        let program = vec!(1, 0, 0, 3, 1101, 2, 3, 9, 99, 0);
+
+        let optimization = optimize(&program, &[0, 1, 2]);
+
+        assert_eq!(optimization.program, program);
+        assert_eq!(optimization.refused.as_ref().unwrap(), "reads from runtime-computed addresses");
+    }
+
+    #[test]
+    fn refuses_relative_writes() {
+        let optimization = optimize(&[109, 5, 21101, 1, 1, 0, 99], &[]);
+
+        assert_eq!(optimization.refused.as_ref().unwrap(), "writes through the relative base");
+    }
+
+    #[test]
+    fn refuses_relative_reads() {
+        // the output reads back the first operand of the add, which constant
+        // propagation would otherwise replace with the value of [9]
+        let program = vec!(109, 2, 1, 9, 10, 11, 204, 1, 99, 7, 8, 0);
+
+        let optimization = optimize(&program, &[]);
+
+        assert_eq!(optimization.program, program);
+        assert_eq!(optimization.refused.as_ref().unwrap(), "reads through the relative base");
+    }
+
+    #[test]
+    fn refuses_writes_into_undecoded_words() {
+        // [4] starts as 0, which doesn't decode, until the first instruction
+        // makes it an add
+        let program = vec!(1107, 0, 9, 4, 0, 0, 0, 8);
+
+        let optimization = optimize(&program, &[]);
+
+        assert_eq!(optimization.program, program);
+        assert_eq!(optimization.refused.as_ref().unwrap(), "writes into words that don't decode yet");
+    }
+
+    #[test]
+    fn refuses_overwritten_opcodes() {
+        // the add at 4 turns the equals at 8 into something else
+        let program = vec!(4, 0, 104, 0, 1, 8, 0, 8, 8, 7, 7, 317);
+
+        let optimization = optimize(&program, &[]);
+
+        assert_eq!(optimization.program, program);
+        assert_eq!(optimization.refused.as_ref().unwrap(), "overwrites its own opcodes");
+    }
+
+    #[test]
+    fn refuses_instructions_cut_short() {
+        // the jump lands on an add whose operands lie past the end
+        let program = vec!(1105, 1, 3, 1);
+
+        let optimization = optimize(&program, &[]);
+
+        assert_eq!(optimization.program, program);
+        assert_eq!(optimization.refused.as_ref().unwrap(), "runs off the end of the program");
+    }
+
+    #[test]
+    fn keeps_words_shared_by_instructions() {
+        // the never taken jump still names 3, so the second output's operand
+        // is also an instruction. that output stays, and so does the cell it
+        // reads, which is the first output's opcode
+        let program = vec!(4, 0, 4, 0, 1105, 0, 3);
+
+        let optimization = optimize(&program, &[]);
+
+        assert_eq!(optimization.program, program);
+        assert_eq!(optimization.refused.as_ref().unwrap(), "runs words as more than one instruction, so no instructions were removed");
+        assert!(verify(&program, &optimization.program, &[], &[inputs(&[])]).is_ok());
+    }
+
+    #[test]
+    fn patches_past_the_end() {
+        let sample = Sample { patches: vec!((6, 42)), inputs: Vec::new() };
+        let (outputs, termination, memory) = run_sample(&[4, 6, 99], &sample);
+
+        assert_eq!(outputs, vec!(42));
+        assert_eq!(termination, Termination::Halted);
+        assert_eq!(memory.len(), 7);
+    }
+
+    #[test]
+    fn equivalent_on_generated_programs() {
+        let mut rng = Rng::new(31);
+
+        for i in 0..300 {
+            let dialect = if i % 2 == 0 { Dialect::Day2 } else { Dialect::Day5 };
+            let case = fuzz::generate_valid(&mut rng, dialect);
+            let optimization = optimize(&case.program, &[]);
+
+            let samples = [inputs(&case.inputs)];
+            let result = verify(&case.program, &optimization.program, &[], &samples);
+
+            assert!(result.is_ok(), "{:?} -> {:?}", case.program, optimization.program);
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use std::io::{ BufRead, Read, Write };
+use std::time::Instant;
+use std::vec::Vec;
+
+use super::Word;
+use super::engine::Termination;
+use super::machine::{ Event, Machine };
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    Input,
+    Output
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Entry {
+    pub elapsed_ms: u64,
+    pub kind: Kind,
+    pub value: Word
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Transcript {
+    pub entries: Vec<Entry>
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Mismatch {
+    // position in the event sequence where the runs part ways
+    pub position: usize,
+    pub expected: Option<(Kind, Word)>,
+    pub actual: Option<(Kind, Word)>
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Replay {
+    pub mismatches: Vec<Mismatch>,
+    pub termination: Termination
+}
+
+impl Kind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Kind::Input => "in",
+            Kind::Output => "out"
+        }
+    }
+
+    fn parse(kind: &str) -> Option<Kind> {
+        match kind {
+            "in" => Some(Kind::Input),
+            "out" => Some(Kind::Output),
+            _ => None
+        }
+    }
+}
+
+impl Transcript {
+    pub fn inputs(&self) -> Vec<Word> {
+        self.values(Kind::Input)
+    }
+
+    pub fn outputs(&self) -> Vec<Word> {
+        self.values(Kind::Output)
+    }
+
+    fn values(&self, kind: Kind) -> Vec<Word> {
+        self.entries.iter()
+            .filter(|entry| entry.kind == kind)
+            .map(|entry| entry.value)
+            .collect()
+    }
+
+    // one `elapsed_ms,kind,value` row per event
+    pub fn write_csv<W: Write>(&self, writer: W) -> csv::Result<()> {
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(writer);
+
+        for entry in self.entries.iter() {
+            writer.write_record(&[entry.elapsed_ms.to_string(), String::from(entry.kind.as_str()), entry.value.to_string()])?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    pub fn read_csv<R: Read>(reader: R) -> Result<Transcript, String> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(reader);
+
+        let mut entries = Vec::new();
+
+        for (line, record) in reader.records().enumerate() {
+            let record = record.map_err(|err| err.to_string())?;
+            let bad = || format!("bad transcript entry on line {}", line + 1);
+
+            if record.len() != 3 {
+                return Err(bad());
+            }
+
+            entries.push(Entry {
+                elapsed_ms: record[0].parse().map_err(|_| bad())?,
+                kind: Kind::parse(&record[1]).ok_or_else(bad)?,
+                value: record[2].parse().map_err(|_| bad())?
+            });
+        }
+
+        Ok(Transcript { entries: entries })
+    }
+}
+
+// reads one number per line, skipping blank lines; stops at the first line
+// that isn't a number or at end of input
+pub fn line_input<R: BufRead>(reader: R) -> impl FnMut() -> Option<Word> {
+    let mut lines = reader.lines();
+
+    move || {
+        loop {
+            let line = lines.next()?.ok()?;
+            let line = line.trim();
+
+            if !line.is_empty() {
+                return line.parse().ok();
+            }
+        }
+    }
+}
+
+// runs the machine, asking next_input whenever it blocks and passing each
+// output on, while recording both with timestamps
+pub fn record<I, O>(machine: &mut Machine, mut next_input: I, mut on_output: O, step_limit: usize) -> (Transcript, Termination) where
+    I: FnMut() -> Option<Word>,
+    O: FnMut(Word) {
+
+    let start = Instant::now();
+    let mut transcript = Transcript::default();
+
+    let mut log = |kind: Kind, value: Word| {
+        transcript.entries.push(Entry {
+            elapsed_ms: start.elapsed().as_millis() as u64,
+            kind: kind,
+            value: value
+        });
+    };
+
+    let termination = loop {
+        if machine.steps() >= step_limit {
+            break Termination::StepLimit;
+        }
+
+        match machine.step() {
+            Ok(None) => (),
+            Ok(Some(Event::Output(value))) => {
+                log(Kind::Output, value);
+                on_output(value);
+            },
+            Ok(Some(Event::NeedInput)) => match next_input() {
+                Some(value) => {
+                    log(Kind::Input, value);
+                    machine.push_input(value);
+                },
+                None => break Termination::InputExhausted
+            },
+            Ok(Some(Event::Halted)) => break Termination::Halted,
+            Err(err) => break Termination::Error(err.kind)
+        }
+    };
+
+    (transcript, termination)
+}
+
+pub fn record_scripted(program: &[Word], inputs: &[Word], step_limit: usize) -> (Transcript, Termination) {
+    let mut machine = Machine::new(program.to_vec());
+    let mut inputs = inputs.iter().cloned();
+
+    record(&mut machine, || inputs.next(), |_| (), step_limit)
+}
+
+// feeds the transcript's inputs to program and compares the events it
+// produces against the recorded ones
+pub fn replay(program: &[Word], transcript: &Transcript, step_limit: usize) -> Replay {
+    let (actual, termination) = record_scripted(program, &transcript.inputs(), step_limit);
+
+    let expected: Vec<(Kind, Word)> = transcript.entries.iter().map(|e| (e.kind, e.value)).collect();
+    let actual: Vec<(Kind, Word)> = actual.entries.iter().map(|e| (e.kind, e.value)).collect();
+
+    let mismatches = (0..expected.len().max(actual.len()))
+        .filter(|&i| expected.get(i) != actual.get(i))
+        .map(|i| Mismatch {
+            position: i,
+            expected: expected.get(i).cloned(),
+            actual: actual.get(i).cloned()
+        })
+        .collect();
+
+    Replay {
+        mismatches: mismatches,
+        termination: termination
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // outputs 999, 1000 or 1001 as the input is below, equal to or above 8
+    const DAY5_EXAMPLE: [Word; 47] = [3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31,
+        1106, 0, 36, 98, 0, 0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104,
+        999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20, 1105, 1, 46, 98, 99];
+
+    #[test]
+    fn record_from_lines() {
+        let mut machine = Machine::new(DAY5_EXAMPLE.to_vec());
+        let mut printed = Vec::new();
+
+        let input = line_input("\n  9\n".as_bytes());
+        let (transcript, termination) = record(&mut machine, input, |v| printed.push(v), 10_000);
+
+        assert_eq!(termination, Termination::Halted);
+        assert_eq!(printed, vec!(1001));
+        assert_eq!(transcript.inputs(), vec!(9));
+        assert_eq!(transcript.outputs(), vec!(1001));
+        assert_eq!(transcript.entries[0].kind, Kind::Input);
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let (transcript, _) = record_scripted(&DAY5_EXAMPLE, &[8], 10_000);
+
+        let mut buffer = Vec::new();
+        transcript.write_csv(&mut buffer).unwrap();
+
+        let text = String::from_utf8(buffer.to_vec()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].ends_with(",in,8"));
+        assert!(lines[1].ends_with(",out,1000"));
+
+        assert_eq!(Transcript::read_csv(&buffer[..]).unwrap(), transcript);
+        assert!(Transcript::read_csv("0,sideways,3\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn replay_same_program() {
+        let (transcript, _) = record_scripted(&DAY5_EXAMPLE, &[7], 10_000);
+
+        let replay = replay(&DAY5_EXAMPLE, &transcript, 10_000);
+
+        assert!(replay.mismatches.is_empty());
+        assert_eq!(replay.termination, Termination::Halted);
+    }
+
+    #[test]
+    fn replay_modified_program() {
+        let (transcript, _) = record_scripted(&DAY5_EXAMPLE, &[7], 10_000);
+
+        // report 998 instead of 999 for inputs below 8
+        let mut modified = DAY5_EXAMPLE.to_vec();
+        modified[32] = 998;
+
+        let replay = replay(&modified, &transcript, 10_000);
+
+        let expected = vec!(Mismatch {
+            position: 1,
+            expected: Some((Kind::Output, 999)),
+            actual: Some((Kind::Output, 998))
+        });
+        assert_eq!(replay.mismatches, expected);
+    }
+
+    #[test]
+    fn replay_reports_missing_events() {
+        let (transcript, _) = record_scripted(&[3, 0, 4, 0, 4, 0, 99], &[5], 10_000);
+
+        let replay = replay(&[3, 0, 4, 0, 99], &transcript, 10_000);
+
+        assert_eq!(replay.mismatches.len(), 1);
+        assert_eq!(replay.mismatches[0].actual, None);
+    }
+}
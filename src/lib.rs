@@ -0,0 +1,9 @@
+extern crate alloc;
+
+pub mod util;
+pub mod intcode;
+pub mod day1;
+pub mod day2;
+pub mod day3;
+pub mod day5;
+pub mod day7;
@@ -1,8 +1,13 @@
-#[allow(dead_code)]
+use std::env;
 
-mod util;
-mod day7;
+use advent2019::{ day7, intcode };
 
 fn main() {
+    // `dap` turns the binary into an Intcode debug adapter speaking over stdio
+    if env::args().nth(1).as_deref() == Some("dap") {
+        intcode::dap::serve_stdio().expect("debug adapter failed");
+        return;
+    }
+
     day7::solve();
 }
@@ -1,5 +1,4 @@
 #[allow(dead_code)]
-
 use std::fs::File;
 use std::io::BufReader;
 use std::path::PathBuf;
@@ -16,13 +15,18 @@ fn find_data_path(day: u32, dir: &str, ext: &str) -> String {
 pub fn get_input(day: u32) -> File {
     let path = find_data_path(day, "./data", ".txt");
 
-    File::open(&path).expect(&format!("unable to open input file: {:?}", &path))
+    File::open(&path).unwrap_or_else(|_| panic!("unable to open input file: {:?}", &path))
 }
 
 pub fn get_test_input(day: u32) -> File {
     let path = find_data_path(day, "./data", ".test");
 
-    File::open(&path).expect(&format!("unable to open input file: {:?}", &path))
+    File::open(&path).unwrap_or_else(|_| panic!("unable to open input file: {:?}", &path))
+}
+
+// an input in some other format, if there is one
+pub fn find_input(day: u32, ext: &str) -> Option<File> {
+    File::open(find_data_path(day, "./data", ext)).ok()
 }
 
 pub fn get_input_line_reader(day: u32) -> BufReader<File> {